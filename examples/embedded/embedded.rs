@@ -8,6 +8,8 @@ use cortex_m_rt::entry;
 use embedded_alloc::Heap;
 
 use delharc::*;
+use delharc::crc::Crc16;
+use delharc::decode::{Decoder, Lh1Decoder, Lh1Workspace, Lh7Decoder, Lh7Workspace};
 
 #[no_mangle]
 #[global_allocator]
@@ -57,8 +59,29 @@ fn extract_check<R: Read, P: AsRef<str>>(
     Ok(false)
 }
 
+// decodes the first file parsed in place with the decoder working in the provided workspace
+// without allocations
+fn decode_check<'a, D: Decoder<&'a [u8]>>(
+        data: &'a [u8],
+        buf: &mut [u8],
+        new_decoder: impl FnOnce(&'a [u8]) -> D
+    )
+{
+    let entry = LhaSliceArchive::new(data).entry_at(0).unwrap().unwrap();
+    assert_eq!(entry.header.filename, FILE_MATCH.as_bytes());
+    let mut decoder = new_decoder(entry.data);
+    let buf = &mut buf[..entry.header.original_size as usize];
+    decoder.fill_buffer(buf).unwrap();
+    let mut crc = Crc16::default();
+    crc.digest(buf);
+    assert_eq!(entry.header.file_crc, crc.sum16());
+}
+
 #[entry]
 fn main() -> ! {
+  static mut LH1_WORKSPACE: Lh1Workspace = Lh1Workspace::new();
+  static mut LH7_WORKSPACE: Lh7Workspace = Lh7Workspace::new();
+  static mut BUFFER: [u8; UNCOMPRESSED_SIZE] = [0; UNCOMPRESSED_SIZE];
   {
       #[no_mangle]
       static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
@@ -71,5 +94,8 @@ fn main() -> ! {
   let lha_reader = delharc::LhaDecodeReader::new(COMPRESSED_6).unwrap();
   assert!(extract_check(lha_reader, FILE_MATCH).unwrap());
 
+  decode_check(COMPRESSED_1, BUFFER, |rd| Lh1Decoder::with_workspace(rd, &mut *LH1_WORKSPACE));
+  decode_check(COMPRESSED_6, BUFFER, |rd| Lh7Decoder::with_workspace(rd, &mut *LH7_WORKSPACE));
+
   loop {}
 }
@@ -67,5 +67,5 @@ fn main() -> Result<(), LhaError<UnexpectedEofError>> {
 
 #[cfg(feature = "std")]
 fn main() {
-    eprintln!("Re-run with --no-default-features");
+    eprintln!("Re-run with --no-default-features --features alloc");
 }
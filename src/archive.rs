@@ -0,0 +1,340 @@
+/*! # Iterating over archive entries.
+
+[`Archive`] wraps a stream of an LHA archive and yields its files as [`Entry`] objects, similar
+to the `tar` crate's API:
+
+```no_run
+# fn main() -> std::io::Result<()> {
+use std::{fs::File, io::{BufReader, Read}};
+use delharc::{Archive, EntryKind};
+
+let mut archive = Archive::new(BufReader::new(File::open("archive.lzh")?));
+for entry in archive.entries() {
+    let mut entry = entry?;
+    if entry.kind() == EntryKind::File {
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        println!("{}: {}", entry.path().display(), content.len());
+    }
+}
+# Ok(())
+# }
+```
+*/
+use core::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+use filetime::FileTime;
+use crate::decode::{DecoderFactory, DecoderRegistry, LhaDecodeReader};
+use crate::stub_io::Take;
+use crate::header::{HeaderManifest, LhaHeader};
+use crate::limits::Limits;
+
+/// An LHA archive read from a stream, yielding its files as [`Entry`] objects.
+///
+/// See the [module documentation](crate::archive).
+#[derive(Debug)]
+pub struct Archive<R> {
+    inner: RefCell<ArchiveInner<R>>
+}
+
+/// An iterator over the entries of [`Archive`], see [`Archive::entries`].
+///
+/// The iterator ends at the end of archive or after yielding an error.
+#[derive(Debug)]
+pub struct Entries<'a, R> {
+    archive: &'a Archive<R>
+}
+
+/// A file read from [`Archive`].
+///
+/// The decompressed content of the file is read with [`io::Read`] methods. The CRC-16 checksum is
+/// verified when the end of the content is reached and a mismatch results in an
+/// [`io::ErrorKind::InvalidData`] error.
+///
+/// An entry can only be read until the next entry is yielded by [`Entries`]. The remaining content
+/// of the dropped entry is skipped at that moment.
+#[derive(Debug)]
+pub struct Entry<'a, R> {
+    archive: &'a Archive<R>,
+    index: u64,
+    header: LhaHeader,
+    path: String,
+    link_target: Option<String>,
+    checked: bool
+}
+
+/// The kind of [`Entry`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryKind {
+    /// A regular file.
+    File,
+    /// An empty directory.
+    Directory,
+    /// A symbolic link, see [`Entry::link_target`].
+    Symlink
+}
+
+#[derive(Debug)]
+struct ArchiveInner<R> {
+    reader: LhaDecodeReader<R>,
+    source: Option<R>,
+    // the number of the entries yielded so far
+    index: u64,
+    done: bool
+}
+
+impl<R: Read> Archive<R> {
+    /// Create a new archive from the given stream reader `rd`.
+    ///
+    /// No data is being read until the first entry is requested.
+    pub fn new(rd: R) -> Self {
+        Self::with_limits(rd, Limits::UNLIMITED)
+    }
+    /// Create a new archive from the given stream reader `rd`, enforcing the resource `limits`,
+    /// see [`LhaDecodeReader::with_limits`].
+    pub fn with_limits(rd: R, limits: Limits) -> Self {
+        Self::with_limits_and_registry(rd, limits, DecoderRegistry::default())
+    }
+    /// Create a new archive from the given stream reader `rd`, enforcing the resource `limits` and
+    /// using the decoder factories from the `registry`, see [`LhaDecodeReader::with_limits_and_registry`].
+    pub fn with_limits_and_registry(rd: R, limits: Limits, registry: DecoderRegistry<Take<R>>) -> Self {
+        let mut reader = LhaDecodeReader::default();
+        reader.set_limits(limits);
+        *reader.decoder_registry_mut() = registry;
+        let inner = ArchiveInner { reader, source: Some(rd), index: 0, done: false };
+        Archive { inner: RefCell::new(inner) }
+    }
+    /// Register the decoder `factory` for the compression method identifier `id`, e.g. `*b"-lh2-"`.
+    ///
+    /// The registered decoders are used for the entries yielded from now on, see
+    /// [`LhaDecodeReader::register_decoder`].
+    pub fn register_decoder<F>(&mut self, id: [u8;5], factory: F) -> bool
+        where F: DecoderFactory<Take<R>> + 'static
+    {
+        self.inner.get_mut().reader.register_decoder(id, factory)
+    }
+    /// Return an iterator over the entries of the archive.
+    ///
+    /// Calling this method again continues after the last yielded entry.
+    pub fn entries(&mut self) -> Entries<'_, R> {
+        Entries { archive: self }
+    }
+    /// Unpack all the entries of the archive into the directory `dir`, see [`Entry::unpack_in`].
+    ///
+    /// The directory is created if it doesn't exist.
+    ///
+    /// # Errors
+    /// Returns the first error encountered when reading the archive or writing files.
+    pub fn unpack<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        for entry in self.entries() {
+            entry?.unpack_in(dir)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, R: Read> Iterator for Entries<'a, R> {
+    type Item = io::Result<Entry<'a, R>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut inner = self.archive.inner.borrow_mut();
+        if inner.done {
+            return None
+        }
+        let res = match inner.source.take() {
+            Some(rd) => inner.reader.begin_new(rd),
+            None => inner.reader.next_file()
+        };
+        match res {
+            Ok(true) => {
+                inner.index += 1;
+                let header = inner.reader.header().clone();
+                Some(Ok(Entry::new(self.archive, inner.index, header)))
+            }
+            Ok(false) => {
+                inner.done = true;
+                None
+            }
+            Err(e) => {
+                inner.done = true;
+                Some(Err(e.into()))
+            }
+        }
+    }
+}
+
+impl<R: Read> core::iter::FusedIterator for Entries<'_, R> {}
+
+impl<'a, R: Read> Entry<'a, R> {
+    fn new(archive: &'a Archive<R>, index: u64, header: LhaHeader) -> Self {
+        let (path, link_target) = header.parse_link();
+        Entry { archive, index, header, path, link_target, checked: false }
+    }
+    /// Return the header of the entry.
+    pub fn header(&self) -> &LhaHeader {
+        &self.header
+    }
+    /// Return the high-level view of the entry's header, see [`LhaHeader::manifest`].
+    pub fn manifest(&self) -> HeaderManifest {
+        self.header.manifest()
+    }
+    /// Return the path name of the entry, see [`LhaHeader::parse_link`].
+    ///
+    /// For symbolic links this is the path of the link itself. Empty, `.` and `..` path
+    /// components are dropped by the header parser, but [`Entry::unpack_in`] still verifies the
+    /// path before extracting.
+    pub fn path(&self) -> &Path {
+        Path::new(&self.path)
+    }
+    /// Return the target of the symbolic link or `None` if the entry is not a symbolic link.
+    pub fn link_target(&self) -> Option<&str> {
+        self.link_target.as_deref()
+    }
+    /// Return the kind of the entry.
+    pub fn kind(&self) -> EntryKind {
+        if self.link_target.is_some() {
+            EntryKind::Symlink
+        }
+        else if self.header.is_directory() {
+            EntryKind::Directory
+        }
+        else {
+            EntryKind::File
+        }
+    }
+    /// Return the size of the decompressed file.
+    pub fn size(&self) -> u64 {
+        self.header.original_size
+    }
+    /// Unpack the entry to the path `dst`, which is being created or replaced.
+    ///
+    /// Files are restored with their last modification time and, on Unix, with the file mode if
+    /// present in the header. Parent directories are created if missing.
+    ///
+    /// # Errors
+    /// Returns an error if the content could not be decoded, the checksum doesn't match or
+    /// the file could not be written. Symbolic links are only supported on Unix.
+    pub fn unpack<P: AsRef<Path>>(&mut self, dst: P) -> io::Result<()> {
+        let dst = dst.as_ref();
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // never write through a symbolic link
+        if matches!(dst.symlink_metadata(), Ok(meta) if meta.file_type().is_symlink()) {
+            fs::remove_file(dst)?;
+        }
+        match self.kind() {
+            EntryKind::Directory => fs::create_dir_all(dst),
+            EntryKind::Symlink => unpack_symlink(self.link_target.as_deref().unwrap(), dst),
+            EntryKind::File => {
+                let mut file = BufWriter::new(File::create(dst)?);
+                io::copy(self, &mut file)?;
+                let file = file.into_inner().map_err(|e| e.into_error())?;
+                if let Some(modified) = self.header.parse_last_modified().to_local() {
+                    let modified = FileTime::from_system_time(SystemTime::from(modified));
+                    filetime::set_file_handle_times(&file, None, Some(modified))?;
+                }
+                #[cfg(unix)]
+                if let Some(mode) = self.header.parse_unix_mode() {
+                    use std::os::unix::fs::PermissionsExt;
+                    file.set_permissions(fs::Permissions::from_mode(u32::from(mode) & 0o7777))?;
+                }
+                Ok(())
+            }
+        }
+    }
+    /// Unpack the entry into the directory `dir` at the entry's path, see [`Entry::unpack`].
+    ///
+    /// Return `Ok(false)` and skip the entry if its path would escape `dir`, i.e. contains `..`
+    /// components or leads through a symbolic link. Absolute paths are made relative to `dir`.
+    ///
+    /// # Errors
+    /// See [`Entry::unpack`].
+    pub fn unpack_in<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<bool> {
+        let dir = dir.as_ref();
+        let relative = match sanitize_path(&self.path) {
+            Some(relative) if relative.as_os_str().is_empty() => return Ok(false),
+            Some(relative) if !traverses_symlink(dir, &relative) => relative,
+            _ => return Ok(false)
+        };
+        self.unpack(dir.join(relative))?;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for Entry<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut inner = self.archive.inner.borrow_mut();
+        if inner.index != self.index {
+            return Err(io::Error::new(io::ErrorKind::Other, "the archive has advanced past this entry"))
+        }
+        if buf.is_empty() {
+            return Ok(0)
+        }
+        let reader = &mut inner.reader;
+        if reader.is_empty() {
+            if !self.checked && self.kind() == EntryKind::File {
+                reader.crc_check()?;
+                self.checked = true;
+            }
+            return Ok(0)
+        }
+        reader.read(buf)
+    }
+}
+
+#[cfg(unix)]
+fn unpack_symlink(target: &str, dst: &Path) -> io::Result<()> {
+    if dst.symlink_metadata().is_ok() {
+        fs::remove_file(dst)?;
+    }
+    std::os::unix::fs::symlink(target, dst)
+}
+
+#[cfg(not(unix))]
+fn unpack_symlink(_target: &str, _dst: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "symbolic links are not supported"))
+}
+
+/// Return `path` as a relative path with only normal components, or `None` if `path` contains
+/// `..` components.
+///
+/// Root, prefix and `.` components are dropped, so absolute paths become relative. The result may
+/// be empty. This is how [`Entry::unpack_in`] verifies entry paths before joining them to
+/// the output directory.
+pub fn sanitize_path(path: &str) -> Option<PathBuf> {
+    let mut res = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(name) => res.push(name),
+            Component::ParentDir => return None,
+            Component::Prefix(..)|Component::RootDir|Component::CurDir => {}
+        }
+    }
+    Some(res)
+}
+
+/// Return `true` if any of the parent directories of `relative`, joined to `base`, exists as
+/// a symbolic link.
+///
+/// The last component of `relative` is not checked, as unpacking replaces it.
+pub fn traverses_symlink(base: &Path, relative: &Path) -> bool {
+    let mut path = base.to_path_buf();
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        if components.peek().is_none() {
+            break
+        }
+        path.push(component);
+        if matches!(path.symlink_metadata(), Ok(meta) if meta.file_type().is_symlink()) {
+            return true
+        }
+    }
+    false
+}
@@ -0,0 +1,499 @@
+//! A command-line tool for listing, testing and extracting **LHA** archives.
+//!
+//! Run `delharc -h` for usage.
+use std::{env, fmt, process};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use filetime::FileTime;
+use delharc::{HeaderIter, LhaDecodeReader, LhaError, Limits, Listing, ListingFormat};
+use delharc::archive::{self, traverses_symlink};
+use delharc::decode::DecoderRegistry;
+use delharc::stub_io::Take;
+
+const USAGE: &str = "\
+usage: delharc <command>[modifiers] [options] ARCHIVE [PATTERN...]
+
+commands:
+  l        list the archive contents
+  v        list the archive contents verbosely
+  t        test the integrity of archived files
+  x, e     extract files
+  p        print the content of files to the standard output
+
+modifiers (appended to the command, like in lha):
+  q        quiet mode
+  f        overwrite existing files
+  i        ignore directory paths when extracting
+  w=DIR    extract files to DIR
+
+options:
+  -q, --quiet              quiet mode
+  -f, --overwrite          overwrite existing files
+  -i, --ignore-paths       ignore directory paths when extracting
+  -w, --output DIR         extract files to DIR
+  -x, --exclude PATTERN    exclude files matching PATTERN, can be repeated
+  -h, --help               print this help
+
+Patterns may contain `*` and `?` wildcards. A pattern matches the whole path of an archived file
+or any of its parent directories. A pattern without `/` matches any of the path components.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    List,
+    Verbose,
+    Test,
+    Extract,
+    Print
+}
+
+#[derive(Debug)]
+struct Options {
+    command: Command,
+    quiet: bool,
+    overwrite: bool,
+    ignore_paths: bool,
+    output: PathBuf,
+    archive: PathBuf,
+    include: Vec<String>,
+    exclude: Vec<String>
+}
+
+#[derive(Debug)]
+struct UsageError(String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return
+    }
+    let opts = match parse_args(args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("delharc: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    match run(&opts) {
+        Ok(0) => {}
+        Ok(errors) => {
+            eprintln!("delharc: {} error(s) occurred", errors);
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("delharc: {}: {}", opts.archive.display(), e);
+            process::exit(1);
+        }
+    }
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, UsageError> {
+    let mut args = args.into_iter();
+    let command = args.next().ok_or_else(|| UsageError("missing command".into()))?;
+    let mut opts = Options {
+        command: Command::List,
+        quiet: false,
+        overwrite: false,
+        ignore_paths: false,
+        output: PathBuf::new(),
+        archive: PathBuf::new(),
+        include: Vec::new(),
+        exclude: Vec::new()
+    };
+    let mut chars = command.trim_start_matches('-').char_indices();
+    opts.command = match chars.next() {
+        Some((_, 'l')) => Command::List,
+        Some((_, 'v')) => Command::Verbose,
+        Some((_, 't')) => Command::Test,
+        Some((_, 'x'|'e')) => Command::Extract,
+        Some((_, 'p')) => Command::Print,
+        _ => return Err(UsageError(format!("unknown command: {}", command)))
+    };
+    for (index, c) in chars {
+        match c {
+            'q' => opts.quiet = true,
+            'f' => opts.overwrite = true,
+            'i' => opts.ignore_paths = true,
+            'w' => {
+                let dir = command[index + 1..].trim_start_matches('=');
+                if dir.is_empty() {
+                    return Err(UsageError("missing directory for the `w` modifier".into()))
+                }
+                opts.output = dir.into();
+                break
+            }
+            _ => return Err(UsageError(format!("unknown modifier: {}", c)))
+        }
+    }
+    let mut positional = Vec::new();
+    let mut options_end = false;
+    while let Some(arg) = args.next() {
+        if options_end || !arg.starts_with('-') || arg == "-" {
+            positional.push(arg);
+            continue
+        }
+        let mut value = |name: &str| args.next().ok_or_else(||
+                            UsageError(format!("missing value for {}", name)));
+        match arg.as_str() {
+            "--" => options_end = true,
+            "-q"|"--quiet" => opts.quiet = true,
+            "-f"|"--overwrite" => opts.overwrite = true,
+            "-i"|"--ignore-paths" => opts.ignore_paths = true,
+            "-w"|"--output" => opts.output = value(&arg)?.into(),
+            "-x"|"--exclude" => opts.exclude.push(value(&arg)?),
+            _ => return Err(UsageError(format!("unknown option: {}", arg)))
+        }
+    }
+    let mut positional = positional.into_iter();
+    opts.archive = positional.next().ok_or_else(|| UsageError("missing archive name".into()))?.into();
+    opts.include = positional.collect();
+    Ok(opts)
+}
+
+fn run(opts: &Options) -> io::Result<usize> {
+    let file = BufReader::new(File::open(&opts.archive)?);
+    match opts.command {
+        Command::List|Command::Verbose => list(opts, file),
+        _ => process_entries(opts, file)
+    }
+}
+
+// walks headers only, without decoding
+fn list(opts: &Options, file: BufReader<File>) -> io::Result<usize> {
+    let mut headers = HeaderIter::new_seekable(file);
+    let mut header = headers.next().ok_or_else(||
+                        io::Error::from(LhaError::<io::Error>::HeaderParse("a header is missing"))
+                     )??;
+    let mut listing = Listing::new();
+    let format = match opts.command {
+        Command::Verbose => ListingFormat::LhaVerbose,
+        _ => ListingFormat::Lha
+    };
+    let mut text = String::new();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if !opts.quiet {
+        listing.write_header(&mut text, format).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    }
+    loop {
+        let path = header.parse_pathname_to_str();
+        if is_selected(opts, &path) {
+            listing.push(&header);
+            let entry = listing.entries().last().unwrap();
+            listing.write_entry(&mut text, entry, format).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            out.write_all(text.as_bytes())?;
+            text.clear();
+        }
+        header = match headers.next() {
+            Some(header) => header?,
+            None => break
+        };
+    }
+    if !opts.quiet {
+        listing.write_footer(&mut text, format).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    }
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(0)
+}
+
+// the decoders of compression methods not supported by the library are registered here
+fn decoder_registry() -> DecoderRegistry<Take<BufReader<File>>> {
+    DecoderRegistry::new()
+}
+
+fn process_entries(opts: &Options, file: BufReader<File>) -> io::Result<usize> {
+    let mut lha_reader = LhaDecodeReader::with_limits_and_registry(file, Limits::UNLIMITED, decoder_registry())?;
+    let mut errors = 0;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    loop {
+        let header = lha_reader.header();
+        let path = header.parse_pathname_to_str();
+        if is_selected(opts, &path) {
+            match opts.command {
+                Command::Test => {
+                    errors += test_entry(&mut lha_reader, &path, opts.quiet)?;
+                }
+                Command::Extract => {
+                    errors += extract_entry(&mut lha_reader, &path, opts)?;
+                }
+                Command::Print => {
+                    errors += print_entry(&mut lha_reader, &mut out, &path, opts.quiet)?;
+                }
+                Command::List|Command::Verbose => unreachable!()
+            }
+        }
+        if !lha_reader.next_file()? {
+            break
+        }
+    }
+    out.flush()?;
+    Ok(errors)
+}
+
+fn is_selected(opts: &Options, path: &str) -> bool {
+    (opts.include.is_empty() || opts.include.iter().any(|pat| pattern_matches(pat, path)))
+    && !opts.exclude.iter().any(|pat| pattern_matches(pat, path))
+}
+
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_end_matches('/').as_bytes();
+    let path = path.split('|').next().unwrap_or(path);
+    if !pattern.contains(&b'/') {
+        // any of the path components
+        return path.split('/').any(|name| glob_matches(pattern, name.as_bytes()))
+    }
+    // the whole path or any of the parent directories
+    path.match_indices('/').map(|(index, _)| &path[..index]).chain([path])
+        .any(|prefix| glob_matches(pattern, prefix.as_bytes()))
+}
+
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, t));
+                p += 1;
+                continue
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+                continue
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((bp, bt)) => {
+                p = bp + 1;
+                t = bt + 1;
+                backtrack = Some((bp, bt + 1));
+            }
+            None => return false
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/* testing, printing and extracting */
+
+fn report<S: fmt::Display>(path: &str, status: S) {
+    println!("{} - {}", path, status);
+}
+
+fn report_error<S: fmt::Display>(path: &str, error: S) {
+    eprintln!("{} - {}", path, error);
+}
+
+fn unsupported<R: io::Read>(lha_reader: &LhaDecodeReader<R>, path: &str) -> usize {
+    report_error(path, format_args!("unsupported compression method {}",
+                                    String::from_utf8_lossy(&lha_reader.header().compression)));
+    1
+}
+
+fn test_entry<R: io::Read>(lha_reader: &mut LhaDecodeReader<R>, path: &str, quiet: bool) -> io::Result<usize> {
+    if lha_reader.header().is_directory() {
+        return Ok(0)
+    }
+    if !lha_reader.is_decoder_supported() {
+        return Ok(unsupported(lha_reader, path))
+    }
+    if let Err(e) = io::copy(lha_reader, &mut io::sink()) {
+        report_error(path, e);
+        return Ok(1)
+    }
+    if let Err(e) = lha_reader.crc_check() {
+        report_error(path, e);
+        return Ok(1)
+    }
+    if !quiet {
+        report(path, "Tested");
+    }
+    Ok(0)
+}
+
+fn print_entry<R: io::Read, W: Write>(
+        lha_reader: &mut LhaDecodeReader<R>,
+        out: &mut W,
+        path: &str,
+        quiet: bool
+    ) -> io::Result<usize>
+{
+    if lha_reader.header().is_directory() {
+        return Ok(0)
+    }
+    if !lha_reader.is_decoder_supported() {
+        return Ok(unsupported(lha_reader, path))
+    }
+    if !quiet {
+        writeln!(out, "::::::::\n{}\n::::::::", path)?;
+    }
+    if let Err(e) = io::copy(lha_reader, out) {
+        out.flush()?;
+        report_error(path, e);
+        return Ok(1)
+    }
+    if let Err(e) = lha_reader.crc_check() {
+        out.flush()?;
+        report_error(path, e);
+        return Ok(1)
+    }
+    Ok(0)
+}
+
+fn extract_entry<R: io::Read + io::Seek>(
+        lha_reader: &mut LhaDecodeReader<R>,
+        path: &str,
+        opts: &Options
+    ) -> io::Result<usize>
+{
+    let header = lha_reader.header();
+    let (link, link_target) = header.parse_link();
+    let relative = match sanitize_path(&link, opts.ignore_paths) {
+        Some(relative) => relative,
+        None => {
+            report_error(path, "unsafe path name, skipped");
+            return Ok(1)
+        }
+    };
+    if relative.as_os_str().is_empty() {
+        return Ok(0)
+    }
+    let target = opts.output.join(&relative);
+    if traverses_symlink(&opts.output, &relative) {
+        report_error(path, "path traverses a symbolic link, skipped");
+        return Ok(1)
+    }
+    if let Some(link_target) = link_target {
+        return extract_symlink(&target, &link, &link_target, opts)
+    }
+    if header.is_directory() {
+        if opts.ignore_paths {
+            return Ok(0)
+        }
+        fs::create_dir_all(&target)?;
+        if !opts.quiet {
+            report(path, "Created");
+        }
+        return Ok(0)
+    }
+    if !lha_reader.is_decoder_supported() {
+        return Ok(unsupported(lha_reader, path))
+    }
+    if target.symlink_metadata().is_ok() && !opts.overwrite {
+        report_error(path, "already exists, skipped");
+        return Ok(0)
+    }
+    let modified = header.parse_last_modified().to_local().map(SystemTime::from);
+    let mode = header.parse_unix_mode();
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // never write through a symbolic link
+    if matches!(target.symlink_metadata(), Ok(meta) if meta.file_type().is_symlink()) {
+        fs::remove_file(&target)?;
+    }
+    let mut file = BufWriter::new(File::create(&target)?);
+    // the checksum is reported after the file's metadata is restored
+    let crc_res = match lha_reader.copy_seekable_entry_to(&mut file) {
+        Err(e @ LhaError::Checksum(..)) => Err(e),
+        Err(e) => {
+            report_error(path, e);
+            return Ok(1)
+        }
+        Ok(..) => Ok(())
+    };
+    let file = file.into_inner().map_err(|e| e.into_error())?;
+    if let Some(modified) = modified {
+        filetime::set_file_handle_times(&file, None, Some(FileTime::from_system_time(modified)))?;
+    }
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(u32::from(mode) & 0o7777))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    if let Err(e) = crc_res {
+        report_error(path, e);
+        return Ok(1)
+    }
+    if !opts.quiet {
+        report(path, "Extracted");
+    }
+    Ok(0)
+}
+
+#[cfg(unix)]
+fn extract_symlink(target: &Path, link: &str, link_target: &str, opts: &Options) -> io::Result<usize> {
+    if target.symlink_metadata().is_ok() {
+        if !opts.overwrite {
+            report_error(link, "already exists, skipped");
+            return Ok(0)
+        }
+        fs::remove_file(target)?;
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    std::os::unix::fs::symlink(link_target, target)?;
+    if !opts.quiet {
+        report(link, format_args!("Symbolic link to {}", link_target));
+    }
+    Ok(0)
+}
+
+#[cfg(not(unix))]
+fn extract_symlink(_target: &Path, link: &str, _link_target: &str, _opts: &Options) -> io::Result<usize> {
+    report_error(link, "symbolic links are not supported, skipped");
+    Ok(0)
+}
+
+// like `archive::sanitize_path`, keeping only the file name if `ignore_paths` is set
+fn sanitize_path(path: &str, ignore_paths: bool) -> Option<PathBuf> {
+    let res = archive::sanitize_path(path)?;
+    if ignore_paths {
+        return Some(res.file_name().map(PathBuf::from).unwrap_or_default())
+    }
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_matches_works() {
+        assert!(pattern_matches("*.txt", "dir/hello.txt"));
+        assert!(pattern_matches("dir", "dir/hello.txt"));
+        assert!(pattern_matches("dir/*.txt", "dir/hello.txt"));
+        assert!(pattern_matches("d?r/", "dir/sub/hello.txt"));
+        assert!(pattern_matches("hello*", "dir/hello.txt"));
+        assert!(!pattern_matches("*.txt", "dir/hello.bin"));
+        assert!(!pattern_matches("sub/*", "dir/sub/hello.txt"));
+        assert!(pattern_matches("link", "dir/link|target"));
+        assert!(pattern_matches("sub", "dir/sub/hello.txt"));
+        assert!(glob_matches(b"*a*b?", b"xxaxxbxbc"));
+        assert!(!glob_matches(b"*a*b?", b"xxaxxb"));
+        assert!(glob_matches(b"", b""));
+        assert!(glob_matches(b"**", b""));
+    }
+
+    #[test]
+    fn sanitize_path_works() {
+        assert_eq!(sanitize_path("/abs/path", false), Some(PathBuf::from("abs/path")));
+        assert_eq!(sanitize_path("dir/../../etc", false), None);
+        assert_eq!(sanitize_path("dir/sub/file", true), Some(PathBuf::from("file")));
+    }
+}
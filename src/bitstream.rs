@@ -1,11 +1,13 @@
 //! # Bit-stream tools.
 use core::mem;
 use crate::error::{LhaResult, LhaError};
-use crate::stub_io::Read;
+use crate::stub_io::{Read, ReadAhead};
 
 type BitBuf = usize;
 const BITBUF_BYTESIZE: usize = mem::size_of::<BitBuf>();
 const BITBUF_BITSIZE: u32 = (BITBUF_BYTESIZE * 8) as u32;
+/// The maximum number of bits that can be peeked at once with [BitRead::peek_bits].
+pub const MAX_PEEK_BITS: u32 = BITBUF_BITSIZE - 8;
 
 /// The trait is implemented for all the types that can receive bits using [BitRead::read_bits].
 pub trait UBits: Copy {
@@ -27,6 +29,18 @@ pub trait BitRead {
     /// # Panics
     /// Panics if `n` exceed the bit capacity of `T`.
     fn read_bits<T: UBits>(&mut self, n: u32) -> Result<T, LhaError<Self::Error>>;
+    /// Returns the next `n` bits from the stream without consuming them.
+    ///
+    /// Bits missing past the end of the stream are returned as `0`. Returns `0` if `n` is `0`.
+    ///
+    /// `n` must not exceed [MAX_PEEK_BITS].
+    fn peek_bits(&mut self, n: u32) -> Result<usize, LhaError<Self::Error>>;
+    /// Consumes the next `n` bits from the stream, usually after calling [BitRead::peek_bits].
+    ///
+    /// Returns an unexpected end of stream error if fewer than `n` bits are available.
+    ///
+    /// `n` must not exceed [MAX_PEEK_BITS].
+    fn skip_bits(&mut self, n: u32) -> Result<(), LhaError<Self::Error>>;
     // /// Creates a "by reference" adaptor for this instance of `BitRead`.
     // /// The returned adaptor also implements `BitRead` and will simply borrow this current reader.
     // fn by_ref(&mut self) -> &mut Self {
@@ -37,9 +51,13 @@ pub trait BitRead {
 /// A simple bit-stream reader, wrapped over a readable stream.
 ///
 /// Bits are being read from an each consecutive byte, starting from its highest bit.
+///
+/// The underlying reader is read ahead in chunks of up to [`READ_AHEAD_SIZE`][crate::stub_io::READ_AHEAD_SIZE]
+/// bytes, so the number of reads is reduced also for unbuffered streams, see
+/// [`ReadAhead`] for why the buffer is held by the bit-stream.
 #[derive(Debug)]
 pub struct BitStream<R> {
-    inner: R,
+    inner: ReadAhead<R>,
     // x..x10..0
     bits_buf: BitBuf,
 }
@@ -63,14 +81,40 @@ impl_ubits!(usize);
 impl<R: Read> BitStream<R> {
     /// Creates a new `BitStream<R>`.
     pub fn new(inner: R) -> BitStream<R> {
-        BitStream { inner, bits_buf: 1 << (BITBUF_BITSIZE - 1) }
+        BitStream { inner: ReadAhead::new(inner), bits_buf: 1 << (BITBUF_BITSIZE - 1) }
     }
     /// Unwraps this `BitStream<R>`, returning the underlying reader.
     ///
-    /// Note that any leftover data in the internal bit buffer is lost. Therefore, a following read from
-    /// the underlying reader may lead to data loss.
+    /// Note that any leftover data in the internal bit buffer and in the read-ahead buffer is lost.
+    /// Therefore, a following read from the underlying reader may lead to data loss.
     pub fn into_inner(self) -> R {
-        self.inner
+        self.inner.into_inner()
+    }
+
+    #[inline(always)]
+    fn have_bits(&self) -> u32 {
+        BITBUF_BITSIZE - self.bits_buf.trailing_zeros() - 1
+    }
+
+    /// Tops up the bit buffer with as many whole bytes as it can hold and returns the number
+    /// of available bits.
+    #[inline(never)]
+    fn refill(&mut self) -> LhaResult<u32, R> {
+        let have_bits = self.have_bits();
+        let nbytes = ((BITBUF_BITSIZE - 1 - have_bits) / 8) as usize;
+        let mut buf = [0u8;BITBUF_BYTESIZE];
+        let bytes_read = self.inner.read_all(&mut buf[..nbytes])
+                                   .map_err(LhaError::Io)?;
+        if bytes_read == 0 {
+            return Ok(have_bits)
+        }
+        let bits = have_bits + 8 * bytes_read as u32;
+        let new_bits = BitBuf::from_be_bytes(buf) >> have_bits;
+        // clear the marker bit, merge and set the new marker bit
+        self.bits_buf = self.bits_buf & (self.bits_buf - 1)
+                        | new_bits
+                        | 1 << (BITBUF_BITSIZE - 1 - bits);
+        Ok(bits)
     }
 
     #[inline]
@@ -131,6 +175,16 @@ impl<R: BitRead> BitRead for &mut R {
     fn read_bits<T: UBits>(&mut self, n: u32) -> Result<T, LhaError<Self::Error>> {
         (*self).read_bits(n)
     }
+
+    #[inline]
+    fn peek_bits(&mut self, n: u32) -> Result<usize, LhaError<Self::Error>> {
+        (*self).peek_bits(n)
+    }
+
+    #[inline]
+    fn skip_bits(&mut self, n: u32) -> Result<(), LhaError<Self::Error>> {
+        (*self).skip_bits(n)
+    }
 }
 
 impl<R: Read> BitRead for BitStream<R> {
@@ -148,6 +202,29 @@ impl<R: Read> BitRead for BitStream<R> {
             _ => Err(LhaError::Decompress("too many bits requested"))
         }.map(T::from_bits)
     }
+
+    #[inline]
+    fn peek_bits(&mut self, n: u32) -> Result<usize, LhaError<Self::Error>> {
+        debug_assert!(n <= MAX_PEEK_BITS);
+        if n == 0 {
+            return Ok(0)
+        }
+        if self.have_bits() < n {
+            self.refill()?;
+        }
+        // clear the marker bit, so the missing bits are read as 0
+        Ok((self.bits_buf & (self.bits_buf - 1)) >> (BITBUF_BITSIZE - n))
+    }
+
+    #[inline]
+    fn skip_bits(&mut self, n: u32) -> Result<(), LhaError<Self::Error>> {
+        debug_assert!(n <= MAX_PEEK_BITS);
+        if self.have_bits() < n && self.refill()? < n {
+            return Err(LhaError::Io(R::unexpected_eof()))
+        }
+        self.bits_buf <<= n;
+        Ok(())
+    }
 }
 
 #[inline(always)]
@@ -196,6 +273,24 @@ mod tests {
         let err: io::Error = brdr.read_bit().unwrap_err().into();
         assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
 
+        let mut somebits: &[u8] = &[0b10110011, 0b10001111, 0b01010101];
+        let mut brdr = BitStream::new(&mut somebits);
+        assert_eq!(brdr.peek_bits(0).unwrap(), 0);
+        assert_eq!(brdr.peek_bits(3).unwrap(), 0b101);
+        assert_eq!(brdr.peek_bits(12).unwrap(), 0b101100111000);
+        brdr.skip_bits(3).unwrap();
+        assert_eq!(brdr.read_bits::<u8>(7).unwrap(), 0b1001110);
+        assert_eq!(brdr.peek_bits(MAX_PEEK_BITS).unwrap(), 0b00111101010101 << (MAX_PEEK_BITS - 14));
+        brdr.skip_bits(4).unwrap();
+        assert!(brdr.read_bit().unwrap());
+        assert_eq!(brdr.peek_bits(12).unwrap(), 0b101010101000);
+        let err: io::Error = brdr.skip_bits(10).unwrap_err().into();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        brdr.skip_bits(9).unwrap();
+        assert_eq!(brdr.peek_bits(1).unwrap(), 0);
+        let err: io::Error = brdr.skip_bits(1).unwrap_err().into();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
         let mut somebits: &[u8] = &[1,2,3,4,5,6,7,8];
         let mut brdr = BitStream::new(&mut somebits);
         match BITBUF_BITSIZE {
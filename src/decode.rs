@@ -1,10 +1,19 @@
 //! # Decoding algorithms.
 use core::fmt;
-use crate::error::{LhaResult, LhaError};
-use crate::stub_io::{Read, Take, discard_to_end};
-
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{boxed::Box, vec, vec::Vec};
+use crate::error::LhaError;
+#[cfg(feature = "alloc")]
+use crate::error::LhaResult;
+use crate::stub_io::Read;
+#[cfg(feature = "alloc")]
+use crate::stub_io::{Take, discard_to_end};
+#[cfg(feature = "alloc")]
 use crate::crc::Crc16;
+#[cfg(feature = "alloc")]
 use crate::header::{CompressionMethod, LhaHeader};
+#[cfg(feature = "alloc")]
+use crate::limits::Limits;
 
 #[cfg(feature = "lz")]
 mod lzs;
@@ -13,6 +22,8 @@ mod lz5;
 #[cfg(feature = "lh1")]
 mod lhv1;
 mod lhv2;
+#[cfg(feature = "alloc")]
+mod registry;
 
 #[cfg(feature = "lz")]
 pub use lzs::*;
@@ -21,6 +32,8 @@ pub use lz5::*;
 #[cfg(feature = "lh1")]
 pub use lhv1::*;
 pub use lhv2::*;
+#[cfg(feature = "alloc")]
+pub use registry::*;
 
 /// The trait implemented by decoders.
 pub trait Decoder<R> {
@@ -34,6 +47,34 @@ pub trait Decoder<R> {
     fn fill_buffer(&mut self, buf: &mut[u8]) -> Result<(), LhaError<Self::Error>>;
 }
 
+/// The trait implemented by decoders keeping the history of the decoded data in a ring buffer.
+///
+/// The decoded data is exposed directly from the ring buffer, so it can be read without copying it
+/// into another buffer first. The data returned by [`BufDecoder::fill_buf`] and not consumed yet
+/// goes first to the buffer filled by [`Decoder::fill_buffer`].
+pub trait BufDecoder<R>: Decoder<R> {
+    /// Return the decoded data not consumed yet. If there is none, decode at most `max` bytes
+    /// directly into the ring buffer first.
+    ///
+    /// Less than `max` bytes are decoded if the data would wrap around the end of the ring buffer.
+    /// The returned slice is empty only if `max` is 0. The caller should be aware of the size of
+    /// the decompressed file, like with [`Decoder::fill_buffer`].
+    fn fill_buf(&mut self, max: usize) -> Result<&[u8], LhaError<Self::Error>>;
+    /// Mark `amt` bytes of the data returned by [`BufDecoder::fill_buf`] as consumed.
+    fn consume(&mut self, amt: usize);
+    /// Return the decoded data not consumed yet.
+    fn buffer(&self) -> &[u8];
+}
+
+// copies the decoded data not consumed yet to `buf`, returns the number of bytes copied
+fn take_buffered<R, D: BufDecoder<R> + ?Sized>(decoder: &mut D, buf: &mut[u8]) -> usize {
+    let buffered = decoder.buffer();
+    let len = buffered.len().min(buf.len());
+    buf[..len].copy_from_slice(&buffered[..len]);
+    decoder.consume(len);
+    len
+}
+
 /// `LhaDecodeReader` provides a convenient way to parse and decode LHA/LZH files.
 ///
 /// To read the current archived file's content use the [`std::io::Read`] trait methods on the instance
@@ -50,17 +91,59 @@ pub trait Decoder<R> {
 /// successfully, invoke [`LhaDecodeReader::is_decoder_supported`] to ensure you can actually read the file.
 /// Otherwise, trying to read from an unsupported decoder will result in an error.
 ///
+/// Use [`LhaDecodeReader::with_limits`] when decoding untrusted archives to enforce the resource
+/// [`Limits`].
+///
+/// In the strict mode, enabled with [`LhaDecodeReader::set_strict`], the checksum is verified
+/// automatically by the read that reaches the end of the file, so e.g. [`std::io::copy`] fails
+/// on corrupted data instead of leaving the check to the caller.
+///
+/// The workspaces of the decoders, holding the sliding window history and Huffman trees, are kept
+/// when moving to the next file and reused by the next decoders of the same family, so archives with
+/// many files don't need to allocate a new workspace for each one of them.
+///
+/// `LhaDecodeReader` also implements [`std::io::BufRead`], so the decompressed content can be read
+/// line by line or accessed in chunks without wrapping it in a `BufReader`. The decoders keeping
+/// the history of the decoded data, see [`BufDecoder`], decode it directly into their ring buffers,
+/// which are then exposed by `fill_buf`. Only the content of files stored without compression or
+/// decoded by the [registered decoders][LhaDecodeReader::register_decoder] is copied into a separate
+/// buffer, allocated on the first call to `fill_buf`. The checksum is updated with the consumed data.
+///
 /// # `no_std`
-/// Without the `std` feature in the absence of `std::io` the crate's [`Read`] trait methods should
-/// be used instead to read the content of the decompressed files.
+/// Without the `std` feature in the absence of `std::io` the crate's [`Read`] and
+/// [`BufRead`][crate::stub_io::BufRead] trait methods should be used instead to read the content
+/// of the decompressed files.
+#[cfg(feature = "alloc")]
 #[derive(Debug)]
 pub struct LhaDecodeReader<R> {
     header: LhaHeader,
     crc: Crc16,
     output_length: u64,
-    decoder: Option<DecoderAny<Take<R>>>
+    limits: Limits,
+    total_output: u64,
+    entries: u64,
+    decoder: Option<DecoderAny<Take<R>>>,
+    pool: WorkspacePool,
+    decoded: DecodedBuffer,
+    strict: bool,
+    registry: DecoderRegistry<Take<R>>
+}
+
+/// The decoded and not yet consumed content of the current file, used to implement `BufRead` for
+/// decoders not implementing [`BufDecoder`].
+#[cfg(feature = "alloc")]
+#[derive(Default)]
+struct DecodedBuffer {
+    data: Box<[u8]>,
+    pos: usize,
+    len: usize
 }
 
+#[cfg(feature = "std")]
+const DECODED_BUFFER_SIZE: usize = 8*1024;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+const DECODED_BUFFER_SIZE: usize = 1024;
+
 /// An empty decoder for storage only methods.
 #[derive(Debug)]
 pub struct PassthroughDecoder<R> {
@@ -80,11 +163,13 @@ pub struct UnsupportedDecoder<R> {
 ///
 /// Alternatively, the error can be converted to the underlying [LhaError] using [From]
 /// trait, thus discarding the contained stream.
+#[cfg(feature = "alloc")]
 pub struct LhaDecodeError<R: Read> {
     read: R,
     source: LhaError<R::Error>
 }
 
+#[cfg(feature = "alloc")]
 #[non_exhaustive]
 #[derive(Debug)]
 pub enum DecoderAny<R> {
@@ -102,8 +187,61 @@ pub enum DecoderAny<R> {
     Lh7Decoder(Lh7Decoder<R>),
     #[cfg(feature = "lhx")]
     LhxDecoder(LhxDecoder<R>),
+    BoxedDecoder(BoxedDecoder<R>),
+}
+
+#[cfg(feature = "alloc")]
+impl DecodedBuffer {
+    fn buffered(&self) -> &[u8] {
+        &self.data[self.pos..self.len]
+    }
+
+    fn clear(&mut self) {
+        self.pos = 0;
+        self.len = 0;
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for DecodedBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecodedBuffer")
+         .field("capacity", &self.data.len())
+         .field("buffered", &(self.len - self.pos))
+         .finish()
+    }
+}
+
+/// Workspaces of the finished decoders kept for reuse by the next decoders of the same family.
+///
+/// At most one workspace per each workspace type is kept. The pool is used internally by
+/// [`LhaDecodeReader`] and can be provided to [`decode_to_slice_in`] or
+/// [`DecoderAny::new_from_compression_in`], so decoding many files doesn't need to allocate
+/// a new workspace for each one of them.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Default)]
+pub struct WorkspacePool {
+    #[cfg(feature = "lz")]
+    lzs: Option<Box<LzsWorkspace>>,
+    #[cfg(feature = "lz")]
+    lz5: Option<Box<Lz5Workspace>>,
+    #[cfg(feature = "lh1")]
+    lh1: Option<Box<Lh1Workspace>>,
+    lh5: Option<Box<Lh5Workspace>>,
+    lh7: Option<Box<Lh7Workspace>>,
+    #[cfg(feature = "lhx")]
+    lhx: Option<Box<LhxWorkspace>>,
 }
 
+#[cfg(feature = "alloc")]
+impl WorkspacePool {
+    /// Create an empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "alloc")]
 macro_rules! decoder_any_dispatch {
     (($model:expr)($($spec:tt)*) => $expr:expr) => {
         match $model {
@@ -121,23 +259,56 @@ macro_rules! decoder_any_dispatch {
             DecoderAny::Lh7Decoder($($spec)*) => $expr,
             #[cfg(feature = "lhx")]
             DecoderAny::LhxDecoder($($spec)*) => $expr,
+            DecoderAny::BoxedDecoder($($spec)*) => $expr,
+        }
+    };
+}
+
+#[cfg(feature = "alloc")]
+macro_rules! buf_decoder_dispatch {
+    (($model:expr)($($spec:tt)*) => $expr:expr, _ => $other:expr) => {
+        match $model {
+            #[cfg(feature = "lz")]
+            DecoderAny::LzsDecoder($($spec)*) => $expr,
+            #[cfg(feature = "lz")]
+            DecoderAny::Lz5Decoder($($spec)*) => $expr,
+            #[cfg(feature = "lh1")]
+            DecoderAny::Lh1Decoder($($spec)*) => $expr,
+            DecoderAny::Lh4Decoder($($spec)*)|
+            DecoderAny::Lh5Decoder($($spec)*) => $expr,
+            DecoderAny::Lh6Decoder($($spec)*)|
+            DecoderAny::Lh7Decoder($($spec)*) => $expr,
+            #[cfg(feature = "lhx")]
+            DecoderAny::LhxDecoder($($spec)*) => $expr,
+            DecoderAny::PassthroughDecoder(..)|
+            DecoderAny::UnsupportedDecoder(..)|
+            DecoderAny::BoxedDecoder(..) => $other,
         }
     };
 }
 
 /// A default implementation creates an instance of `LhaDecodeReader<R>` with no reader present and
 /// with a phony header.
+#[cfg(feature = "alloc")]
 impl<R: Read> Default for LhaDecodeReader<R> {
     fn default() -> Self {
         LhaDecodeReader {
             header: Default::default(),
             crc: Crc16::default(),
             output_length: 0,
-            decoder: None
+            limits: Limits::UNLIMITED,
+            total_output: 0,
+            entries: 0,
+            decoder: None,
+            pool: WorkspacePool::default(),
+            decoded: DecodedBuffer::default(),
+            strict: false,
+            registry: DecoderRegistry::default()
         }
     } 
 }
 
+#[cfg(feature = "alloc")]
 impl<R: Read> LhaDecodeReader<R> where R::Error: fmt::Debug {
     /// Return a new instance of `LhaDecodeReader<R>` after reading and parsing the first header from source.
     ///
@@ -145,21 +316,60 @@ impl<R: Read> LhaDecodeReader<R> where R::Error: fmt::Debug {
     ///
     /// # Errors
     /// Return an error if the header could not be read or parsed.
-    pub fn new(mut rd: R) -> Result<LhaDecodeReader<R>, LhaDecodeError<R>> {
-        let header = match LhaHeader::read(rd.by_ref()).and_then(|h|
+    pub fn new(rd: R) -> Result<LhaDecodeReader<R>, LhaDecodeError<R>> {
+        Self::with_limits(rd, Limits::UNLIMITED)
+    }
+    /// Return a new instance of `LhaDecodeReader<R>` enforcing the given resource `limits`, after
+    /// reading and parsing the first header from source.
+    ///
+    /// The header related limits are enforced when parsing each header, see [`LhaHeader::read_with_limits`].
+    /// The maximum number of entries and the maximum total output are counted from the creation
+    /// of the decoder, across all the entries and the streams provided to [`LhaDecodeReader::begin_new`].
+    ///
+    /// # Errors
+    /// Return an error if the header could not be read or parsed or if any of the limits is exceeded.
+    pub fn with_limits(rd: R, limits: Limits) -> Result<LhaDecodeReader<R>, LhaDecodeError<R>> {
+        Self::with_limits_and_registry(rd, limits, DecoderRegistry::default())
+    }
+    /// Return a new instance of `LhaDecodeReader<R>` enforcing the given resource `limits` and using
+    /// the decoder factories from the `registry`, after reading and parsing the first header from source.
+    ///
+    /// The registered decoders apply to all the files, including the first one.
+    /// See [`LhaDecodeReader::with_limits`] and [`LhaDecodeReader::register_decoder`].
+    ///
+    /// # Errors
+    /// Return an error if the header could not be read or parsed or if any of the limits is exceeded.
+    pub fn with_limits_and_registry(
+            mut rd: R,
+            limits: Limits,
+            registry: DecoderRegistry<Take<R>>
+        ) -> Result<LhaDecodeReader<R>, LhaDecodeError<R>>
+    {
+        let header = match LhaHeader::read_with_limits(rd.by_ref(), &limits).and_then(|h|
                         h.ok_or_else(|| LhaError::HeaderParse("a header is missing"))
                     )
         {
             Ok(h) => h,
             Err(e) => return Err(wrap_err(rd, e))
         };
-        let decoder = DecoderAny::new_from_header(&header, rd);
+        if limits.max_entries == 0 {
+            return Err(wrap_err(rd, LhaError::Limit("number of entries")))
+        }
+        let mut pool = WorkspacePool::default();
+        let decoder = DecoderAny::new_from_header_in(&header, rd, &mut pool, &registry);
         let crc = Crc16::default();
         Ok(LhaDecodeReader {
             header,
             crc,
             output_length: 0,
-            decoder: Some(decoder)
+            limits,
+            total_output: 0,
+            entries: 1,
+            decoder: Some(decoder),
+            pool,
+            decoded: DecodedBuffer::default(),
+            strict: false,
+            registry
         })
     }
     /// Attempt to read the first file header from a new source stream and initialize a decoder returning
@@ -177,14 +387,20 @@ impl<R: Read> LhaDecodeReader<R> where R::Error: fmt::Debug {
     /// reader is not being replaced by a new one and the provided source stream can be retrieved from
     /// the returned error.
     pub fn begin_new(&mut self, mut rd: R) -> Result<bool, LhaDecodeError<R>> {
-        let res = match LhaHeader::read(rd.by_ref()) {
+        let res = match LhaHeader::read_with_limits(rd.by_ref(), &self.limits) {
+            Ok(Some(_)) if self.entries >= self.limits.max_entries => {
+                return Err(wrap_err(rd, LhaError::Limit("number of entries")))
+            }
             Ok(Some(header)) => {
-                let decoder = DecoderAny::new_from_header(&header, rd);
+                self.entries += 1;
+                self.recycle_decoder();
+                let decoder = DecoderAny::new_from_header_in(&header, rd, &mut self.pool, &self.registry);
                 self.decoder = Some(decoder);
                 self.header = header;
                 true
             }
             Ok(None) => {
+                self.recycle_decoder();
                 let decoder = UnsupportedDecoder::new(rd.take(0));
                 self.decoder = Some(DecoderAny::UnsupportedDecoder(decoder));
                 false
@@ -193,6 +409,7 @@ impl<R: Read> LhaDecodeReader<R> where R::Error: fmt::Debug {
         };
         self.crc.reset();
         self.output_length = 0;
+        self.decoded.clear();
         Ok(res)
     }
     /// Assign externally parsed header and decoder to this instance of `LhaDecodeReader<R>`.
@@ -203,11 +420,16 @@ impl<R: Read> LhaDecodeReader<R> where R::Error: fmt::Debug {
     /// with its limit set to the [`LhaHeader::compressed_size`] number of bytes.
     ///
     /// This method assumes the file will be read and decoded from its beginning.
+    ///
+    /// The header is counted as an entry, but it is not checked against the [`Limits`].
     pub fn begin_with_header_and_decoder(&mut self, header: LhaHeader, decoder: DecoderAny<Take<R>>) {
+        self.entries = self.entries.saturating_add(1);
+        self.recycle_decoder();
         self.decoder = Some(decoder);
         self.header = header;
         self.crc.reset();
         self.output_length = 0;
+        self.decoded.clear();
     }
     /// Attempt to parse the next file's header.
     ///
@@ -244,7 +466,7 @@ impl<R: Read> LhaDecodeReader<R> where R::Error: fmt::Debug {
     /// # Panics
     /// Panics when `BUF` = `0`.
     pub fn next_file_with_sink<const BUF: usize>(&mut self) -> Result<bool, LhaDecodeError<R>> {
-        let mut limited_rd = self.decoder.take().expect("decoder not empty").into_inner();
+        let mut limited_rd = self.decoder.take().expect("decoder not empty").into_inner_in(&mut self.pool);
         if limited_rd.limit() != 0 {
             if let Err(e) = discard_to_end::<_, BUF>(&mut limited_rd).map_err(LhaError::Io) {
                 return Err(wrap_err(limited_rd.into_inner(), e))
@@ -256,6 +478,53 @@ impl<R: Read> LhaDecodeReader<R> where R::Error: fmt::Debug {
     pub fn header(&self) -> &LhaHeader {
         &self.header
     }
+    /// Return a reference to the enforced resource [`Limits`].
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+    /// Replace the enforced resource [`Limits`].
+    ///
+    /// The new limits apply to the headers parsed and the content decoded from now on. The number
+    /// of entries and the total output counted so far are preserved.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+    /// Return whether the strict mode is enabled, see [`LhaDecodeReader::set_strict`].
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+    /// Enable or disable the strict mode, which is disabled by default.
+    ///
+    /// In the strict mode the CRC-16 checksum is verified by the read that reaches the end of
+    /// the current file and by every read at the end of the file. A mismatch is reported as
+    /// an [`LhaError::Checksum`] error, returned instead of the read content or `Ok(0)`, so there
+    /// is no need to call [`LhaDecodeReader::crc_check`]. Compressed data ending before the whole
+    /// file has been decoded is always reported as an unexpected EOF error.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+    /// Register the decoder `factory` for the compression method identifier `id`, e.g. `*b"-lh2-"`.
+    ///
+    /// The registered decoders are used for the files with headers parsed from now on, so in order
+    /// to apply them to the first file, create the reader with [`LhaDecodeReader::with_limits_and_registry`].
+    /// The factories take precedence over the built-in decoders. See [`DecoderRegistry::register`].
+    pub fn register_decoder<F>(&mut self, id: [u8;5], factory: F) -> bool
+        where F: DecoderFactory<Take<R>> + 'static
+    {
+        self.registry.register(id, factory)
+    }
+    /// Return a reference to the registry of the decoder factories.
+    pub fn decoder_registry(&self) -> &DecoderRegistry<Take<R>> {
+        &self.registry
+    }
+    /// Return a mutable reference to the registry of the decoder factories.
+    pub fn decoder_registry_mut(&mut self) -> &mut DecoderRegistry<Take<R>> {
+        &mut self.registry
+    }
+    /// Return the total number of bytes decoded so far from all the entries.
+    pub fn total_output(&self) -> u64 {
+        self.total_output
+    }
     /// Unwrap the underlying stream reader and return it.
     ///
     /// # Panics
@@ -270,15 +539,16 @@ impl<R: Read> LhaDecodeReader<R> where R::Error: fmt::Debug {
         self.header.original_size = 0;
         self.output_length = 0;
         self.crc.reset();
-        self.decoder.take().map(|decoder| decoder.into_inner().into_inner())
+        self.decoded.clear();
+        self.decoder.take().map(|decoder| decoder.into_inner_in(&mut self.pool).into_inner())
     }
     /// Return the number of remaining bytes of the currently decompressed file to be read.
     pub fn len(&self) -> u64 {
-        self.header.original_size - self.output_length
+        self.header.original_size - self.output_length + self.buffered().len() as u64
     }
     /// Return whether the current file has been finished reading or if the file was empty.
     pub fn is_empty(&self) -> bool {
-        self.header.original_size == self.output_length
+        self.len() == 0
     }
     /// Return whether an underlying stream reader is present in the decoder.
     pub fn is_present(&self) -> bool {
@@ -308,6 +578,29 @@ impl<R: Read> LhaDecodeReader<R> where R::Error: fmt::Debug {
             Err(LhaError::Checksum("crc16 mismatch"))
         }
     }
+    /// Read the remaining content of the current file into a new vector and verify the checksum.
+    ///
+    /// The content is decoded directly into the vector, which is allocated up front with the size
+    /// declared in the header. When reading untrusted archives make sure the [`Limits`] are
+    /// enforced, e.g. by [`Limits::max_original_size`].
+    ///
+    /// # Errors
+    /// Returns an error if the vector could not be allocated, if the content could not be decoded,
+    /// if the total output limit is exceeded or if the computed CRC-16 checksum of the whole file
+    /// doesn't match the one in the header.
+    pub fn read_entry_to_vec(&mut self) -> LhaResult<Vec<u8>, R> {
+        let len = usize::try_from(self.len()).map_err(|_| LhaError::Limit("entry original size"))?;
+        let buffered = self.buffered().len();
+        if self.total_output.saturating_add((len - buffered) as u64) > self.limits.max_total_output {
+            return Err(LhaError::Limit("total output size"))
+        }
+        let mut buf = Vec::new();
+        buf.try_reserve_exact(len).map_err(|_| LhaError::Limit("entry original size"))?;
+        buf.resize(len, 0);
+        self.decode_into(&mut buf)?;
+        self.crc_check()?;
+        Ok(buf)
+    }
     /// Return whether the current file's compression method is supported.
     ///
     /// If this method returns `false`, trying to read from the decoder will result in an error.
@@ -320,21 +613,232 @@ impl<R: Read> LhaDecodeReader<R> where R::Error: fmt::Debug {
     pub fn is_decoder_supported(&self) -> bool {
         self.decoder.as_ref().map(|d| d.is_supported()).unwrap_or(false)
     }
-}
 
-#[cfg(feature = "std")]
-impl<R: Read<Error=std::io::Error>> std::io::Read for LhaDecodeReader<R> {
-    fn read(&mut self, buf: &mut[u8]) -> std::io::Result<usize> {
+    // keeps the workspace of the replaced decoder for the next entries
+    fn recycle_decoder(&mut self) {
+        if let Some(decoder) = self.decoder.take() {
+            decoder.into_inner_in(&mut self.pool);
+        }
+    }
+
+    fn decode_into(&mut self, buf: &mut[u8]) -> LhaResult<usize, R> {
+        // the data buffered by `fill_buf` goes first
+        let buffered = self.buffered();
+        let start = buf.len().min(buffered.len());
+        if start != 0 {
+            buf[..start].copy_from_slice(&buffered[..start]);
+            self.consume_decoded(start);
+        }
+        let target = &mut buf[start..];
+        let len = if target.is_empty() {
+            0
+        }
+        else {
+            let len = self.decode_next(target)?;
+            self.crc.digest(&target[..len]);
+            len
+        };
+        self.strict_check()?;
+        Ok(start + len)
+    }
+
+    // verifies the checksum in the strict mode at the end of the file
+    fn strict_check(&self) -> LhaResult<(), R> {
+        if self.strict && self.is_empty() {
+            self.crc_check()?;
+        }
+        Ok(())
+    }
+
+    // decodes the next chunk of the file into `buf` without updating the checksum
+    fn decode_next(&mut self, buf: &mut[u8]) -> LhaResult<usize, R> {
         let len = buf.len().min((self.header.original_size - self.output_length) as usize);
+        if self.total_output.saturating_add(len as u64) > self.limits.max_total_output {
+            return Err(LhaError::Limit("total output size"))
+        }
         let target = &mut buf[..len];
         self.decoder.as_mut().unwrap().fill_buffer(target)?;
         self.output_length += len as u64;
-        self.crc.digest(target);
+        self.total_output += len as u64;
         Ok(len)
     }
+
+    // the decoded data not consumed yet, kept by the decoder or in the side buffer
+    fn buffered(&self) -> &[u8] {
+        match self.decoder.as_ref() {
+            Some(decoder) if decoder.has_history() => decoder.history_buffer(),
+            _ => self.decoded.buffered()
+        }
+    }
+
+    // the decoders keeping the history expose the decoded data directly from their ring buffers,
+    // the other ones decode into the side buffer
+    fn fill_decoded(&mut self) -> LhaResult<&[u8], R> {
+        if !self.buffered().is_empty() {
+            return Ok(self.buffered())
+        }
+        let remaining = self.header.original_size - self.output_length;
+        let len = remaining.min(DECODED_BUFFER_SIZE as u64) as usize;
+        if self.decoder.as_ref().unwrap().has_history() {
+            if self.total_output.saturating_add(len as u64) > self.limits.max_total_output {
+                return Err(LhaError::Limit("total output size"))
+            }
+            let len = self.decoder.as_mut().unwrap().fill_history(len)?.len();
+            self.output_length += len as u64;
+            self.total_output += len as u64;
+            if len == 0 {
+                self.strict_check()?;
+            }
+            return Ok(self.decoder.as_ref().unwrap().history_buffer())
+        }
+        let mut data = core::mem::take(&mut self.decoded.data);
+        if data.len() < len {
+            data = vec![0u8; DECODED_BUFFER_SIZE].into_boxed_slice();
+        }
+        let res = self.decode_next(&mut data);
+        self.decoded = DecodedBuffer { data, pos: 0, len: *res.as_ref().unwrap_or(&0) };
+        if res? == 0 {
+            self.strict_check()?;
+        }
+        Ok(self.decoded.buffered())
+    }
+
+    // the consumed data is included in the checksum
+    fn consume_decoded(&mut self, amt: usize) {
+        match self.decoder.as_mut() {
+            Some(decoder) if decoder.has_history() => {
+                let buffered = decoder.history_buffer();
+                let amt = amt.min(buffered.len());
+                self.crc.digest(&buffered[..amt]);
+                decoder.consume_history(amt);
+            }
+            _ => {
+                let buffered = self.decoded.buffered();
+                let amt = amt.min(buffered.len());
+                self.crc.digest(&buffered[..amt]);
+                self.decoded.pos += amt;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read<Error=std::io::Error>> LhaDecodeReader<R> {
+    /// Copy the remaining content of the current file to `writer` and verify the checksum.
+    /// Return the number of bytes copied.
+    ///
+    /// The content is decoded and written in chunks and the checksum is computed in the same pass.
+    /// This works with any stream, see [`LhaDecodeReader::copy_seekable_entry_to`] for copying
+    /// the content of files stored without compression directly from seekable streams.
+    ///
+    /// # Errors
+    /// Returns an error if the content could not be read, decoded or written, if the total output
+    /// limit is exceeded or if the computed CRC-16 checksum of the whole file doesn't match the one
+    /// in the header. The content has been already written when the checksum is being verified.
+    pub fn copy_entry_to<W: std::io::Write + ?Sized>(&mut self, writer: &mut W) -> LhaResult<u64, R> {
+        let total = self.len();
+        let buffered = self.buffered();
+        writer.write_all(buffered).map_err(LhaError::Io)?;
+        self.consume_decoded(buffered.len());
+        let mut buf = [0u8; 8*1024];
+        while !self.is_empty() {
+            let len = self.decode_next(&mut buf)?;
+            self.crc.digest(&buf[..len]);
+            writer.write_all(&buf[..len]).map_err(LhaError::Io)?;
+        }
+        self.crc_check()?;
+        Ok(total)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read + std::io::Seek> LhaDecodeReader<R> {
+    /// Copy the remaining content of the current file to `writer` and verify the checksum, like
+    /// [`LhaDecodeReader::copy_entry_to`]. Return the number of bytes copied.
+    ///
+    /// The content of files stored without compression (`-lh0-`, `-lz4-` and `-pm0-`) is copied
+    /// directly from the inner stream with [`std::io::copy`], which on Linux moves the data inside
+    /// the kernel with `copy_file_range` or `sendfile` when both the inner stream and the `writer`
+    /// are files, also wrapped in `BufReader` or `BufWriter`. The checksum is then computed in
+    /// a single pass over the copied range of the inner stream, which is why it must be seekable.
+    ///
+    /// The content of compressed files is decoded and written in chunks.
+    ///
+    /// # Errors
+    /// See [`LhaDecodeReader::copy_entry_to`].
+    pub fn copy_seekable_entry_to<W: std::io::Write + ?Sized>(&mut self, writer: &mut W) -> LhaResult<u64, R> {
+        if !matches!(self.decoder, Some(DecoderAny::PassthroughDecoder(..))) {
+            return self.copy_entry_to(writer)
+        }
+        let total = self.len();
+        let buffered = self.buffered();
+        writer.write_all(buffered).map_err(LhaError::Io)?;
+        self.consume_decoded(buffered.len());
+        let len = self.len();
+        if self.total_output.saturating_add(len) > self.limits.max_total_output {
+            return Err(LhaError::Limit("total output size"))
+        }
+        if let Some(DecoderAny::PassthroughDecoder(decoder)) = self.decoder.as_mut() {
+            let limited_rd = &mut decoder.inner;
+            let limit = limited_rd.limit().checked_sub(len).ok_or_else(|| LhaError::Io(R::unexpected_eof()))?;
+            copy_stored(limited_rd.get_mut(), len, writer, &mut self.crc).map_err(LhaError::Io)?;
+            limited_rd.set_limit(limit);
+        }
+        self.output_length += len;
+        self.total_output += len;
+        self.crc_check()?;
+        Ok(total)
+    }
+}
+
+// copies `len` bytes from `rd` to `writer`, then reads them again to compute the checksum
+#[cfg(feature = "std")]
+fn copy_stored<R: std::io::Read + std::io::Seek, W: std::io::Write + ?Sized>(
+        rd: &mut R,
+        len: u64,
+        writer: &mut W,
+        crc: &mut Crc16
+    ) -> std::io::Result<()>
+{
+    use std::io;
+    let start = rd.stream_position()?;
+    if io::copy(&mut io::Read::take(&mut *rd, len), writer)? != len {
+        return Err(io::ErrorKind::UnexpectedEof.into())
+    }
+    rd.seek(io::SeekFrom::Start(start))?;
+    let mut copied = io::Read::take(rd, len);
+    let mut buf = [0u8; 8*1024];
+    loop {
+        match copied.read_all(&mut buf)? {
+            0 => break,
+            n => crc.digest(&buf[..n])
+        }
+    }
+    if copied.limit() != 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into())
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+impl<R: Read<Error=std::io::Error>> std::io::Read for LhaDecodeReader<R> {
+    fn read(&mut self, buf: &mut[u8]) -> std::io::Result<usize> {
+        Ok(self.decode_into(buf)?)
+    }
 }
 
-#[cfg(not(feature = "std"))]
+#[cfg(feature = "std")]
+impl<R: Read<Error=std::io::Error>> std::io::BufRead for LhaDecodeReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        Ok(self.fill_decoded()?)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consume_decoded(amt)
+    }
+}
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 impl<R: Read> Read for LhaDecodeReader<R> where R::Error: fmt::Debug {
     type Error = LhaError<R::Error>;
 
@@ -343,28 +847,71 @@ impl<R: Read> Read for LhaDecodeReader<R> where R::Error: fmt::Debug {
     }
 
     fn read_all(&mut self, buf: &mut[u8]) -> Result<usize, Self::Error> {
-        let len = buf.len().min((self.header.original_size - self.output_length) as usize);
-        let target = &mut buf[..len];
-        self.decoder.as_mut().unwrap().fill_buffer(target)?;
-        self.output_length += len as u64;
-        self.crc.digest(target);
-        Ok(len)
+        self.decode_into(buf)
     }
 }
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+impl<R: Read> crate::stub_io::BufRead for LhaDecodeReader<R> where R::Error: fmt::Debug {
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        self.fill_decoded()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consume_decoded(amt)
+    }
+}
+
+#[cfg(feature = "alloc")]
 impl<R: Read> DecoderAny<R> {
     /// Creates an instance of `DecoderAny<Take<R>>` from the given `LhaHeader` reference and a stream reader.
     pub fn new_from_header(header: &LhaHeader, rd: R) -> DecoderAny<Take<R>> {
+        DecoderAny::new_from_header_in(header, rd, &mut WorkspacePool::default(), &DecoderRegistry::default())
+    }
+    /// Creates an instance of `DecoderAny<Take<R>>` from the given `LhaHeader` reference and a stream reader,
+    /// using the decoder factory from the `registry` if one is registered for the header's compression method.
+    pub fn new_from_header_with_registry(
+            header: &LhaHeader,
+            rd: R,
+            registry: &DecoderRegistry<Take<R>>
+        ) -> DecoderAny<Take<R>>
+    {
+        DecoderAny::new_from_header_in(header, rd, &mut WorkspacePool::default(), registry)
+    }
+    /// Creates an instance of `DecoderAny<R>` from the given compression method and a stream reader.
+    pub fn new_from_compression(
+            compression: CompressionMethod,
+            rd: R
+        ) -> Self
+    {
+        DecoderAny::new_from_compression_in(compression, rd, &mut WorkspacePool::default())
+    }
+
+    fn new_from_header_in(
+            header: &LhaHeader,
+            rd: R,
+            pool: &mut WorkspacePool,
+            registry: &DecoderRegistry<Take<R>>
+        ) -> DecoderAny<Take<R>>
+    {
         let limited_rd = rd.take(header.compressed_size);
+        if let Some(factory) = registry.get(&header.compression) {
+            return DecoderAny::BoxedDecoder(factory.new_decoder(header, limited_rd))
+        }
         match header.compression_method() {
-            Ok(compression) => DecoderAny::new_from_compression(compression, limited_rd),
+            Ok(compression) => DecoderAny::new_from_compression_in(compression, limited_rd, pool),
             Err(..) => DecoderAny::UnsupportedDecoder(UnsupportedDecoder::new(limited_rd))
         }
     }
-    /// Creates an instance of `DecoderAny<R>` from the given compression method and a stream reader.
-    pub fn new_from_compression(
+
+    /// Creates an instance of `DecoderAny<R>` from the given compression method and a stream reader,
+    /// taking the workspace from the `pool` or allocating a new one if the pool has none.
+    ///
+    /// Return the workspace to the pool with [`DecoderAny::into_inner_in`].
+    pub fn new_from_compression_in(
             compression: CompressionMethod,
-            rd: R
+            rd: R,
+            pool: &mut WorkspacePool
         ) -> Self
     {
         match compression {
@@ -372,26 +919,77 @@ impl<R: Read> DecoderAny<R> {
             CompressionMethod::Lz4|
             CompressionMethod::Lh0 => DecoderAny::PassthroughDecoder(PassthroughDecoder::new(rd)),
             #[cfg(feature = "lz")]
-            CompressionMethod::Lzs => DecoderAny::LzsDecoder(LzsDecoder::new(rd)),
+            CompressionMethod::Lzs => DecoderAny::LzsDecoder(
+                LzsDecoder::with_workspace(rd, pool.lzs.take().unwrap_or_default())),
             #[cfg(feature = "lz")]
-            CompressionMethod::Lz5 => DecoderAny::Lz5Decoder(Lz5Decoder::new(rd)),
+            CompressionMethod::Lz5 => DecoderAny::Lz5Decoder(
+                Lz5Decoder::with_workspace(rd, pool.lz5.take().unwrap_or_default())),
             #[cfg(feature = "lh1")]
-            CompressionMethod::Lh1 => DecoderAny::Lh1Decoder(Lh1Decoder::new(rd)),
-            CompressionMethod::Lh4 => DecoderAny::Lh4Decoder(Lh5Decoder::new(rd)),
-            CompressionMethod::Lh5 => DecoderAny::Lh5Decoder(Lh5Decoder::new(rd)),
-            CompressionMethod::Lh6 => DecoderAny::Lh6Decoder(Lh7Decoder::new(rd)),
-            CompressionMethod::Lh7 => DecoderAny::Lh7Decoder(Lh7Decoder::new(rd)),
+            CompressionMethod::Lh1 => DecoderAny::Lh1Decoder(
+                Lh1Decoder::with_workspace(rd, pool.lh1.take().unwrap_or_default())),
+            CompressionMethod::Lh4 => DecoderAny::Lh4Decoder(
+                Lh5Decoder::with_workspace(rd, pool.lh5.take().unwrap_or_default())),
+            CompressionMethod::Lh5 => DecoderAny::Lh5Decoder(
+                Lh5Decoder::with_workspace(rd, pool.lh5.take().unwrap_or_default())),
+            CompressionMethod::Lh6 => DecoderAny::Lh6Decoder(
+                Lh7Decoder::with_workspace(rd, pool.lh7.take().unwrap_or_default())),
+            CompressionMethod::Lh7 => DecoderAny::Lh7Decoder(
+                Lh7Decoder::with_workspace(rd, pool.lh7.take().unwrap_or_default())),
             #[cfg(feature = "lhx")]
-            CompressionMethod::Lhx => DecoderAny::LhxDecoder(LhxDecoder::new(rd)),
+            CompressionMethod::Lhx => DecoderAny::LhxDecoder(
+                LhxDecoder::with_workspace(rd, pool.lhx.take().unwrap_or_default())),
             _ => DecoderAny::UnsupportedDecoder(UnsupportedDecoder::new(rd))
         }
     }
+
+    /// Unwraps and returns the inner reader, putting the decoder's workspace into the `pool`.
+    pub fn into_inner_in(self, pool: &mut WorkspacePool) -> R {
+        fn keep<R, W>((rd, workspace): (R, W), slot: &mut Option<W>) -> R {
+            *slot = Some(workspace);
+            rd
+        }
+        match self {
+            DecoderAny::PassthroughDecoder(decoder) => decoder.inner,
+            DecoderAny::UnsupportedDecoder(decoder) => decoder.inner,
+            #[cfg(feature = "lz")]
+            DecoderAny::LzsDecoder(decoder) => keep(decoder.into_parts(), &mut pool.lzs),
+            #[cfg(feature = "lz")]
+            DecoderAny::Lz5Decoder(decoder) => keep(decoder.into_parts(), &mut pool.lz5),
+            #[cfg(feature = "lh1")]
+            DecoderAny::Lh1Decoder(decoder) => keep(decoder.into_parts(), &mut pool.lh1),
+            DecoderAny::Lh4Decoder(decoder)|
+            DecoderAny::Lh5Decoder(decoder) => keep(decoder.into_parts(), &mut pool.lh5),
+            DecoderAny::Lh6Decoder(decoder)|
+            DecoderAny::Lh7Decoder(decoder) => keep(decoder.into_parts(), &mut pool.lh7),
+            #[cfg(feature = "lhx")]
+            DecoderAny::LhxDecoder(decoder) => keep(decoder.into_parts(), &mut pool.lhx),
+            DecoderAny::BoxedDecoder(decoder) => decoder.into_inner_boxed(),
+        }
+    }
     /// Returns `true` if the decoder is able to decode the file's content.
     pub fn is_supported(&self) -> bool {
         !matches!(self, DecoderAny::UnsupportedDecoder(..))
     }
+
+    // whether the decoder implements `BufDecoder`
+    fn has_history(&self) -> bool {
+        buf_decoder_dispatch!((self)(..) => true, _ => false)
+    }
+
+    fn fill_history(&mut self, max: usize) -> Result<&[u8], LhaError<R::Error>> where R::Error: fmt::Debug {
+        buf_decoder_dispatch!((self)(decoder) => decoder.fill_buf(max), _ => Ok(&[]))
+    }
+
+    fn consume_history(&mut self, amt: usize) where R::Error: fmt::Debug {
+        buf_decoder_dispatch!((self)(decoder) => decoder.consume(amt), _ => {})
+    }
+
+    fn history_buffer(&self) -> &[u8] where R::Error: fmt::Debug {
+        buf_decoder_dispatch!((self)(decoder) => decoder.buffer(), _ => &[])
+    }
 }
 
+#[cfg(feature = "alloc")]
 impl<R: Read> Decoder<R> for DecoderAny<R> where R::Error: fmt::Debug {
     type Error = R::Error;
 
@@ -405,6 +1003,53 @@ impl<R: Read> Decoder<R> for DecoderAny<R> where R::Error: fmt::Debug {
     }
 }
 
+/// Decode the whole `compressed` content of a file with the given `compression` method into `out`
+/// and return the CRC-16 checksum of the decoded data.
+///
+/// The length of `out` should be the original size of the file. The data is decoded directly into
+/// `out`, which serves as the most recent history of the decoder, so only the bytes preceding it are
+/// read from the decoder's ring buffer and only the last bytes of `out`, up to the size of the ring
+/// buffer, are copied there when finished. The returned checksum should be compared with the
+/// [`LhaHeader::file_crc`] value, see also [`LhaSliceEntry::decode_to_slice`][crate::LhaSliceEntry::decode_to_slice].
+///
+/// The decoder's workspace is allocated on each call, use [`decode_to_slice_in`] to decode many files.
+///
+/// # Errors
+/// Returns an error if the compression method is not supported, if the compressed data is malformed
+/// or if it ends before `out` is filled.
+#[cfg(feature = "alloc")]
+pub fn decode_to_slice<'a>(
+        compression: CompressionMethod,
+        compressed: &'a [u8],
+        out: &mut [u8]
+    ) -> LhaResult<u16, &'a [u8]>
+{
+    decode_to_slice_in(compression, compressed, out, &mut WorkspacePool::default())
+}
+
+/// Decode the whole `compressed` content of a file into `out` like [`decode_to_slice`], taking
+/// the decoder's workspace from the `pool` and returning it there afterwards.
+///
+/// # Errors
+/// Returns an error if the compression method is not supported, if the compressed data is malformed
+/// or if it ends before `out` is filled.
+#[cfg(feature = "alloc")]
+pub fn decode_to_slice_in<'a>(
+        compression: CompressionMethod,
+        compressed: &'a [u8],
+        out: &mut [u8],
+        pool: &mut WorkspacePool
+    ) -> LhaResult<u16, &'a [u8]>
+{
+    let mut decoder = DecoderAny::new_from_compression_in(compression, compressed, pool);
+    let res = decoder.fill_buffer(out);
+    decoder.into_inner_in(pool);
+    res?;
+    let mut crc = Crc16::default();
+    crc.digest(out);
+    Ok(crc.sum16())
+}
+
 impl<R: Read> PassthroughDecoder<R> {
     pub fn new(inner: R) -> Self {
         PassthroughDecoder { inner }
@@ -443,6 +1088,7 @@ impl<R: Read> Decoder<R> for UnsupportedDecoder<R> where R::Error: fmt::Debug {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<R: Read> LhaDecodeError<R> {
     /// Gets a reference to the contained reader.
     pub fn get_ref(&self) -> &R {
@@ -467,6 +1113,7 @@ impl<R: Read> std::error::Error for LhaDecodeError<R>
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<R: Read> fmt::Debug for LhaDecodeError<R>
     where LhaError<R::Error>: fmt::Debug
 {
@@ -477,6 +1124,7 @@ impl<R: Read> fmt::Debug for LhaDecodeError<R>
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<R: Read> fmt::Display for LhaDecodeError<R>
     where LhaError<R::Error>: fmt::Display
 {
@@ -485,6 +1133,7 @@ impl<R: Read> fmt::Display for LhaDecodeError<R>
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<R: Read> From<LhaDecodeError<R>> for LhaError<R::Error> {
     fn from(e: LhaDecodeError<R>) -> Self {
         e.source
@@ -500,6 +1149,7 @@ impl<R: Read> From<LhaDecodeError<R>> for std::io::Error
     }
 }
 
+#[cfg(feature = "alloc")]
 fn wrap_err<R: Read>(read: R, source: LhaError<R::Error>) -> LhaDecodeError<R> {
     LhaDecodeError { read, source }
 }
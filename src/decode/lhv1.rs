@@ -1,9 +1,10 @@
+use core::borrow::BorrowMut;
 use core::num::NonZeroU16;
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 use alloc::boxed::Box;
 use crate::error::LhaResult;
 use crate::stub_io::Read;
-use crate::decode::Decoder;
+use crate::decode::{BufDecoder, Decoder, take_buffered};
 use crate::ringbuf::*;
 use crate::bitstream::*;
 
@@ -13,30 +14,76 @@ use dyntree::*;
 const RING_BUFFER_SIZE: usize = 4096;
 
 /// A decoder for `-lh1-` compression method.
+///
+/// The decoder keeps its history and the Huffman tree in the workspace `W`, see [`Lh1Workspace`].
+/// `W` defaults to `Box<Lh1Workspace>` only with the `alloc` feature.
 #[derive(Debug)]
-pub struct Lh1Decoder<R> {
+pub struct Lh1Decoder<R, #[cfg(feature = "alloc")] W = Box<Lh1Workspace>, #[cfg(not(feature = "alloc"))] W> {
     bit_reader: BitStream<R>,
-    command_tree: Box<DynHuffTree>,
     copy_progress: Option<(u16, NonZeroU16)>,
-    ringbuf: Box<RingArrayBuf<RING_BUFFER_SIZE>>,
+    buffered: usize,
+    workspace: W,
 }
 
+/// The work buffers of [`Lh1Decoder`] (about 12 kB): the history ring buffer and the dynamic Huffman tree.
+///
+/// The workspace can be created in a `static` or on the stack and lent to the decoder with
+/// [`Lh1Decoder::with_workspace`], so decoding requires no allocations.
+#[derive(Debug, Clone)]
+pub struct Lh1Workspace {
+    command_tree: DynHuffTree,
+    ringbuf: RingArrayBuf<RING_BUFFER_SIZE>,
+}
+
+impl Lh1Workspace {
+    /// Creates a new workspace.
+    pub const fn new() -> Self {
+        Lh1Workspace {
+            command_tree: DynHuffTree::EMPTY,
+            ringbuf: RingArrayBuf::new()
+        }
+    }
+}
+
+impl Default for Lh1Workspace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
 impl<R: Read> Lh1Decoder<R> {
+    /// Creates a new decoder with the workspace allocated on the heap.
     pub fn new(rd: R) -> Lh1Decoder<R> {
+        Self::with_workspace(rd, Box::default())
+    }
+}
+
+impl<R: Read, W: BorrowMut<Lh1Workspace>> Lh1Decoder<R, W> {
+    /// Creates a new decoder with the given `workspace`, e.g. `&mut Lh1Workspace`.
+    ///
+    /// The workspace is reset, so it can be reused by subsequent decoders.
+    pub fn with_workspace(rd: R, mut workspace: W) -> Lh1Decoder<R, W> {
         let bit_reader = BitStream::new(rd);
-        let ringbuf = Default::default();
-        let command_tree = Box::new(DynHuffTree::new());
+        let ws = workspace.borrow_mut();
+        ws.command_tree.reset();
+        ws.ringbuf.reset();
         Lh1Decoder {
             bit_reader,
-            ringbuf,
-            command_tree,
-            copy_progress: None
+            workspace,
+            copy_progress: None,
+            buffered: 0
         }
     }
 
+    /// Unwraps and returns the inner reader and the workspace.
+    pub fn into_parts(self) -> (R, W) {
+        (self.bit_reader.into_inner(), self.workspace)
+    }
+
     #[inline]
     fn read_command(&mut self) -> LhaResult<u16, R> {
-        self.command_tree.read_entry(&mut self.bit_reader)
+        self.workspace.borrow_mut().command_tree.read_entry(&mut self.bit_reader)
     }
 
     #[inline]
@@ -47,61 +94,78 @@ impl<R: Read> Lh1Decoder<R> {
         Ok(offset)
     }
 
-    fn copy_from_history<'a, I: Iterator<Item=&'a mut u8> + ExactSizeIterator>(
-            &mut self,
-            target: I,
-            offset: usize,
-            count: usize
-        ) -> LhaResult<(), R>
+    /// Copies up to `count` bytes from history to `target` at `index` and returns the index past the
+    /// copied bytes. Remembers the number of bytes which didn't fit in `target`.
+    ///
+    /// The bytes of `target` preceding `index` are the most recent history, the older one is in the
+    /// ring buffer.
+    #[inline]
+    fn copy_from_history<T>(&mut self, target: &mut T, index: usize, offset: usize, count: usize) -> usize
+        where T: HistoryTarget<RingArrayBuf<RING_BUFFER_SIZE>>
     {
-        let history_iter = self.ringbuf.iter_from_offset(offset);
-        let count_after = count - target.len().min(count);
-        for (t, s) in target.zip(history_iter).take(count) {
-            *t = s;
-        }
+        let end = target.copy_from_offset(&mut self.workspace.borrow_mut().ringbuf, offset, index, count);
+        let count_after = count - (end - index);
         self.copy_progress = NonZeroU16::new(count_after as u16)
                              .map(|count| (offset as u16, count));
-        Ok(())
+        end
     }
-}
 
-impl<R: Read> Decoder<R> for Lh1Decoder<R> where R::Error: core::fmt::Debug {
-    type Error = R::Error;
-
-    fn into_inner(self) -> R {
-        self.bit_reader.into_inner()
-    }
-
-    fn fill_buffer(&mut self, buf: &mut[u8]) -> LhaResult<(), R> {
-        let buflen = buf.len();
-        let mut target = buf.iter_mut();
+    fn fill_target<T>(&mut self, mut target: T) -> LhaResult<(), R>
+        where T: HistoryTarget<RingArrayBuf<RING_BUFFER_SIZE>>, R::Error: core::fmt::Debug
+    {
+        let mut index = 0;
         if let Some((offset, count)) = self.copy_progress {
-            self.copy_from_history(&mut target,
-                                   offset as usize,
-                                   count.get() as usize)?;
+            index = self.copy_from_history(&mut target, index, offset as usize, count.get() as usize);
         }
-
-        while let Some(dst) = target.next() {
+        while index < target.size() {
             match self.read_command()? {
                 code @ 0..=0xff => {
-                    let value = code as u8;
-                    *dst = value;
-                    self.ringbuf.push(value);
+                    target.set(&mut self.workspace.borrow_mut().ringbuf, index, code as u8);
+                    index += 1;
                 }
                 count => {
                     let offset = self.read_offset()?;
-                    let index = buflen - target.len() - 1;
-                    target = buf[index..].iter_mut();
-                    self.copy_from_history(&mut target,
-                                           offset as usize,
-                                           (count - 0x100 + 3).into())?;
+                    index = self.copy_from_history(&mut target, index, offset as usize, (count - 0x100 + 3).into());
                 }
             }
         }
+        target.finish(&mut self.workspace.borrow_mut().ringbuf);
         Ok(())
     }
 }
 
+impl<R: Read, W: BorrowMut<Lh1Workspace>> Decoder<R> for Lh1Decoder<R, W> where R::Error: core::fmt::Debug {
+    type Error = R::Error;
+
+    fn into_inner(self) -> R {
+        self.bit_reader.into_inner()
+    }
+
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> LhaResult<(), R> {
+        let start = take_buffered(self, buf);
+        self.fill_target(&mut buf[start..])
+    }
+}
+
+impl<R: Read, W: BorrowMut<Lh1Workspace>> BufDecoder<R> for Lh1Decoder<R, W> where R::Error: core::fmt::Debug {
+    fn fill_buf(&mut self, max: usize) -> LhaResult<&[u8], R> {
+        if self.buffered == 0 {
+            let len = max.min(self.workspace.borrow().ringbuf.span_capacity());
+            self.fill_target(RingSpan(len))?;
+            self.buffered = len;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.buffered -= amt.min(self.buffered);
+    }
+
+    fn buffer(&self) -> &[u8] {
+        self.workspace.borrow().ringbuf.recent(self.buffered)
+    }
+}
+
 /// Returns (incomplete offset, additional bits to read)
 #[inline(always)]
 fn decode_offset(bits9: u16) -> (u16, u32) {
@@ -139,5 +203,6 @@ mod tests {
         println!("Lh1Decoder<Empty> {}", core::mem::size_of::<Lh1Decoder<io::Empty>>());
         println!("Lh1Decoder<File> {}", core::mem::size_of::<Lh1Decoder<fs::File>>());
         println!("DynHuffTree {}", core::mem::size_of::<DynHuffTree>());
+        println!("Lh1Workspace {}", core::mem::size_of::<Lh1Workspace>());
     }
 }
@@ -1,7 +1,7 @@
 //! # Dynamic Huffman Coding.
 use core::fmt;
-#[cfg(not(feature = "std"))]
-use alloc::{string::String};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::String;
 use crate::error::LhaError;
 use crate::bitstream::BitRead;
 use crate::statictree::entry::*;
@@ -42,12 +42,7 @@ struct TreeNode {
 impl Default for TreeNode {
     /// Creates an invalid node (a branch pointing to the root) by default.
     fn default() -> TreeNode {
-        TreeNode {
-            entry: TreeEntry::branch(0).unwrap(),
-            freq: 0,
-            parent: 0,
-            group: 0
-        }
+        TreeNode::EMPTY
     }
 }
 
@@ -72,13 +67,6 @@ impl fmt::Debug for Groups {
 }
 
 impl Groups {
-    fn new() -> Self {
-        let groups = [0u16; NUM_NODES];
-        let mut res = Groups { ngroups: 0, groups, leaders: groups };
-        res.reset();
-        res
-    }
-
     fn reset(&mut self) {
         self.ngroups = 0;
         for (p, n) in self.groups.iter_mut().zip(0u16..) {
@@ -177,6 +165,14 @@ impl LeaveIndex {
 }
 
 impl TreeNode {
+    /// An invalid node (a branch pointing to the root).
+    const EMPTY: TreeNode = TreeNode {
+        entry: TreeEntry::ROOT_BRANCH,
+        freq: 0,
+        parent: 0,
+        group: 0
+    };
+
     fn new_leaf(value: u16, group: u16) -> Self {
         debug_assert!((value as usize) < NUM_LEAVES);
         let entry = TreeEntry::leaf(value);
@@ -206,12 +202,29 @@ impl TreeNode {
 }
 
 impl DynHuffTree {
+    /// An uninitialized tree, [DynHuffTree::reset] must be called before reading from it.
+    pub const EMPTY: DynHuffTree = DynHuffTree {
+        nodes: [TreeNode::EMPTY; NUM_NODES],
+        leaves: LeaveIndex([0; NUM_LEAVES]),
+        groups: Groups { ngroups: 0, groups: [0; NUM_NODES], leaders: [0; NUM_NODES] }
+    };
+
+    #[allow(dead_code)]
     pub fn new() -> Self {
-        let mut groups = Groups::new();
-        let mut nodes = [TreeNode::default(); NUM_NODES];
-        let leaves: [u16; NUM_LEAVES] = core::array::from_fn(|value| {
-            (NUM_NODES - 1) as u16 - value as u16
-        });
+        let mut tree = Self::EMPTY;
+        tree.reset();
+        tree
+    }
+
+    /// Initializes the tree in place to the initial state.
+    pub fn reset(&mut self) {
+        let groups = &mut self.groups;
+        groups.reset();
+        let nodes = &mut self.nodes;
+        nodes.fill(TreeNode::EMPTY);
+        for (leaf, value) in self.leaves.0.iter_mut().zip(0..) {
+            *leaf = (NUM_NODES - 1) as u16 - value;
+        }
 
         let mut last_group = groups.allocate();
 
@@ -252,12 +265,6 @@ impl DynHuffTree {
             tail_len -= parent_len;
             rest = head;
         }
-
-        DynHuffTree {
-            nodes,
-            leaves: LeaveIndex(leaves),
-            groups,
-        }
     }
 
     #[inline(never)]
@@ -459,6 +466,7 @@ impl DynHuffTree {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl fmt::Display for DynHuffTree {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
@@ -613,7 +621,7 @@ mod tests {
         println!("--------------\n{}", tree);
 
         // now with some random bit stream
-        let rnd_stream: &mut (dyn RngCore) = rng;
+        let rnd_stream: &mut dyn RngCore = rng;
         let mut rndstream = BitStream::new(rnd_stream);
         for _ in 0..1_000_000 {
             tree.read_entry(&mut rndstream).unwrap();
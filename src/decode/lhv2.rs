@@ -1,5 +1,6 @@
+use core::borrow::BorrowMut;
 use core::num::NonZeroU32;
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 use alloc::boxed::Box;
 use crate::error::{LhaResult, LhaError};
 use crate::stub_io::Read;
@@ -7,7 +8,7 @@ use crate::bitstream::*;
 use crate::statictree::*;
 use crate::ringbuf::*;
 
-use super::Decoder;
+use super::{BufDecoder, Decoder, take_buffered};
 
 const NUM_COMMANDS: usize = 510;
 const NUM_TEMP_CODELEN: usize = 20;
@@ -19,14 +20,31 @@ pub trait LhaDecoderConfig {
 }
 
 /// A generic decoder for LHArc version 2 compression methods.
+///
+/// The decoder keeps its history and Huffman trees in the workspace `W`, see [`LhaV2Workspace`].
+/// With the `alloc` feature `W` defaults to a `Box`, otherwise it has to be specified, e.g.
+/// `&'static mut Lh5Workspace`.
 #[derive(Debug)]
-pub struct LhaV2Decoder<C: LhaDecoderConfig, R> {
+pub struct LhaV2Decoder<C: LhaDecoderConfig, R,
+                        #[cfg(feature = "alloc")] W = Box<LhaV2Workspace<C>>,
+                        #[cfg(not(feature = "alloc"))] W> {
     bit_reader: BitStream<R>,
-    command_tree: HuffTree,
-    offset_tree: HuffTree,
     remaining_commands: u16,
     copy_progress: Option<(u32, NonZeroU32)>,
-    ringbuf: Box<C::RingBuffer>,
+    buffered: usize,
+    workspace: W,
+    _config: core::marker::PhantomData<C>
+}
+
+/// The work buffers of [`LhaV2Decoder`]: the history ring buffer and the Huffman trees.
+///
+/// The workspace can be created in a `static` or on the stack and lent to the decoder with
+/// [`LhaV2Decoder::with_workspace`], so decoding requires no allocations.
+#[derive(Debug, Clone)]
+pub struct LhaV2Workspace<C: LhaDecoderConfig> {
+    ringbuf: C::RingBuffer,
+    command_tree: HuffTree<{NUM_COMMANDS * 2}>,
+    offset_tree: HuffTree<{NUM_TEMP_CODELEN * 2}>,
 }
 
 macro_rules! impl_lhav2_decoder {
@@ -48,30 +66,68 @@ impl_lhav2_decoder!(Lh7DecoderCfg, HISTORY_BITS=17, OFFSET_BITS=5);
 impl_lhav2_decoder!(LhxDecoderCfg, HISTORY_BITS=20, OFFSET_BITS=5);
 
 /// A decoder for `-lh4-` and `-lh5-` compression methods.
-pub type Lh5Decoder<R> = LhaV2Decoder<Lh5DecoderCfg, R>;
+pub type Lh5Decoder<R, #[cfg(feature = "alloc")] W = Box<Lh5Workspace>, #[cfg(not(feature = "alloc"))] W> = LhaV2Decoder<Lh5DecoderCfg, R, W>;
 /// A decoder for `-lh6-` and `-lh7-` compression methods.
-pub type Lh7Decoder<R> = LhaV2Decoder<Lh7DecoderCfg, R>;
+pub type Lh7Decoder<R, #[cfg(feature = "alloc")] W = Box<Lh7Workspace>, #[cfg(not(feature = "alloc"))] W> = LhaV2Decoder<Lh7DecoderCfg, R, W>;
 /// A decoder for `-lhx-` compression methods.
 #[cfg(feature = "lhx")]
-pub type LhxDecoder<R> = LhaV2Decoder<LhxDecoderCfg, R>;
+pub type LhxDecoder<R, #[cfg(feature = "alloc")] W = Box<LhxWorkspace>, #[cfg(not(feature = "alloc"))] W> = LhaV2Decoder<LhxDecoderCfg, R, W>;
+
+/// A workspace of [`Lh5Decoder`] (about 18 kB).
+pub type Lh5Workspace = LhaV2Workspace<Lh5DecoderCfg>;
+/// A workspace of [`Lh7Decoder`] (about 74 kB).
+pub type Lh7Workspace = LhaV2Workspace<Lh7DecoderCfg>;
+/// A workspace of [`LhxDecoder`] (about 522 kB).
+#[cfg(feature = "lhx")]
+pub type LhxWorkspace = LhaV2Workspace<LhxDecoderCfg>;
+
+impl<C: LhaDecoderConfig> LhaV2Workspace<C> {
+    /// Creates a new workspace.
+    pub const fn new() -> Self {
+        LhaV2Workspace {
+            ringbuf: C::RingBuffer::NEW,
+            command_tree: HuffTree::new(),
+            offset_tree: HuffTree::new()
+        }
+    }
+}
 
+impl<C: LhaDecoderConfig> Default for LhaV2Workspace<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
+#[cfg(feature = "alloc")]
 impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
+    /// Creates a new decoder with the workspace allocated on the heap.
     pub fn new(rd: R) -> LhaV2Decoder<C, R> {
+        Self::with_workspace(rd, Box::default())
+    }
+}
+
+impl<C: LhaDecoderConfig, R: Read, W: BorrowMut<LhaV2Workspace<C>>> LhaV2Decoder<C, R, W> {
+    /// Creates a new decoder with the given `workspace`, e.g. `&mut LhaV2Workspace`.
+    ///
+    /// The workspace is reset, so it can be reused by subsequent decoders.
+    pub fn with_workspace(rd: R, mut workspace: W) -> LhaV2Decoder<C, R, W> {
         let bit_reader = BitStream::new(rd);
-        let ringbuf = Default::default();
-        let command_tree = HuffTree::with_capacity(NUM_COMMANDS * 2);
-        let offset_tree = HuffTree::with_capacity(NUM_TEMP_CODELEN * 2);
+        workspace.borrow_mut().ringbuf.reset();
         LhaV2Decoder {
             bit_reader,
-            ringbuf,
-            command_tree,
-            offset_tree,
             remaining_commands: 0,
-            copy_progress: None
+            copy_progress: None,
+            buffered: 0,
+            workspace,
+            _config: core::marker::PhantomData
         }
     }
 
+    /// Unwraps and returns the inner reader and the workspace.
+    pub fn into_parts(self) -> (R, W) {
+        (self.bit_reader.into_inner(), self.workspace)
+    }
+
     // reads code length value, usually 0..=7 but might be higher
     fn read_code_length(&mut self) -> LhaResult<u8, R> {
         let mut len: u8 = self.bit_reader.read_bits(3)?;
@@ -104,7 +160,7 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
         // single code only
         if num_codes == 0 {
             let code = self.bit_reader.read_bits(5)?;
-            self.offset_tree.set_single(code);
+            self.workspace.borrow_mut().offset_tree.set_single(code);
             return Ok(());
         }
 
@@ -129,7 +185,7 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
             // println!("length: {:?}", *p);
         }
 
-        self.offset_tree.build_tree(&code_lengths[0..num_codes])
+        self.workspace.borrow_mut().offset_tree.build_tree(&code_lengths[0..num_codes])
             .map_err(LhaError::Decompress)?;
         Ok(())
     }
@@ -144,7 +200,7 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
         // single code only
         if num_codes == 0 {
             let code = self.bit_reader.read_bits(9)?;
-            self.command_tree.set_single(code);
+            self.workspace.borrow_mut().command_tree.set_single(code);
             return Ok(());
         }
 
@@ -155,7 +211,7 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
         let mut index = 0;
         'outer: while index < num_codes {
             for (n, p) in code_lengths[index..num_codes].iter_mut().enumerate() {
-                match self.offset_tree.read_entry(&mut self.bit_reader)? {
+                match self.workspace.borrow().offset_tree.read_entry(&mut self.bit_reader)? {
                     skip_range @ 0..=2 => {
                         let skip_count = self.read_code_skip(skip_range)?;
                         // println!("n: {} skip: {}", n + index, skip_count);
@@ -171,7 +227,7 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
             break;
         }
 
-        self.command_tree.build_tree(&code_lengths[0..num_codes])
+        self.workspace.borrow_mut().command_tree.build_tree(&code_lengths[0..num_codes])
             .map_err(LhaError::Decompress)?;
         Ok(())
     }
@@ -187,7 +243,7 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
         // single code only
         if num_codes == 0 {
             let code = self.bit_reader.read_bits(C::OFFSET_BITS)?;
-            self.offset_tree.set_single(code);
+            self.workspace.borrow_mut().offset_tree.set_single(code);
             return Ok(());
         }
 
@@ -201,7 +257,7 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
             // println!("length: {}", *p);
         }
 
-        self.offset_tree.build_tree(&code_lengths[0..num_codes])
+        self.workspace.borrow_mut().offset_tree.build_tree(&code_lengths[0..num_codes])
             .map_err(LhaError::Decompress)?;
         Ok(())
     }
@@ -215,12 +271,12 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
 
     #[inline]
     fn read_command(&mut self) -> LhaResult<u16, R> {
-        self.command_tree.read_entry(&mut self.bit_reader)
+        self.workspace.borrow().command_tree.read_entry(&mut self.bit_reader)
     }
 
     #[inline]
     fn read_offset(&mut self) -> LhaResult<u32, R> {
-        match self.offset_tree.read_entry(&mut self.bit_reader)?.into() {
+        match self.workspace.borrow().offset_tree.read_entry(&mut self.bit_reader)?.into() {
         //   bits => 0 ->    0
         //   bits => 1 ->    1
             res @ 0..=1 => Ok(res),
@@ -234,44 +290,30 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
         }
     }
 
-    fn copy_from_history<'a, I: Iterator<Item=&'a mut u8> + ExactSizeIterator>(
-            &mut self,
-            target: I,
-            offset: usize,
-            count: usize
-        ) -> LhaResult<(), R>
+    /// Copies up to `count` bytes from history to `target` at `index` and returns the index past the
+    /// copied bytes. Remembers the number of bytes which didn't fit in `target`.
+    ///
+    /// The bytes of `target` preceding `index` are the most recent history, the older one is in the
+    /// ring buffer.
+    #[inline]
+    fn copy_from_history<T>(&mut self, target: &mut T, index: usize, offset: usize, count: usize) -> usize
+        where T: HistoryTarget<C::RingBuffer>
     {
-        let history_iter = self.ringbuf.iter_from_offset(offset);
-        let count_after = count - target.len().min(count);
-        for (t, s) in target.zip(history_iter).take(count) {
-            *t = s;
-        }
+        let end = target.copy_from_offset(&mut self.workspace.borrow_mut().ringbuf, offset, index, count);
+        let count_after = count - (end - index);
         self.copy_progress = NonZeroU32::new(count_after as u32)
                              .map(|count| (offset as u32, count));
-        Ok(())
-    }
-
-}
-
-impl<C: LhaDecoderConfig, R: Read> Decoder<R> for LhaV2Decoder<C, R>
-    where R::Error: core::fmt::Debug
-{
-    type Error = R::Error;
-
-    fn into_inner(self) -> R {
-        self.bit_reader.into_inner()
+        end
     }
 
-    fn fill_buffer(&mut self, buf: &mut[u8]) -> LhaResult<(), R> {
-        let buflen = buf.len();
-        let mut target = buf.iter_mut();
+    fn fill_target<T>(&mut self, mut target: T) -> LhaResult<(), R>
+        where T: HistoryTarget<C::RingBuffer>, R::Error: core::fmt::Debug
+    {
+        let mut index = 0;
         if let Some((offset, count)) = self.copy_progress {
-            self.copy_from_history(&mut target,
-                                   offset as usize,
-                                   count.get() as usize)?;
+            index = self.copy_from_history(&mut target, index, offset as usize, count.get() as usize);
         }
-
-        while let Some(dst) = target.next() {
+        while index < target.size() {
             while self.remaining_commands == 0 {
                 self.begin_new_block()?;
             }
@@ -280,24 +322,56 @@ impl<C: LhaDecoderConfig, R: Read> Decoder<R> for LhaV2Decoder<C, R>
 
             match self.read_command()? {
                 code @ 0..=0xff => {
-                    let value = code as u8;
-                    *dst = value;
-                    self.ringbuf.push(value);
+                    target.set(&mut self.workspace.borrow_mut().ringbuf, index, code as u8);
+                    index += 1;
                 }
                 count => {
                     let offset = self.read_offset()?;
-                    let index = buflen - target.len() - 1;
-                    target = buf[index..].iter_mut();
-                    self.copy_from_history(&mut target,
-                                           offset as usize,
-                                           (count - 0x100 + 3).into())?;
+                    index = self.copy_from_history(&mut target, index, offset as usize, (count - 0x100 + 3).into());
                 }
             }
         }
+        target.finish(&mut self.workspace.borrow_mut().ringbuf);
         Ok(())
     }
 }
 
+impl<C: LhaDecoderConfig, R: Read, W: BorrowMut<LhaV2Workspace<C>>> Decoder<R> for LhaV2Decoder<C, R, W>
+    where R::Error: core::fmt::Debug
+{
+    type Error = R::Error;
+
+    fn into_inner(self) -> R {
+        self.bit_reader.into_inner()
+    }
+
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> LhaResult<(), R> {
+        let start = take_buffered(self, buf);
+        self.fill_target(&mut buf[start..])
+    }
+}
+
+impl<C: LhaDecoderConfig, R: Read, W: BorrowMut<LhaV2Workspace<C>>> BufDecoder<R> for LhaV2Decoder<C, R, W>
+    where R::Error: core::fmt::Debug
+{
+    fn fill_buf(&mut self, max: usize) -> LhaResult<&[u8], R> {
+        if self.buffered == 0 {
+            let len = max.min(self.workspace.borrow().ringbuf.span_capacity());
+            self.fill_target(RingSpan(len))?;
+            self.buffered = len;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.buffered -= amt.min(self.buffered);
+    }
+
+    fn buffer(&self) -> &[u8] {
+        self.workspace.borrow().ringbuf.recent(self.buffered)
+    }
+}
+
 #[cfg(feature = "std")]
 #[cfg(test)]
 mod tests {
@@ -313,8 +387,8 @@ mod tests {
         println!("Lh7Decoder<Empty> {}", core::mem::size_of::<Lh7Decoder<io::Empty>>());
         println!("Lh7Decoder<File> {}", core::mem::size_of::<Lh7Decoder<fs::File>>());
         println!("BitStream<File> {}", core::mem::size_of::<BitStream<fs::File>>());
-        println!("HuffTree {}", core::mem::size_of::<HuffTree>());
         println!("Option<(u32, NonZeroU32)> {}", core::mem::size_of::<Option<(u32, NonZeroU32)>>());
-        println!("Box<C::RingBuffer> {}", core::mem::size_of::<Box<<Lh7DecoderCfg as LhaDecoderConfig>::RingBuffer>>());
+        println!("Lh5Workspace {}", core::mem::size_of::<Lh5Workspace>());
+        println!("Lh7Workspace {}", core::mem::size_of::<Lh7Workspace>());
     }
 }
@@ -1,27 +1,66 @@
 use core::slice;
+use core::borrow::BorrowMut;
 use core::num::NonZeroU16;
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 use alloc::boxed::Box;
 use crate::error::{LhaResult, LhaError};
-use crate::stub_io::Read;
-use crate::decode::Decoder;
+use crate::stub_io::{Read, ReadAhead};
+use crate::decode::{BufDecoder, Decoder, take_buffered};
 use crate::ringbuf::*;
 
 const RING_BUFFER_SIZE: usize = 4096;
 const START_OFFSET: isize = -18;
 
 /// A decoder for `-lz5-` compression method.
+///
+/// The decoder keeps its history in the workspace `W`, see [`Lz5Workspace`]. `W` defaults to
+/// `Box<Lz5Workspace>` only with the `alloc` feature.
 #[derive(Debug)]
-pub struct Lz5Decoder<R> {
-    reader: R,
+pub struct Lz5Decoder<R, #[cfg(feature = "alloc")] W = Box<Lz5Workspace>, #[cfg(not(feature = "alloc"))] W> {
+    reader: ReadAhead<R>,
     bitmap: u16,
     copy_progress: Option<(u16, NonZeroU16)>,
-    ringbuf: Box<RingArrayBuf<RING_BUFFER_SIZE>>,
+    buffered: usize,
+    workspace: W,
 }
 
+/// The work buffer of [`Lz5Decoder`] (4 kB): the history ring buffer.
+///
+/// The workspace can be created in a `static` or on the stack and lent to the decoder with
+/// [`Lz5Decoder::with_workspace`], so decoding requires no allocations.
+#[derive(Debug, Clone)]
+pub struct Lz5Workspace {
+    ringbuf: RingArrayBuf<RING_BUFFER_SIZE>,
+}
+
+impl Lz5Workspace {
+    /// Creates a new workspace.
+    pub const fn new() -> Self {
+        Lz5Workspace { ringbuf: RingArrayBuf::new() }
+    }
+}
+
+impl Default for Lz5Workspace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
 impl<R: Read> Lz5Decoder<R> {
+    /// Creates a new decoder with the workspace allocated on the heap.
     pub fn new(reader: R) -> Lz5Decoder<R> {
-        let mut ringbuf: Box<RingArrayBuf<RING_BUFFER_SIZE>> = Box::default();
+        Self::with_workspace(reader, Box::default())
+    }
+}
+
+impl<R: Read, W: BorrowMut<Lz5Workspace>> Lz5Decoder<R, W> {
+    /// Creates a new decoder with the given `workspace`, e.g. `&mut Lz5Workspace`.
+    ///
+    /// The workspace is reset, so it can be reused by subsequent decoders.
+    pub fn with_workspace(reader: R, mut workspace: W) -> Lz5Decoder<R, W> {
+        let ringbuf = &mut workspace.borrow_mut().ringbuf;
+        ringbuf.reset();
 
         // fill 13 times with each byte value (3328)
         for i in 0..=255 {
@@ -51,50 +90,46 @@ impl<R: Read> Lz5Decoder<R> {
         ringbuf.set_cursor(START_OFFSET);
 
         Lz5Decoder {
-            reader,
-            ringbuf,
+            reader: ReadAhead::new(reader),
+            workspace,
             bitmap: 1,
-            copy_progress: None
+            copy_progress: None,
+            buffered: 0
         }
     }
 
-    fn copy_from_history<'a, I: Iterator<Item=&'a mut u8> + ExactSizeIterator>(
-            &mut self,
-            target: I,
-            pos: usize,
-            count: usize
-        ) -> LhaResult<(), R>
+    /// Unwraps and returns the inner reader and the workspace.
+    pub fn into_parts(self) -> (R, W) {
+        (self.reader.into_inner(), self.workspace)
+    }
+
+    /// Copies up to `count` bytes from history to `target` at `index` and returns the index past the
+    /// copied bytes. Remembers the number of bytes which didn't fit in `target`.
+    ///
+    /// The bytes of `target` preceding `index` are the most recent history, the older one is in the
+    /// ring buffer.
+    #[inline]
+    fn copy_from_history<T>(&mut self, target: &mut T, index: usize, pos: usize, count: usize) -> usize
+        where T: HistoryTarget<RingArrayBuf<RING_BUFFER_SIZE>>
     {
-        let history_iter = self.ringbuf.iter_from_pos(pos);
-        let real_count = target.len().min(count);
-        for (t, s) in target.zip(history_iter).take(real_count) {
-            *t = s;
-        }
+        let end = target.copy_from_pos(&mut self.workspace.borrow_mut().ringbuf, pos, index, count);
+        let real_count = end - index;
         self.copy_progress = NonZeroU16::new((count - real_count) as u16)
                              .map(|count| ((pos + real_count) as u16, count));
-        Ok(())
-    }
-}
-
-impl<R: Read> Decoder<R> for Lz5Decoder<R> where R::Error: core::fmt::Debug {
-    type Error = R::Error;
-
-    fn into_inner(self) -> R {
-        self.reader
+        end
     }
 
-    fn fill_buffer(&mut self, buf: &mut[u8]) -> LhaResult<(), R> {
-        let buflen = buf.len();
-        let mut target = buf.iter_mut();
+    fn fill_target<T>(&mut self, mut target: T) -> LhaResult<(), R>
+        where T: HistoryTarget<RingArrayBuf<RING_BUFFER_SIZE>>, R::Error: core::fmt::Debug
+    {
+        let mut index = 0;
         if let Some((pos, count)) = self.copy_progress {
-            self.copy_from_history(&mut target,
-                                   pos as usize,
-                                   count.get() as usize)?;
+            index = self.copy_from_history(&mut target, index, pos as usize, count.get() as usize);
         }
 
         let mut bitmap = self.bitmap;
 
-        while let Some(dst) = target.next() {
+        while index < target.size() {
             if bitmap == 1 {
                 let mut byte = 0u8;
                 self.reader.read_exact(slice::from_mut(&mut byte))
@@ -103,11 +138,11 @@ impl<R: Read> Decoder<R> for Lz5Decoder<R> where R::Error: core::fmt::Debug {
             }
 
             if bitmap & 1 == 1 {
-                let mut value = 0u8;
-                self.reader.read_exact(slice::from_mut(&mut value))
+                let mut byte = 0u8;
+                self.reader.read_exact(slice::from_mut(&mut byte))
                            .map_err(LhaError::Io)?;
-                *dst = value;
-                self.ringbuf.push(value);
+                target.set(&mut self.workspace.borrow_mut().ringbuf, index, byte);
+                index += 1;
             }
             else {
                 let mut cmd = [0u8;2];
@@ -116,14 +151,45 @@ impl<R: Read> Decoder<R> for Lz5Decoder<R> where R::Error: core::fmt::Debug {
                 let [lo, hi] = cmd;
                 let pos = (((hi & 0xf0) as usize) << 4) | lo as usize;
                 let count = (hi & 0x0f) as usize;
-                let index = buflen - target.len() - 1;
-                target = buf[index..].iter_mut();
-                self.copy_from_history(&mut target, pos, count + 3)?;
+                index = self.copy_from_history(&mut target, index, pos, count + 3);
             }
 
             bitmap >>= 1;
         }
+        target.finish(&mut self.workspace.borrow_mut().ringbuf);
         self.bitmap = bitmap;
         Ok(())
     }
 }
+
+impl<R: Read, W: BorrowMut<Lz5Workspace>> Decoder<R> for Lz5Decoder<R, W> where R::Error: core::fmt::Debug {
+    type Error = R::Error;
+
+    fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> LhaResult<(), R> {
+        let start = take_buffered(self, buf);
+        self.fill_target(&mut buf[start..])
+    }
+}
+
+impl<R: Read, W: BorrowMut<Lz5Workspace>> BufDecoder<R> for Lz5Decoder<R, W> where R::Error: core::fmt::Debug {
+    fn fill_buf(&mut self, max: usize) -> LhaResult<&[u8], R> {
+        if self.buffered == 0 {
+            let len = max.min(self.workspace.borrow().ringbuf.span_capacity());
+            self.fill_target(RingSpan(len))?;
+            self.buffered = len;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.buffered -= amt.min(self.buffered);
+    }
+
+    fn buffer(&self) -> &[u8] {
+        self.workspace.borrow().ringbuf.recent(self.buffered)
+    }
+}
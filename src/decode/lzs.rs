@@ -1,9 +1,10 @@
+use core::borrow::BorrowMut;
 use core::num::NonZeroU16;
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 use alloc::boxed::Box;
 use crate::error::LhaResult;
 use crate::stub_io::Read;
-use crate::decode::Decoder;
+use crate::decode::{BufDecoder, Decoder, take_buffered};
 use crate::ringbuf::*;
 use crate::bitstream::*;
 
@@ -11,73 +12,138 @@ const RING_BUFFER_SIZE: usize = 2048;
 const START_OFFSET: isize = -17;
 
 /// A decoder for `-lzs-` compression method.
+///
+/// The decoder keeps its history in the workspace `W`, see [`LzsWorkspace`]. `W` defaults to
+/// `Box<LzsWorkspace>` only with the `alloc` feature.
 #[derive(Debug)]
-pub struct LzsDecoder<R> {
+pub struct LzsDecoder<R, #[cfg(feature = "alloc")] W = Box<LzsWorkspace>, #[cfg(not(feature = "alloc"))] W> {
     bit_reader: BitStream<R>,
     copy_progress: Option<(u16, NonZeroU16)>,
-    ringbuf: Box<RingArrayBuf<RING_BUFFER_SIZE>>,
+    buffered: usize,
+    workspace: W,
 }
 
+/// The work buffer of [`LzsDecoder`] (2 kB): the history ring buffer.
+///
+/// The workspace can be created in a `static` or on the stack and lent to the decoder with
+/// [`LzsDecoder::with_workspace`], so decoding requires no allocations.
+#[derive(Debug, Clone)]
+pub struct LzsWorkspace {
+    ringbuf: RingArrayBuf<RING_BUFFER_SIZE>,
+}
+
+impl LzsWorkspace {
+    /// Creates a new workspace.
+    pub const fn new() -> Self {
+        LzsWorkspace { ringbuf: RingArrayBuf::new() }
+    }
+}
+
+impl Default for LzsWorkspace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
 impl<R: Read> LzsDecoder<R> {
+    /// Creates a new decoder with the workspace allocated on the heap.
     pub fn new(rd: R) -> LzsDecoder<R> {
+        Self::with_workspace(rd, Box::default())
+    }
+}
+
+impl<R: Read, W: BorrowMut<LzsWorkspace>> LzsDecoder<R, W> {
+    /// Creates a new decoder with the given `workspace`, e.g. `&mut LzsWorkspace`.
+    ///
+    /// The workspace is reset, so it can be reused by subsequent decoders.
+    pub fn with_workspace(rd: R, mut workspace: W) -> LzsDecoder<R, W> {
         let bit_reader = BitStream::new(rd);
-        let mut ringbuf: Box<RingArrayBuf<RING_BUFFER_SIZE>> = Box::default();
+        let ringbuf = &mut workspace.borrow_mut().ringbuf;
+        ringbuf.reset();
         ringbuf.set_cursor(START_OFFSET);
         LzsDecoder {
             bit_reader,
-            ringbuf,
-            copy_progress: None
+            workspace,
+            copy_progress: None,
+            buffered: 0
         }
     }
 
-    fn copy_from_history<'a, I: Iterator<Item=&'a mut u8> + ExactSizeIterator>(
-            &mut self,
-            target: I,
-            pos: usize,
-            count: usize
-        ) -> LhaResult<(), R>
+    /// Unwraps and returns the inner reader and the workspace.
+    pub fn into_parts(self) -> (R, W) {
+        (self.bit_reader.into_inner(), self.workspace)
+    }
+
+    /// Copies up to `count` bytes from history to `target` at `index` and returns the index past the
+    /// copied bytes. Remembers the number of bytes which didn't fit in `target`.
+    ///
+    /// The bytes of `target` preceding `index` are the most recent history, the older one is in the
+    /// ring buffer.
+    #[inline]
+    fn copy_from_history<T>(&mut self, target: &mut T, index: usize, pos: usize, count: usize) -> usize
+        where T: HistoryTarget<RingArrayBuf<RING_BUFFER_SIZE>>
     {
-        let history_iter = self.ringbuf.iter_from_pos(pos);
-        let real_count = target.len().min(count);
-        for (t, s) in target.zip(history_iter).take(real_count) {
-            *t = s;
-        }
+        let end = target.copy_from_pos(&mut self.workspace.borrow_mut().ringbuf, pos, index, count);
+        let real_count = end - index;
         self.copy_progress = NonZeroU16::new((count - real_count) as u16)
                              .map(|count| ((pos + real_count) as u16, count));
-        Ok(())
-    }
-}
-
-impl<R: Read> Decoder<R> for LzsDecoder<R> where R::Error: core::fmt::Debug {
-    type Error = R::Error;
-
-    fn into_inner(self) -> R {
-        self.bit_reader.into_inner()
+        end
     }
 
-    fn fill_buffer(&mut self, buf: &mut[u8]) -> LhaResult<(), R> {
-        let buflen = buf.len();
-        let mut target = buf.iter_mut();
+    fn fill_target<T>(&mut self, mut target: T) -> LhaResult<(), R>
+        where T: HistoryTarget<RingArrayBuf<RING_BUFFER_SIZE>>, R::Error: core::fmt::Debug
+    {
+        let mut index = 0;
         if let Some((pos, count)) = self.copy_progress {
-            self.copy_from_history(&mut target,
-                                   pos as usize,
-                                   count.get() as usize)?;
+            index = self.copy_from_history(&mut target, index, pos as usize, count.get() as usize);
         }
 
-        while let Some(dst) = target.next() {
+        while index < target.size() {
             if self.bit_reader.read_bit()? {
-                let value = self.bit_reader.read_bits(8)?;
-                *dst = value;
-                self.ringbuf.push(value);
+                let byte = self.bit_reader.read_bits(8)?;
+                target.set(&mut self.workspace.borrow_mut().ringbuf, index, byte);
+                index += 1;
             }
             else {
                 let pos = self.bit_reader.read_bits(11)?;
                 let count: usize = self.bit_reader.read_bits(4)?;
-                let index = buflen - target.len() - 1;
-                target = buf[index..].iter_mut();
-                self.copy_from_history(&mut target, pos, count + 2)?;
+                index = self.copy_from_history(&mut target, index, pos, count + 2);
             }
         }
+        target.finish(&mut self.workspace.borrow_mut().ringbuf);
         Ok(())
     }
 }
+
+impl<R: Read, W: BorrowMut<LzsWorkspace>> Decoder<R> for LzsDecoder<R, W> where R::Error: core::fmt::Debug {
+    type Error = R::Error;
+
+    fn into_inner(self) -> R {
+        self.bit_reader.into_inner()
+    }
+
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> LhaResult<(), R> {
+        let start = take_buffered(self, buf);
+        self.fill_target(&mut buf[start..])
+    }
+}
+
+impl<R: Read, W: BorrowMut<LzsWorkspace>> BufDecoder<R> for LzsDecoder<R, W> where R::Error: core::fmt::Debug {
+    fn fill_buf(&mut self, max: usize) -> LhaResult<&[u8], R> {
+        if self.buffered == 0 {
+            let len = max.min(self.workspace.borrow().ringbuf.span_capacity());
+            self.fill_target(RingSpan(len))?;
+            self.buffered = len;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.buffered -= amt.min(self.buffered);
+    }
+
+    fn buffer(&self) -> &[u8] {
+        self.workspace.borrow().ringbuf.recent(self.buffered)
+    }
+}
@@ -0,0 +1,152 @@
+//! # Decoders of compression methods provided by the user.
+use core::fmt;
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec::Vec};
+use crate::error::LhaError;
+use crate::header::LhaHeader;
+use crate::stub_io::Read;
+use super::Decoder;
+
+/// A dyn compatible counterpart of the [`Decoder`] trait, implemented for all the decoders.
+///
+/// It allows [`DecoderAny`][super::DecoderAny] to hold decoders of compression methods unknown to
+/// this crate as [`BoxedDecoder`].
+pub trait DynDecoder<R: Read>: fmt::Debug {
+    /// Fills the whole `buf` with decoded data, see [`Decoder::fill_buffer`].
+    fn fill_buffer_dyn(&mut self, buf: &mut[u8]) -> Result<(), LhaError<R::Error>>;
+    /// Unwraps and returns the inner reader, see [`Decoder::into_inner`].
+    fn into_inner_boxed(self: Box<Self>) -> R;
+}
+
+/// A decoder of a compression method created by [`DecoderFactory`].
+pub type BoxedDecoder<R> = Box<dyn DynDecoder<R>>;
+
+/// A factory of decoders for a compression method, e.g. one that this crate doesn't support.
+///
+/// The factories are registered in the [`DecoderRegistry`] by the 5-byte compression method
+/// identifiers, such as `-lh2-`. It is implemented for closures taking the header and the reader.
+pub trait DecoderFactory<R: Read> {
+    /// Create a decoder of the file described by the `header`, reading the compressed data from `rd`.
+    fn new_decoder(&self, header: &LhaHeader, rd: R) -> BoxedDecoder<R>;
+}
+
+/// Decoder factories registered by the compression method identifiers.
+///
+/// The registered factories take precedence over the built-in decoders, so they can also replace
+/// decoders of the methods which are recognized, but not supported, such as `-pm2-`.
+///
+/// The registry can be provided to [`LhaDecodeReader::with_limits_and_registry`][crate::LhaDecodeReader::with_limits_and_registry],
+/// [`LhaSliceEntry::decoder_in`][crate::LhaSliceEntry::decoder_in] or
+/// [`LhaSliceEntry::decode_to_slice_in`][crate::LhaSliceEntry::decode_to_slice_in]. The decoders
+/// can also be registered with [`LhaDecodeReader::register_decoder`][crate::LhaDecodeReader::register_decoder]
+/// or `Archive::register_decoder`.
+///
+/// The factories and the decoders are not required to be `Send`, so neither are the readers
+/// holding them, such as [`LhaDecodeReader`][crate::LhaDecodeReader].
+pub struct DecoderRegistry<R> {
+    factories: Vec<([u8;5], Box<dyn DecoderFactory<R>>)>
+}
+
+impl<R: Read, D> DynDecoder<R> for D
+    where D: Decoder<R, Error=R::Error> + fmt::Debug
+{
+    #[inline]
+    fn fill_buffer_dyn(&mut self, buf: &mut[u8]) -> Result<(), LhaError<R::Error>> {
+        self.fill_buffer(buf)
+    }
+
+    fn into_inner_boxed(self: Box<Self>) -> R {
+        (*self).into_inner()
+    }
+}
+
+impl<R: Read> Decoder<R> for BoxedDecoder<R> where R::Error: fmt::Debug {
+    type Error = R::Error;
+
+    fn into_inner(self) -> R {
+        self.into_inner_boxed()
+    }
+
+    #[inline]
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> Result<(), LhaError<Self::Error>> {
+        // the box itself is a `DynDecoder` too
+        (**self).fill_buffer_dyn(buf)
+    }
+}
+
+impl<R: Read, F> DecoderFactory<R> for F
+    where F: Fn(&LhaHeader, R) -> BoxedDecoder<R>
+{
+    fn new_decoder(&self, header: &LhaHeader, rd: R) -> BoxedDecoder<R> {
+        self(header, rd)
+    }
+}
+
+impl<R> Default for DecoderRegistry<R> {
+    fn default() -> Self {
+        DecoderRegistry { factories: Vec::new() }
+    }
+}
+
+impl<R: Read> DecoderRegistry<R> {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Register the decoder `factory` for the compression method identifier `id`, e.g. `*b"-lh2-"`.
+    ///
+    /// Return `true` if the factory replaced a previously registered one.
+    pub fn register<F>(&mut self, id: [u8;5], factory: F) -> bool
+        where F: DecoderFactory<R> + 'static
+    {
+        let factory = Box::new(factory);
+        match self.factories.iter_mut().find(|(key, _)| *key == id) {
+            Some((_, slot)) => {
+                *slot = factory;
+                true
+            }
+            None => {
+                self.factories.push((id, factory));
+                false
+            }
+        }
+    }
+    /// Remove the factory registered for the compression method identifier `id`.
+    ///
+    /// Return `true` if the factory has been removed.
+    pub fn unregister(&mut self, id: &[u8;5]) -> bool {
+        let len = self.factories.len();
+        self.factories.retain(|(key, _)| key != id);
+        len != self.factories.len()
+    }
+    /// Return the factory registered for the compression method identifier `id`.
+    pub fn get(&self, id: &[u8;5]) -> Option<&dyn DecoderFactory<R>> {
+        self.factories.iter().find(|(key, _)| key == id).map(|(_, factory)| &**factory)
+    }
+    /// Return whether a factory is registered for the compression method identifier `id`.
+    pub fn contains(&self, id: &[u8;5]) -> bool {
+        self.get(id).is_some()
+    }
+    /// Return whether no factories are registered.
+    pub fn is_empty(&self) -> bool {
+        self.factories.is_empty()
+    }
+    /// Return an iterator over the registered compression method identifiers.
+    pub fn ids(&self) -> impl Iterator<Item=&[u8;5]> + '_ {
+        self.factories.iter().map(|(id, _)| id)
+    }
+}
+
+impl<R> fmt::Debug for DecoderRegistry<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Id<'a>(&'a [u8;5]);
+        impl fmt::Debug for Id<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0.escape_ascii())
+            }
+        }
+        f.debug_list()
+         .entries(self.factories.iter().map(|(id, _)| Id(id)))
+         .finish()
+    }
+}
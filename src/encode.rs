@@ -0,0 +1,101 @@
+//! # Encoding algorithms.
+//!
+//! This module is only available with `std` feature enabled.
+use std::io::{self, Write};
+
+use crate::header::CompressionMethod;
+
+mod lhv2;
+
+pub use lhv2::*;
+
+/// An encoder for storage only methods.
+#[derive(Debug)]
+pub struct PassthroughEncoder<W> {
+    inner: W
+}
+
+/// An encoder of any of the supported compression methods, see [`EncoderAny::new_from_compression`].
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum EncoderAny<W: Write> {
+    PassthroughEncoder(PassthroughEncoder<W>),
+    Lh5Encoder(Lh5Encoder<W>),
+    Lh6Encoder(Lh6Encoder<W>),
+    Lh7Encoder(Lh7Encoder<W>),
+}
+
+macro_rules! encoder_any_dispatch {
+    (($model:expr)($($spec:tt)*) => $expr:expr) => {
+        match $model {
+            EncoderAny::PassthroughEncoder($($spec)*) => $expr,
+            EncoderAny::Lh5Encoder($($spec)*) => $expr,
+            EncoderAny::Lh6Encoder($($spec)*) => $expr,
+            EncoderAny::Lh7Encoder($($spec)*) => $expr,
+        }
+    };
+}
+
+impl<W: Write> EncoderAny<W> {
+    /// Creates an instance of `EncoderAny<W>` from the given compression method and a stream writer.
+    ///
+    /// Returns `None` if encoding with the given compression method is not supported.
+    pub fn new_from_compression(
+            compression: CompressionMethod,
+            wr: W
+        ) -> Option<Self>
+    {
+        Some(match compression {
+            CompressionMethod::Lh0 => EncoderAny::PassthroughEncoder(PassthroughEncoder::new(wr)),
+            CompressionMethod::Lh5 => EncoderAny::Lh5Encoder(Lh5Encoder::new(wr)),
+            CompressionMethod::Lh6 => EncoderAny::Lh6Encoder(Lh6Encoder::new(wr)),
+            CompressionMethod::Lh7 => EncoderAny::Lh7Encoder(Lh7Encoder::new(wr)),
+            _ => return None
+        })
+    }
+    /// Returns `true` if encoding with the given compression method is supported.
+    pub fn is_supported(compression: CompressionMethod) -> bool {
+        matches!(compression, CompressionMethod::Lh0|
+                              CompressionMethod::Lh5|
+                              CompressionMethod::Lh6|
+                              CompressionMethod::Lh7)
+    }
+    /// Encodes all the remaining data, flushes the encoder and returns the underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        encoder_any_dispatch!((self)(encoder) => encoder.finish())
+    }
+}
+
+impl<W: Write> Write for EncoderAny<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        encoder_any_dispatch!((self)(encoder) => encoder.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        encoder_any_dispatch!((self)(encoder) => encoder.flush())
+    }
+}
+
+impl<W: Write> PassthroughEncoder<W> {
+    /// Creates a new `PassthroughEncoder<W>` writing the data unchanged to `inner`.
+    pub fn new(inner: W) -> Self {
+        PassthroughEncoder { inner }
+    }
+    /// Flushes the encoder and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for PassthroughEncoder<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,555 @@
+use core::marker::PhantomData;
+use std::collections::BinaryHeap;
+use std::cmp::Reverse;
+use std::io::{self, Write};
+
+const NUM_COMMANDS: usize = 510;
+const NUM_TEMP_CODELEN: usize = 19;
+const MAX_OFFSET_CODES: usize = 17;
+const MAX_CODE_LENGTH: u32 = 16;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 256;
+// the maximum number of commands in a single block, must fit in 16 bits
+const BLOCK_COMMANDS: usize = 0x4000;
+const HASH_BITS: u32 = 15;
+const MAX_CHAIN: usize = 256;
+
+pub trait LhaEncoderConfig {
+    /// The size of the sliding dictionary as a power of 2.
+    const DICTIONARY_BITS: u32;
+    /// The number of bits used to encode the size of the offset codes table.
+    const OFFSET_BITS: u32;
+}
+
+/// A generic encoder for LHArc version 2 compression methods.
+///
+/// Write the content of a file to the encoder and call [`LhaV2Encoder::finish`] to flush the
+/// remaining compressed data to the underlying writer.
+///
+/// The encoder uses hash chains with lazy matching to find repeated sequences and emits blocks of
+/// static Huffman codes.
+#[derive(Debug)]
+pub struct LhaV2Encoder<C, W> {
+    bit_writer: BitWriter<W>,
+    window: Vec<u8>,
+    pos: usize,
+    next_insert: usize,
+    head: Box<[u32]>,
+    prev: Box<[u32]>,
+    commands: Vec<(u16, u16)>,
+    command_freq: Box<[u32; NUM_COMMANDS]>,
+    offset_freq: [u32; MAX_OFFSET_CODES],
+    _config: PhantomData<C>
+}
+
+macro_rules! impl_lhav2_encoder {
+    ($cfg_name:ident, DICTIONARY_BITS=$dict_bits:literal, OFFSET_BITS=$offset_bits:literal) => {
+        #[derive(Debug)]
+        pub struct $cfg_name;
+
+        impl LhaEncoderConfig for $cfg_name {
+            const DICTIONARY_BITS: u32 = $dict_bits;
+            const OFFSET_BITS: u32 = $offset_bits;
+        }
+    };
+}
+
+impl_lhav2_encoder!(Lh5EncoderCfg, DICTIONARY_BITS=13, OFFSET_BITS=4);
+impl_lhav2_encoder!(Lh6EncoderCfg, DICTIONARY_BITS=15, OFFSET_BITS=5);
+impl_lhav2_encoder!(Lh7EncoderCfg, DICTIONARY_BITS=16, OFFSET_BITS=5);
+
+/// An encoder for `-lh5-` compression method.
+pub type Lh5Encoder<W> = LhaV2Encoder<Lh5EncoderCfg, W>;
+/// An encoder for `-lh6-` compression method.
+pub type Lh6Encoder<W> = LhaV2Encoder<Lh6EncoderCfg, W>;
+/// An encoder for `-lh7-` compression method.
+pub type Lh7Encoder<W> = LhaV2Encoder<Lh7EncoderCfg, W>;
+
+/// A simple bit-stream writer, filling each consecutive byte starting from its highest bit.
+#[derive(Debug)]
+struct BitWriter<W> {
+    inner: W,
+    buf: Vec<u8>,
+    bits: u32,
+    nbits: u32
+}
+
+impl<W: Write> BitWriter<W> {
+    fn new(inner: W) -> Self {
+        BitWriter { inner, buf: Vec::new(), bits: 0, nbits: 0 }
+    }
+
+    #[inline]
+    fn write_bits(&mut self, n: u32, value: u32) {
+        debug_assert!(n <= 16);
+        self.bits = self.bits << n | value & ((1 << n) - 1);
+        self.nbits += n;
+        while self.nbits >= 8 {
+            self.nbits -= 8;
+            self.buf.push((self.bits >> self.nbits) as u8);
+        }
+        self.bits &= (1 << self.nbits) - 1;
+    }
+
+    // writes code length value, lengths above 6 are encoded as 7 followed by unary bits
+    fn write_code_length(&mut self, len: u8) {
+        if len < 7 {
+            self.write_bits(3, len.into());
+        }
+        else {
+            self.write_bits(3, 7);
+            for _ in 7..len {
+                self.write_bits(1, 1);
+            }
+            self.write_bits(1, 0);
+        }
+    }
+
+    fn flush_bytes(&mut self) -> io::Result<()> {
+        self.inner.write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        if self.nbits != 0 {
+            self.write_bits(8 - self.nbits, 0);
+        }
+        self.flush_bytes()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<C: LhaEncoderConfig, W: Write> LhaV2Encoder<C, W> {
+    const DICTIONARY_SIZE: usize = 1 << C::DICTIONARY_BITS;
+    const NUM_OFFSET_CODES: usize = C::DICTIONARY_BITS as usize + 1;
+
+    pub fn new(wr: W) -> LhaV2Encoder<C, W> {
+        debug_assert!(Self::NUM_OFFSET_CODES <= MAX_OFFSET_CODES);
+        LhaV2Encoder {
+            bit_writer: BitWriter::new(wr),
+            window: Vec::new(),
+            pos: 0,
+            next_insert: 0,
+            head: vec![0; 1 << HASH_BITS].into_boxed_slice(),
+            prev: vec![0; Self::DICTIONARY_SIZE].into_boxed_slice(),
+            commands: Vec::with_capacity(BLOCK_COMMANDS),
+            command_freq: Box::new([0; NUM_COMMANDS]),
+            offset_freq: [0; MAX_OFFSET_CODES],
+            _config: PhantomData
+        }
+    }
+    /// Encodes all the remaining data, flushes the encoder and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.process(true)?;
+        if !self.commands.is_empty() {
+            self.write_block()?;
+        }
+        self.bit_writer.finish()
+    }
+
+    fn process(&mut self, finish: bool) -> io::Result<()> {
+        // keep enough look-ahead for the longest match and the lazy evaluation
+        let lookahead = if finish { 1 } else { MAX_MATCH + 1 };
+        while self.window.len() - self.pos >= lookahead {
+            self.step()?;
+        }
+        if self.pos >= 2 * Self::DICTIONARY_SIZE {
+            self.slide();
+        }
+        Ok(())
+    }
+
+    fn step(&mut self) -> io::Result<()> {
+        let pos = self.pos;
+        let (len, distance) = self.find_match(pos);
+        if len >= MIN_MATCH {
+            // prefer a literal if the match at the next position is longer
+            if len < MAX_MATCH && self.find_match(pos + 1).0 > len {
+                return self.push_literal(self.window[pos])
+            }
+            self.pos += len;
+            self.push_match(len, distance)
+        }
+        else {
+            self.push_literal(self.window[pos])
+        }
+    }
+
+    fn push_literal(&mut self, value: u8) -> io::Result<()> {
+        self.pos += 1;
+        self.push_command(value.into(), 0)
+    }
+
+    fn push_match(&mut self, len: usize, distance: usize) -> io::Result<()> {
+        let offset = (distance - 1) as u16;
+        self.offset_freq[offset_code(offset)] += 1;
+        self.push_command((len - MIN_MATCH + 0x100) as u16, offset)
+    }
+
+    fn push_command(&mut self, command: u16, offset: u16) -> io::Result<()> {
+        self.command_freq[command as usize] += 1;
+        self.commands.push((command, offset));
+        if self.commands.len() == BLOCK_COMMANDS {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn hash(&self, pos: usize) -> usize {
+        let bytes = &self.window[pos..pos + MIN_MATCH];
+        ((bytes[0] as usize) << 10 ^ (bytes[1] as usize) << 5 ^ bytes[2] as usize)
+        & ((1 << HASH_BITS) - 1)
+    }
+
+    fn insert_upto(&mut self, end: usize) {
+        let mask = Self::DICTIONARY_SIZE - 1;
+        for pos in self.next_insert..end.min(self.window.len().saturating_sub(MIN_MATCH - 1)) {
+            let hash = self.hash(pos);
+            self.prev[pos & mask] = self.head[hash];
+            self.head[hash] = pos as u32 + 1;
+        }
+        self.next_insert = self.next_insert.max(end);
+    }
+
+    // returns the length and the distance of the longest match found at `pos`
+    fn find_match(&mut self, pos: usize) -> (usize, usize) {
+        self.insert_upto(pos);
+        let max_len = MAX_MATCH.min(self.window.len() - pos);
+        if max_len < MIN_MATCH {
+            return (0, 0)
+        }
+        let mask = Self::DICTIONARY_SIZE - 1;
+        let window = &self.window[..];
+        let target = &window[pos..pos + max_len];
+        let (mut best_len, mut best_distance) = (MIN_MATCH - 1, 0);
+        let mut next = self.head[self.hash(pos)];
+        for _ in 0..MAX_CHAIN {
+            if next == 0 {
+                break
+            }
+            let candidate = next as usize - 1;
+            let distance = pos - candidate;
+            if distance > Self::DICTIONARY_SIZE {
+                break
+            }
+            if window[candidate + best_len] == target[best_len] {
+                let len = window[candidate..candidate + max_len].iter()
+                          .zip(target).take_while(|(a, b)| a == b).count();
+                if len > best_len {
+                    best_len = len;
+                    best_distance = distance;
+                    if len == max_len {
+                        break
+                    }
+                }
+            }
+            let prev = self.prev[candidate & mask];
+            if prev >= next {
+                break
+            }
+            next = prev;
+        }
+        if best_distance == 0 {
+            (0, 0)
+        }
+        else {
+            (best_len, best_distance)
+        }
+    }
+
+    // discards the data no longer reachable by the dictionary
+    fn slide(&mut self) {
+        let shift = (self.pos - Self::DICTIONARY_SIZE) & !(Self::DICTIONARY_SIZE - 1);
+        self.window.drain(..shift);
+        self.pos -= shift;
+        self.next_insert -= shift;
+        let shift = shift as u32;
+        for p in self.head.iter_mut().chain(self.prev.iter_mut()) {
+            *p = p.saturating_sub(shift);
+        }
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        let mut command_lengths = [0u8; NUM_COMMANDS];
+        let mut offset_lengths = [0u8; MAX_OFFSET_CODES];
+        let num_offset_codes = Self::NUM_OFFSET_CODES;
+        let command_single = build_lengths(&self.command_freq[..], &mut command_lengths);
+        let offset_single = build_lengths(&self.offset_freq[..num_offset_codes],
+                                          &mut offset_lengths[..num_offset_codes]);
+        let bw = &mut self.bit_writer;
+        bw.write_bits(16, self.commands.len() as u32);
+        match command_single {
+            Some(code) => {
+                // single temporary and command codes
+                bw.write_bits(5, 0);
+                bw.write_bits(5, 0);
+                bw.write_bits(9, 0);
+                bw.write_bits(9, code as u32);
+            }
+            None => write_command_lengths(bw, &command_lengths)
+        }
+        match offset_single {
+            Some(code) => {
+                bw.write_bits(C::OFFSET_BITS, 0);
+                bw.write_bits(C::OFFSET_BITS, code as u32);
+            }
+            None => {
+                let num_codes = trimmed_len(&offset_lengths);
+                bw.write_bits(C::OFFSET_BITS, num_codes as u32);
+                for &len in offset_lengths[..num_codes].iter() {
+                    bw.write_code_length(len);
+                }
+            }
+        }
+        let command_codes = build_codes(&command_lengths);
+        let offset_codes = build_codes(&offset_lengths);
+        for &(command, offset) in self.commands.iter() {
+            let command = command as usize;
+            bw.write_bits(command_lengths[command].into(), command_codes[command].into());
+            if command >= 0x100 {
+                let code = offset_code(offset);
+                bw.write_bits(offset_lengths[code].into(), offset_codes[code].into());
+                if code > 1 {
+                    bw.write_bits(code as u32 - 1, offset.into());
+                }
+            }
+        }
+        self.commands.clear();
+        self.command_freq.fill(0);
+        self.offset_freq.fill(0);
+        bw.flush_bytes()
+    }
+}
+
+impl<C: LhaEncoderConfig, W: Write> Write for LhaV2Encoder<C, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // limit the look-ahead data kept in the window
+        let len = buf.len().min(Self::DICTIONARY_SIZE);
+        self.window.extend_from_slice(&buf[..len]);
+        self.process(false)?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.bit_writer.flush_bytes()?;
+        self.bit_writer.inner.flush()
+    }
+}
+
+//   offset       -> code, extra bits
+//        0       -> 0
+//        1       -> 1
+//   1x           -> 2, x
+//  1xx           -> 3, xx
+// 1xxx           -> 4, xxx
+#[inline]
+fn offset_code(offset: u16) -> usize {
+    (u16::BITS - offset.leading_zeros()) as usize
+}
+
+fn trimmed_len(lengths: &[u8]) -> usize {
+    lengths.iter().rposition(|&len| len != 0).map_or(0, |n| n + 1)
+}
+
+fn write_command_lengths<W: Write>(bw: &mut BitWriter<W>, command_lengths: &[u8]) {
+    let num_codes = trimmed_len(command_lengths);
+    // temporary codes: 0 -> skip 1, 1 -> skip 3..=18, 2 -> skip 20..=531, n -> length n - 2
+    let mut temp_codes: Vec<(u8, u32, u32)> = Vec::with_capacity(num_codes);
+    let mut temp_freq = [0u32; NUM_TEMP_CODELEN];
+    let mut lengths = command_lengths[..num_codes].iter().copied().peekable();
+    while let Some(len) = lengths.next() {
+        if len != 0 {
+            temp_codes.push((len + 2, 0, 0));
+            continue
+        }
+        let mut run = 1;
+        while lengths.next_if_eq(&0).is_some() {
+            run += 1;
+        }
+        match run {
+            1..=2 => temp_codes.extend((0..run).map(|_| (0, 0, 0))),
+            3..=18 => temp_codes.push((1, 4, run - 3)),
+            19 => temp_codes.extend([(0, 0, 0), (1, 4, 15)]),
+            _ => temp_codes.push((2, 9, run - 20))
+        }
+    }
+    for &(code, ..) in temp_codes.iter() {
+        temp_freq[code as usize] += 1;
+    }
+    let mut temp_lengths = [0u8; NUM_TEMP_CODELEN];
+    match build_lengths(&temp_freq, &mut temp_lengths) {
+        Some(code) => {
+            bw.write_bits(5, 0);
+            bw.write_bits(5, code as u32);
+        }
+        None => {
+            // there is always at least one code above 2 present
+            let num_temp = trimmed_len(&temp_lengths);
+            debug_assert!(num_temp > 3);
+            bw.write_bits(5, num_temp as u32);
+            for &len in temp_lengths[..3].iter() {
+                bw.write_code_length(len);
+            }
+            let skip = temp_lengths[3..num_temp].iter().take(3).take_while(|&&len| len == 0).count();
+            bw.write_bits(2, skip as u32);
+            for &len in temp_lengths[3 + skip..num_temp].iter() {
+                bw.write_code_length(len);
+            }
+        }
+    }
+    let temp_bits = build_codes(&temp_lengths);
+    bw.write_bits(9, num_codes as u32);
+    for (code, extra_bits, extra) in temp_codes {
+        let code = code as usize;
+        bw.write_bits(temp_lengths[code].into(), temp_bits[code].into());
+        bw.write_bits(extra_bits, extra);
+    }
+}
+
+/// Builds length limited Huffman code lengths for the given symbol frequencies.
+///
+/// Returns the symbol if only one (or none) is present, in this instance all `lengths` are `0`.
+fn build_lengths(freq: &[u32], lengths: &mut [u8]) -> Option<usize> {
+    lengths.fill(0);
+    let symbols: Vec<usize> = (0..freq.len()).filter(|&n| freq[n] != 0).collect();
+    if symbols.len() < 2 {
+        return Some(symbols.first().copied().unwrap_or(0))
+    }
+    // build the Huffman tree, nodes are created with ascending indices
+    let num_leaves = symbols.len();
+    let mut parent = vec![0usize; 2 * num_leaves - 1];
+    let mut heap: BinaryHeap<_> = symbols.iter().enumerate()
+                                  .map(|(node, &sym)| Reverse((freq[sym] as u64, node)))
+                                  .collect();
+    let mut next_node = num_leaves;
+    while let (Some(Reverse((w0, n0))), Some(Reverse((w1, n1)))) = (heap.pop(), heap.pop()) {
+        parent[n0] = next_node;
+        parent[n1] = next_node;
+        heap.push(Reverse((w0 + w1, next_node)));
+        next_node += 1;
+    }
+    let mut depth = vec![0u32; next_node];
+    for node in (0..next_node - 1).rev() {
+        depth[node] = depth[parent[node]] + 1;
+    }
+    let mut len_count = [0u32; MAX_CODE_LENGTH as usize + 1];
+    for &d in depth[..num_leaves].iter() {
+        len_count[d.min(MAX_CODE_LENGTH) as usize] += 1;
+    }
+    // fix the overflow caused by limiting lengths
+    let mut kraft: u32 = len_count.iter().enumerate().skip(1)
+                         .map(|(len, &count)| count << (MAX_CODE_LENGTH - len as u32))
+                         .sum();
+    while kraft != 1 << MAX_CODE_LENGTH {
+        len_count[MAX_CODE_LENGTH as usize] -= 1;
+        for len in (1..MAX_CODE_LENGTH as usize).rev() {
+            if len_count[len] != 0 {
+                len_count[len] -= 1;
+                len_count[len + 1] += 2;
+                break
+            }
+        }
+        kraft -= 1;
+    }
+    // the most frequent symbols receive the shortest codes
+    let mut sorted = symbols;
+    sorted.sort_by(|&a, &b| freq[b].cmp(&freq[a]).then(a.cmp(&b)));
+    let mut sorted = sorted.into_iter();
+    for (len, &count) in len_count.iter().enumerate().skip(1) {
+        for sym in sorted.by_ref().take(count as usize) {
+            lengths[sym] = len as u8;
+        }
+    }
+    None
+}
+
+/// Assigns canonical codes to the given code lengths, matching the tree built by the decoder.
+fn build_codes(lengths: &[u8]) -> Vec<u16> {
+    let mut len_count = [0u32; MAX_CODE_LENGTH as usize + 1];
+    for &len in lengths.iter() {
+        len_count[len as usize] += 1;
+    }
+    len_count[0] = 0;
+    let mut next_code = [0u32; MAX_CODE_LENGTH as usize + 1];
+    let mut code = 0;
+    for len in 1..=MAX_CODE_LENGTH as usize {
+        code = (code + len_count[len - 1]) << 1;
+        next_code[len] = code;
+    }
+    lengths.iter().map(|&len| {
+        if len == 0 {
+            return 0
+        }
+        let code = next_code[len as usize];
+        next_code[len as usize] += 1;
+        code as u16
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{Decoder, Lh5Decoder, Lh7Decoder};
+
+    fn round_trip<E: Write, F: FnOnce(Vec<u8>) -> E, G: FnOnce(E) -> io::Result<Vec<u8>>>(
+            data: &[u8], new: F, finish: G
+        ) -> Vec<u8>
+    {
+        let mut encoder = new(Vec::new());
+        encoder.write_all(data).unwrap();
+        finish(encoder).unwrap()
+    }
+
+    #[test]
+    fn lhav2_encoder_works() {
+        let mut data = Vec::new();
+        let mut seed = 1u32;
+        for n in 0..200_000u32 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            match n / 20_000 % 3 {
+                0 => data.push(b"abcdefgh"[(seed >> 16) as usize % 8]),
+                1 => data.push((seed >> 16) as u8),
+                _ => data.push(b'x')
+            }
+        }
+        for len in [0, 1, 2, 3, 100, 5000, data.len()] {
+            let data = &data[..len];
+            let encoded = round_trip(data, Lh5Encoder::new, Lh5Encoder::finish);
+            let mut decoded = vec![0u8; len];
+            Lh5Decoder::new(&encoded[..]).fill_buffer(&mut decoded).unwrap();
+            assert_eq!(decoded, data);
+            for encoded in [round_trip(data, Lh6Encoder::new, Lh6Encoder::finish),
+                            round_trip(data, Lh7Encoder::new, Lh7Encoder::finish)]
+            {
+                let mut decoded = vec![0u8; len];
+                Lh7Decoder::new(&encoded[..]).fill_buffer(&mut decoded).unwrap();
+                assert_eq!(decoded, data);
+            }
+        }
+    }
+
+    #[test]
+    fn build_lengths_works() {
+        let mut lengths = [0u8; 5];
+        assert_eq!(build_lengths(&[0, 0, 7, 0, 0], &mut lengths), Some(2));
+        assert_eq!(build_lengths(&[0; 5], &mut lengths), Some(0));
+        assert_eq!(build_lengths(&[1, 1, 2, 0, 4], &mut lengths), None);
+        assert_eq!(lengths, [3, 3, 2, 0, 1]);
+        assert_eq!(build_codes(&lengths), [0b110, 0b111, 0b10, 0, 0b0]);
+        // fibonacci frequencies produce lengths exceeding the limit
+        let mut freq = [0u32; 30];
+        let (mut a, mut b) = (1, 1);
+        for f in freq.iter_mut() {
+            *f = a;
+            (a, b) = (b, a + b);
+        }
+        let mut lengths = [0u8; 30];
+        assert_eq!(build_lengths(&freq, &mut lengths), None);
+        assert!(lengths.iter().all(|&len| (1..=16).contains(&len)));
+        let kraft: u32 = lengths.iter().map(|&len| 1 << (16 - len)).sum();
+        assert_eq!(kraft, 1 << 16);
+    }
+}
@@ -20,6 +20,8 @@ pub enum LhaError<E> {
     Decompress(&'static str),
     /// Checksum mismatch.
     Checksum(&'static str),
+    /// One of the [`Limits`][crate::Limits] has been exceeded.
+    Limit(&'static str),
 }
 
 impl<E: fmt::Display> fmt::Display for LhaError<E> {
@@ -30,6 +32,7 @@ impl<E: fmt::Display> fmt::Display for LhaError<E> {
             HeaderParse(e) => write!(f, "while parsing LHA header: {}", e),
             Decompress(e) => write!(f, "while decompressing: {}", e),
             Checksum(e) => write!(f, "checksum: {}", e),
+            Limit(e) => write!(f, "limit exceeded: {}", e),
         }
     }
 }
@@ -55,6 +58,8 @@ impl From<LhaError<io::Error>> for io::Error {
             HeaderParse(e) => Error::new(ErrorKind::InvalidData, e),
             Decompress(e) => Error::new(ErrorKind::InvalidData, e),
             Checksum(e) => Error::new(ErrorKind::InvalidData, e),
+            // can be recognized by downcasting the inner error to LhaError
+            Limit(e) => Error::new(ErrorKind::InvalidData, LhaError::<Error>::Limit(e)),
         }
     }
 }
@@ -3,26 +3,52 @@
 use std::path::PathBuf;
 #[cfg(feature = "std")]
 use std::borrow::Cow;
-#[cfg(not(feature = "std"))]
-use alloc::{boxed::Box, string::String, borrow::Cow};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{boxed::Box, string::String, borrow::Cow, vec::Vec};
 use chrono::{LocalResult, prelude::*};
 
+mod amiga;
+#[cfg(feature = "alloc")]
+mod builder;
 mod compression;
+#[cfg(feature = "alloc")]
+mod iter;
+#[cfg(feature = "alloc")]
+mod manifest;
 mod ostype;
 mod msdos;
+mod os2;
+mod os9;
 mod parser;
+#[cfg(feature = "serde")]
+mod raw_bytes;
 mod timestamp;
 
 use parser::ext::*;
 
+pub use amiga::*;
+#[cfg(feature = "alloc")]
+pub use builder::*;
 pub use msdos::*;
 pub use compression::*;
+#[cfg(feature = "alloc")]
+pub use iter::*;
+#[cfg(feature = "alloc")]
+pub use manifest::*;
 pub use ostype::*;
+pub use os2::*;
+pub use os9::*;
 pub use parser::*;
 pub use timestamp::*;
 
 /// Semi-parsed LHA header.
+///
+/// With `serde` feature enabled, the raw byte fields are serialized as byte arrays.
+///
+/// See [LhaHeaderRef] for a header parsed in place, borrowing its raw data.
+#[cfg(feature = "alloc")]
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LhaHeader {
     /// Header level: 0, 1, 2 or 3.
     pub level: u8,
@@ -35,8 +61,12 @@ pub struct LhaHeader {
     /// A raw filename for level 1 or 0 headers, might be empty. Always being empty for levels 2 or 3.
     ///
     /// In this instance the filename is stored in extra headers.
+    #[cfg_attr(feature = "serde", serde(with = "raw_bytes"))]
     pub filename: Box<[u8]>,
     /// MS-DOS attributes.
+    ///
+    /// Archives created on [OsType::Amiga] store Amiga protection flags in this field instead,
+    /// see [LhaHeader::parse_amiga_protection].
     pub msdos_attrs: MsDosAttrs,
     /// File's last modified date, format depends on the header level.
     ///
@@ -51,13 +81,53 @@ pub struct LhaHeader {
     /// Uncompressed file's CRC-16.
     pub file_crc: u16,
     /// An extended area as raw bytes.
+    #[cfg_attr(feature = "serde", serde(with = "raw_bytes"))]
     pub extended_area: Box<[u8]>,
     /// The size of the first extra header.
     pub first_header_len: u32,
     /// The extra headers' data.
+    #[cfg_attr(feature = "serde", serde(with = "raw_bytes"))]
     pub extra_headers: Box<[u8]>,
 }
 
+/// Semi-parsed LHA header borrowing its raw data, e.g. from an archive in memory.
+///
+/// The header is parsed in place with [LhaHeaderRef::read] or iterated with
+/// [LhaSliceArchive][crate::LhaSliceArchive]. It provides the same methods as [LhaHeader] for parsing
+/// the header properties, all available without the `alloc` feature, except path names, comments and
+/// OS/2 extended attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LhaHeaderRef<'a> {
+    /// Header level: 0, 1, 2 or 3.
+    pub level: u8,
+    /// Raw compression identifier.
+    pub compression: [u8;5],
+    /// Compressed file size.
+    pub compressed_size: u64,
+    /// Original file size.
+    pub original_size: u64,
+    /// A raw filename for level 1 or 0 headers, see [LhaHeader::filename].
+    pub filename: &'a [u8],
+    /// MS-DOS attributes, see [LhaHeader::msdos_attrs].
+    pub msdos_attrs: MsDosAttrs,
+    /// File's last modified date, see [LhaHeader::last_modified].
+    pub last_modified: u32,
+    /// A raw OS-TYPE.
+    pub os_type: u8,
+    /// Uncompressed file's CRC-16.
+    pub file_crc: u16,
+    /// An extended area as raw bytes.
+    pub extended_area: &'a [u8],
+    /// The size of the first extra header.
+    pub first_header_len: u32,
+    /// The extra headers' data.
+    ///
+    /// Unlike in [LhaHeader::extra_headers], the CRC-16 field of the ["Common"][EXT_HEADER_COMMON]
+    /// header is left intact when the header is parsed in place.
+    pub extra_headers: &'a [u8],
+}
+
+#[cfg(feature = "alloc")]
 impl Default for LhaHeader {
     fn default() -> Self {
         LhaHeader {
@@ -77,20 +147,223 @@ impl Default for LhaHeader {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl LhaHeader {
+    /// Return the borrowed view of this header.
+    pub fn as_header_ref(&self) -> LhaHeaderRef<'_> {
+        LhaHeaderRef {
+            level: self.level,
+            compression: self.compression,
+            compressed_size: self.compressed_size,
+            original_size: self.original_size,
+            filename: &self.filename,
+            msdos_attrs: self.msdos_attrs,
+            last_modified: self.last_modified,
+            os_type: self.os_type,
+            file_crc: self.file_crc,
+            extended_area: &self.extended_area,
+            first_header_len: self.first_header_len,
+            extra_headers: &self.extra_headers
+        }
+    }
     /// Return whether the archive is an empty directory or a symbolic link.
     pub fn is_directory(&self) -> bool {
-        self.compression_method().ok()
-            .filter(CompressionMethod::is_directory)
-            .is_some()
+        self.as_header_ref().is_directory()
     }
     /// Attempt to parse the `os_type` field and return the `OsType` enum on success.
     pub fn parse_os_type(&self) -> Result<OsType, UnrecognizedOsType> {
-        OsType::try_from(self.os_type)
+        self.as_header_ref().parse_os_type()
     }
     /// Attempt to parse the extended area, extra headers and as a last resort the `last_modified` field
     /// taking into account the header level, and on success return an instance of [`DateTime<Utc>`][DateTime]
     /// or a [NaiveDateTime] wrapped in an `TimestampResult` enum.
+    pub fn parse_last_modified(&self) -> TimestampResult {
+        self.as_header_ref().parse_last_modified()
+    }
+    /// Attempt to parse the `compression` method field and return the `CompressionMethod` enum on success.
+    pub fn compression_method(&self) -> Result<CompressionMethod, UnrecognizedCompressionMethod> {
+        self.as_header_ref().compression_method()
+    }
+    /// Attempt to parse the `filename` field and search extended data for the directory and an
+    /// alternative file name and return a `PathBuf`.
+    ///
+    /// The method converts all non-ASCII or control characters to `%xx` sequences and all system
+    /// specific directory separator characters to `_` in file names.
+    ///
+    /// Malicious path components, like `..`, `.` or `//` are stripped from the path names.
+    ///
+    /// # Notes
+    /// * If the path name could not be found the returned `PathBuf` will be empty.
+    /// * Some filesystems may still reject the file or path names if path names include some forbidden
+    ///   characters, e.g. `?` or `*` in `Windows`.
+    /// * This method makes its best effort to return a non-absolute path name, however it is not guaranteed,
+    ///   so make sure the path is not absolute before creating a file or a directory.
+    /// * If the archive OS is [OsType::Amiga] the file name parsing terminates before the `nul` character.
+    ///
+    /// # `no_std`
+    ///
+    /// This method is only available with `std` feature enabled.
+    #[cfg(feature = "std")]
+    pub fn parse_pathname(&self) -> PathBuf {
+        self.as_header_ref().parse_pathname()
+    }
+    /// Attempt to parse the `filename` field and search extended data for the directory and an
+    /// alternative file name and return a `String` with a possible path to a `filename`,
+    /// separated by '`/`' characters.
+    /// 
+    /// This method is like [`LhaHeader::parse_pathname`] but will return a `String` instead of
+    /// `PathBuf` and can be used without `std` feature enabled.
+    pub fn parse_pathname_to_str(&self) -> String {
+        self.as_header_ref().parse_pathname_to_str()
+    }
+    /// Parse the path name with [`LhaHeader::parse_pathname_to_str`] and split off the target
+    /// of a symbolic link.
+    ///
+    /// Symbolic links are stored as directory entries with the path name of the link followed by
+    /// the '`|`' character and the target path name. Return the path name of the link and its target,
+    /// or the whole path name and `None` if the entry is not a symbolic link.
+    pub fn parse_link(&self) -> (String, Option<String>) {
+        let mut path = self.parse_pathname_to_str();
+        let target = match path.split_once('|') {
+            Some((link, target)) if self.is_directory() => {
+                let target = target.into();
+                path.truncate(link.len());
+                Some(target)
+            }
+            _ => None
+        };
+        (path, target)
+    }
+    /// Attempts to find and return the file comment field in extended header data.
+    ///
+    /// The routine converts all non-ASCII or control characters to `%xx` sequences.
+    ///
+    /// # Notes
+    /// Some archives made on [OsType::Amiga] can have a comment embedded in the filename field
+    /// after the `nul` character. If the comment could not be found in extended data, an attempt
+    /// is made to extract the comment from the filename if the archive OS supports it.
+    pub fn parse_comment(&self) -> Option<Cow<'_, str>> {
+        self.as_header_ref().parse_comment()
+    }
+    /// Return Amiga protection flags if the archive was created on [OsType::Amiga].
+    ///
+    /// LhA for Amiga stores protection flags in place of MS-DOS attributes, so the flags are
+    /// retrieved from the `msdos_attrs` field. Together with [LhaHeader::parse_comment], which
+    /// extracts Amiga file notes, this provides the complete Amiga file metadata.
+    ///
+    /// # Note
+    /// Level 0 headers do not store the OS type, so `None` is returned for them. If the archive
+    /// is known to be created on Amiga, the flags can be converted directly from the `msdos_attrs`
+    /// field with [`AmigaProtection::from_bits_truncate`].
+    pub fn parse_amiga_protection(&self) -> Option<AmigaProtection> {
+        self.as_header_ref().parse_amiga_protection()
+    }
+    /// Attempt to parse the Unix file mode, including file type bits, e.g. `0o100644` for a regular file.
+    ///
+    /// The mode is retrieved from the ["Unix permissions"][EXT_HEADER_UNIX_PERM] extra header or from
+    /// the extended area of level 0 headers of [OsType::Unix] archives.
+    ///
+    /// Return `None` if the mode could not be found or if the archive was created on [OsType::Os9] or
+    /// [OsType::Osk], see [LhaHeader::parse_os9_metadata].
+    pub fn parse_unix_mode(&self) -> Option<u16> {
+        self.as_header_ref().parse_unix_mode()
+    }
+    /// Attempt to parse the Unix owner's user and group ids and return them as a `(uid, gid)` tuple.
+    ///
+    /// The ids are retrieved from the ["Unix UID/GID"][EXT_HEADER_UNIX_UIDGID] extra header or from
+    /// the extended area of level 0 headers of [OsType::Unix] archives.
+    ///
+    /// Return `None` if the ids could not be found or if the archive was created on [OsType::Os9] or
+    /// [OsType::Osk], see [LhaHeader::parse_os9_metadata].
+    pub fn parse_unix_uid_gid(&self) -> Option<(u16, u16)> {
+        self.as_header_ref().parse_unix_uid_gid()
+    }
+    /// Attempt to parse OS-9 and OS-9/68k specific file metadata.
+    ///
+    /// The metadata is collected from:
+    /// * The extended area of level 0 headers of [OsType::Os9] and [OsType::Osk] archives.
+    /// * The ["OS-9"][EXT_HEADER_OS9] extra header.
+    /// * The ["Unix permissions"][EXT_HEADER_UNIX_PERM] and ["Unix UID/GID"][EXT_HEADER_UNIX_UIDGID]
+    ///   extra headers of [OsType::Os9] and [OsType::Osk] archives, which store OS-9 attributes
+    ///   instead of Unix file modes.
+    ///
+    /// Return `None` if no OS-9 metadata could be found.
+    pub fn parse_os9_metadata(&self) -> Option<Os9Metadata> {
+        self.as_header_ref().parse_os9_metadata()
+    }
+    /// Attempt to parse the content of the ["extended attributes"][EXT_HEADER_EXT_ATTRS] extra header,
+    /// written e.g. by LHA for OS/2.
+    ///
+    /// Return `None` if the extra header is missing.
+    pub fn parse_ext_attrs(&self) -> Option<ExtAttrs> {
+        self.as_header_ref().parse_ext_attrs()
+    }
+    /// Return OS/2 extended attributes found in the ["OS/2 extended attributes"][EXT_HEADER_OS2_EAS]
+    /// extra headers.
+    ///
+    /// The content of each header is parsed as an OS/2 `FEA2LIST` structure. Malformed headers are ignored.
+    pub fn parse_os2_eas(&self) -> Vec<Os2Ea> {
+        self.as_header_ref().parse_os2_eas()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<LhaHeaderRef<'_>> for LhaHeader {
+    fn from(header: LhaHeaderRef<'_>) -> Self {
+        header.to_header()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> From<&'a LhaHeader> for LhaHeaderRef<'a> {
+    fn from(header: &'a LhaHeader) -> Self {
+        header.as_header_ref()
+    }
+}
+
+impl<'a> LhaHeaderRef<'a> {
+    /// Return an owned copy of this header.
+    ///
+    /// The CRC-16 field of the ["Common"][EXT_HEADER_COMMON] header is reset to 0 in the copied
+    /// extra headers' data, exactly like in the header read with [LhaHeader::read].
+    #[cfg(feature = "alloc")]
+    pub fn to_header(&self) -> LhaHeader {
+        let mut extra_headers = Box::<[u8]>::from(self.extra_headers);
+        let base = self.extra_headers.as_ptr() as usize;
+        if let Some(offset) = self.iter_extra()
+                              .find(|header| header[0] == EXT_HEADER_COMMON)
+                              .map(|header| header.as_ptr() as usize - base)
+        {
+            if let Some(crc) = extra_headers.get_mut(offset + 1..offset + 3) {
+                crc.fill(0);
+            }
+        }
+        LhaHeader {
+            level: self.level,
+            compression: self.compression,
+            compressed_size: self.compressed_size,
+            original_size: self.original_size,
+            filename: self.filename.into(),
+            msdos_attrs: self.msdos_attrs,
+            last_modified: self.last_modified,
+            os_type: self.os_type,
+            file_crc: self.file_crc,
+            extended_area: self.extended_area.into(),
+            first_header_len: self.first_header_len,
+            extra_headers
+        }
+    }
+    /// See [`LhaHeader::is_directory`].
+    pub fn is_directory(&self) -> bool {
+        self.compression_method().ok()
+            .filter(CompressionMethod::is_directory)
+            .is_some()
+    }
+    /// See [`LhaHeader::parse_os_type`].
+    pub fn parse_os_type(&self) -> Result<OsType, UnrecognizedOsType> {
+        OsType::try_from(self.os_type)
+    }
+    /// See [`LhaHeader::parse_last_modified`].
     pub fn parse_last_modified(&self) -> TimestampResult {
         for header in self.iter_extra() {
             match header {
@@ -122,29 +395,11 @@ impl LhaHeader {
             Utc.timestamp_opt(self.last_modified as i64, 0).into()
         }
     }
-    /// Attempt to parse the `compression` method field and return the `CompressionMethod` enum on success.
+    /// See [`LhaHeader::compression_method`].
     pub fn compression_method(&self) -> Result<CompressionMethod, UnrecognizedCompressionMethod> {
         CompressionMethod::try_from(&self.compression)
     }
-    /// Attempt to parse the `filename` field and search extended data for the directory and an
-    /// alternative file name and return a `PathBuf`.
-    ///
-    /// The method converts all non-ASCII or control characters to `%xx` sequences and all system
-    /// specific directory separator characters to `_` in file names.
-    ///
-    /// Malicious path components, like `..`, `.` or `//` are stripped from the path names.
-    ///
-    /// # Notes
-    /// * If the path name could not be found the returned `PathBuf` will be empty.
-    /// * Some filesystems may still reject the file or path names if path names include some forbidden
-    ///   characters, e.g. `?` or `*` in `Windows`.
-    /// * This method makes its best effort to return a non-absolute path name, however it is not guaranteed,
-    ///   so make sure the path is not absolute before creating a file or a directory.
-    /// * If the archive OS is [OsType::Amiga] the file name parsing terminates before the `nul` character.
-    ///
-    /// # `no_std`
-    ///
-    /// This method is only available with `std` feature enabled.
+    /// See [`LhaHeader::parse_pathname`].
     #[cfg(feature = "std")]
     pub fn parse_pathname(&self) -> PathBuf {
         let mut path = PathBuf::new();
@@ -163,10 +418,10 @@ impl LhaHeader {
         }
         if filename.is_empty() {
             let data = if nilterm {
-                split_data_at_nil_or_end(&self.filename).0
+                split_data_at_nil_or_end(self.filename).0
             }
             else {
-                &self.filename
+                self.filename
             };
             parse_pathname(data, &mut path);
         }
@@ -175,12 +430,8 @@ impl LhaHeader {
         }
         path
     }
-    /// Attempt to parse the `filename` field and search extended data for the directory and an
-    /// alternative file name and return a `String` with a possible path to a `filename`,
-    /// separated by '`/`' characters.
-    /// 
-    /// This method is like [`LhaHeader::parse_pathname`] but will return a `String` instead of
-    /// `PathBuf` and can be used without `std` feature enabled.
+    #[cfg(feature = "alloc")]
+    /// See [`LhaHeader::parse_pathname_to_str`].
     pub fn parse_pathname_to_str(&self) -> String {
         let mut path = String::new();
         let mut filename = Cow::Borrowed("");
@@ -198,10 +449,10 @@ impl LhaHeader {
         }
         if filename.is_empty() {
             let data = if nilterm {
-                split_data_at_nil_or_end(&self.filename).0
+                split_data_at_nil_or_end(self.filename).0
             }
             else {
-                &self.filename
+                self.filename
             };
             parse_pathname_to_str(data, &mut path);
         }
@@ -213,16 +464,10 @@ impl LhaHeader {
         }
         path
     }
-    /// Attempts to find and return the file comment field in extended header data.
-    ///
-    /// The routine converts all non-ASCII or control characters to `%xx` sequences.
-    ///
-    /// # Notes
-    /// Some archives made on [OsType::Amiga] can have a comment embedded in the filename field
-    /// after the `nul` character. If the comment could not be found in extended data, an attempt
-    /// is made to extract the comment from the filename if the archive OS supports it.
-    pub fn parse_comment(&self) -> Option<Cow<str>> {
-        let mut raw_filename = &self.filename[..];
+    #[cfg(feature = "alloc")]
+    /// See [`LhaHeader::parse_comment`], the returned comment may borrow from the header data.
+    pub fn parse_comment(&self) -> Option<Cow<'a, str>> {
+        let mut raw_filename = self.filename;
         for header in self.iter_extra() {
             match header {
                 [EXT_HEADER_FILENAME, data @ ..] => {
@@ -246,6 +491,143 @@ impl LhaHeader {
             None
         }
     }
+    /// See [`LhaHeader::parse_amiga_protection`].
+    pub fn parse_amiga_protection(&self) -> Option<AmigaProtection> {
+        if self.parse_os_type() == Ok(OsType::Amiga) {
+            Some(AmigaProtection::from_bits_truncate(self.msdos_attrs.bits() as u8))
+        }
+        else {
+            None
+        }
+    }
+    /// See [`LhaHeader::parse_unix_mode`].
+    pub fn parse_unix_mode(&self) -> Option<u16> {
+        if self.is_os9() {
+            return None
+        }
+        self.iter_extra().find_map(|header| match header {
+            [EXT_HEADER_UNIX_PERM, data @ ..] => data.get(0..2).and_then(read_u16),
+            _ => None
+        })
+        .or_else(|| self.unix_extended_area().and_then(|area| area.get(5..7)).and_then(read_u16))
+    }
+    /// See [`LhaHeader::parse_unix_uid_gid`].
+    pub fn parse_unix_uid_gid(&self) -> Option<(u16, u16)> {
+        if self.is_os9() {
+            return None
+        }
+        let parse = |gid: &[u8], uid: &[u8]| Some((read_u16(uid)?, read_u16(gid)?));
+        self.iter_extra().find_map(|header| match header {
+            [EXT_HEADER_UNIX_UIDGID, data @ ..] if data.len() >= 4 => parse(&data[0..2], &data[2..4]),
+            _ => None
+        })
+        .or_else(|| self.unix_extended_area().and_then(|area| {
+            parse(area.get(9..11)?, area.get(7..9)?)
+        }))
+    }
+    /// See [`LhaHeader::parse_os9_metadata`].
+    pub fn parse_os9_metadata(&self) -> Option<Os9Metadata> {
+        let mut meta: Option<Os9Metadata> = None;
+        let os_type = self.parse_os_type();
+        let is_os9 = matches!(os_type, Ok(OsType::Os9)|Ok(OsType::Osk));
+        if self.level < 2 {
+            let area = self.extended_area;
+            match os_type {
+                // attributes, unknown, file descriptor type, an embedded OS-9 extra header
+                Ok(OsType::Os9) => {
+                    if let Some(attrs) = area.get(0..2).and_then(read_u16) {
+                        let meta = meta.get_or_insert_with(Default::default);
+                        meta.attrs = Os9Attrs::from_bits_retain(attrs);
+                        meta.fd_type = area.get(4..6).and_then(read_u16);
+                    }
+                    if let Some(&[lo, hi, EXT_HEADER_OS9, ref data @ ..]) = area.get(6..) {
+                        let len = u16::from_le_bytes([lo, hi]) as usize;
+                        if let Some(data) = data.get(..len.saturating_sub(3)) {
+                            apply_os9_header(meta.get_or_insert_with(Default::default), data);
+                        }
+                    }
+                }
+                // minor version, last modified, unknown, attributes, user id, group id
+                Ok(OsType::Osk) => {
+                    if let Some(attrs) = area.get(9..11).and_then(read_u16) {
+                        let meta = meta.get_or_insert_with(Default::default);
+                        meta.attrs = Os9Attrs::from_bits_retain(attrs);
+                        meta.owner = area.get(11..13).and_then(read_u16);
+                        meta.group = area.get(13..15).and_then(read_u16);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for header in self.iter_extra() {
+            match header {
+                [EXT_HEADER_OS9, data @ ..] => {
+                    apply_os9_header(meta.get_or_insert_with(Default::default), data);
+                }
+                [EXT_HEADER_UNIX_PERM, data @ ..] if is_os9 => {
+                    if let Some(attrs) = data.get(0..2).and_then(read_u16) {
+                        meta.get_or_insert_with(Default::default).attrs = Os9Attrs::from_bits_retain(attrs);
+                    }
+                }
+                [EXT_HEADER_UNIX_UIDGID, data @ ..] if is_os9 && data.len() >= 4 => {
+                    let meta = meta.get_or_insert_with(Default::default);
+                    meta.group = read_u16(&data[0..2]);
+                    meta.owner = read_u16(&data[2..4]);
+                }
+                _ => {}
+            }
+        }
+        meta
+    }
+    /// See [`LhaHeader::parse_ext_attrs`].
+    pub fn parse_ext_attrs(&self) -> Option<ExtAttrs> {
+        self.iter_extra().find_map(|header| match header {
+            [EXT_HEADER_EXT_ATTRS, data @ ..] => ExtAttrs::parse(data),
+            _ => None
+        })
+    }
+    #[cfg(feature = "alloc")]
+    /// See [`LhaHeader::parse_os2_eas`].
+    pub fn parse_os2_eas(&self) -> Vec<Os2Ea> {
+        let mut eas = Vec::new();
+        for header in self.iter_extra() {
+            if let [EXT_HEADER_OS2_EAS, data @ ..] = header {
+                if let Some(list) = Os2Ea::parse_fea2list(data) {
+                    eas.extend(list);
+                }
+            }
+        }
+        eas
+    }
+    fn is_os9(&self) -> bool {
+        matches!(self.parse_os_type(), Ok(OsType::Os9)|Ok(OsType::Osk))
+    }
+    fn unix_extended_area(&self) -> Option<&'a [u8]> {
+        if self.level == 0 && self.parse_os_type() == Ok(OsType::Unix) {
+            Some(self.extended_area)
+        }
+        else {
+            None
+        }
+    }
+}
+
+// the compression method identifier with non-printable characters replaced by `?`
+#[cfg(feature = "alloc")]
+pub(crate) fn method_to_string(compression: &[u8;5]) -> String {
+    compression.iter().map(|&c| {
+        if c.is_ascii_graphic() { c as char } else { '?' }
+    }).collect()
+}
+
+// file descriptor type, attributes
+fn apply_os9_header(meta: &mut Os9Metadata, data: &[u8]) {
+    if let Some(fd_type) = data.get(5..7).and_then(read_u16) {
+        meta.fd_type = Some(fd_type);
+    }
+    if let Some(attrs) = data.get(7..9).and_then(read_u16) {
+        meta.attrs = Os9Attrs::from_bits_retain(attrs);
+    }
 }
 
 /// Returns a `NaiveDateTime` on success from MS-DOS timestamp format.
@@ -274,6 +656,15 @@ pub fn parse_msdos_datetime(ts: u32) -> Option<NaiveDateTime> {
     NaiveDate::from_ymd_opt(year, mon, day).and_then(|d| d.and_hms_opt(hour, min, sec))
 }
 
+/// Returns a date and time on success encoded in the MS-DOS format, see [parse_msdos_datetime].
+///
+/// Seconds are rounded down to even values. Return `None` if the year is out of the [1980, 2107] range.
+pub fn to_msdos_datetime(dt: &NaiveDateTime) -> Option<u32> {
+    let year = u32::try_from(dt.year()).ok()?.checked_sub(1980).filter(|&y| y <= 0x7f)?;
+    Some(year << 25 | dt.month() << 21 | dt.day() << 16 |
+         dt.hour() << 11 | dt.minute() << 5 | dt.second() >> 1)
+}
+
 /// Returns a `DateTime<Utc>` on success from Windows [FILETIME] format.
 ///
 /// [FILETIME]: https://docs.microsoft.com/en-us/windows/win32/api/minwinbase/ns-minwinbase-filetime
@@ -0,0 +1,76 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// Amiga protection flags, displayed by AmigaDOS as `hsparwed`.
+    ///
+    /// Beware that the `READ`, `WRITE`, `EXECUTE` and `DELETE` flags are active-low: when set, the
+    /// operation is **not** permitted. Use [`AmigaProtection::is_readable`] and friends to query
+    /// permissions.
+    #[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct AmigaProtection: u8 {
+        const DELETE  = 0b00000001;
+        const EXECUTE = 0b00000010;
+        const WRITE   = 0b00000100;
+        const READ    = 0b00001000;
+        const ARCHIVE = 0b00010000;
+        const PURE    = 0b00100000;
+        const SCRIPT  = 0b01000000;
+        const HOLD    = 0b10000000;
+    }
+}
+
+impl AmigaProtection {
+    /// Return whether the file can be read.
+    pub fn is_readable(self) -> bool {
+        !self.contains(AmigaProtection::READ)
+    }
+    /// Return whether the file can be written.
+    pub fn is_writable(self) -> bool {
+        !self.contains(AmigaProtection::WRITE)
+    }
+    /// Return whether the file can be executed.
+    pub fn is_executable(self) -> bool {
+        !self.contains(AmigaProtection::EXECUTE)
+    }
+    /// Return whether the file can be deleted.
+    pub fn is_deletable(self) -> bool {
+        !self.contains(AmigaProtection::DELETE)
+    }
+    /// Return Unix permission bits (`0o777` mask) mapped from Amiga protection flags.
+    ///
+    /// The read and execute permissions are granted to everyone, while the write permission
+    /// is granted to the owner only.
+    pub fn to_unix_permissions(self) -> u16 {
+        let mut mode = 0;
+        if self.is_readable() {
+            mode |= 0o444;
+        }
+        if self.is_writable() {
+            mode |= 0o200;
+        }
+        if self.is_executable() {
+            mode |= 0o111;
+        }
+        mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amiga_protection_works() {
+        // ----rwed
+        let prot = AmigaProtection::empty();
+        assert!(prot.is_readable() && prot.is_writable() && prot.is_executable() && prot.is_deletable());
+        assert_eq!(prot.to_unix_permissions(), 0o755);
+        // ----rw-d
+        assert_eq!(AmigaProtection::EXECUTE.to_unix_permissions(), 0o644);
+        // -s--r---
+        let prot = AmigaProtection::from_bits_retain(0b01000111);
+        assert!(prot.contains(AmigaProtection::SCRIPT));
+        assert!(!prot.is_deletable());
+        assert_eq!(prot.to_unix_permissions(), 0o444);
+    }
+}
@@ -0,0 +1,618 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{error::Error, io};
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec, vec::Vec};
+use crate::crc::Crc16;
+use super::*;
+use super::parser::{read_u16, split_data_at_nil_or_end};
+
+/// The error returned when an [`LhaHeader`] can't be built or serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderEncodeError(pub &'static str);
+
+/// A builder of [`LhaHeader`] instances.
+///
+/// The builder assembles the extra headers appropriate for the selected header level and the
+/// resulting header can be serialized with [`LhaHeader::to_bytes`].
+///
+/// The builder can also be created from an existing header with [`LhaHeaderBuilder::from_header`],
+/// e.g. to re-serialize the header into a different level.
+///
+/// ```
+/// use delharc::header::*;
+///
+/// let header = LhaHeaderBuilder::new()
+///     .level(2)
+///     .path("docs/readme.txt")
+///     .compression(CompressionMethod::Lh0)
+///     .sizes(6, 6)
+///     .file_crc(0xA51F)
+///     .os_type(OsType::Unix)
+///     .unix_permissions(0o100644)
+///     .build().unwrap();
+/// assert_eq!(header.parse_pathname_to_str(), "docs/readme.txt");
+/// let bytes = header.to_bytes().unwrap();
+/// let parsed = LhaHeader::read(&mut &bytes[..]).unwrap().unwrap();
+/// assert_eq!(parsed.parse_pathname_to_str(), "docs/readme.txt");
+/// ```
+#[derive(Debug, Clone)]
+pub struct LhaHeaderBuilder {
+    level: u8,
+    compression: [u8;5],
+    compressed_size: u64,
+    original_size: u64,
+    file_crc: u16,
+    dirs: Vec<Box<[u8]>>,
+    filename: Box<[u8]>,
+    last_modified: TimestampResult,
+    msdos_attrs: MsDosAttrs,
+    os_type: u8,
+    unix_perms: Option<u16>,
+    unix_uid_gid: Option<(u16, u16)>,
+    comment: Option<Box<[u8]>>,
+    extended_area: Option<Box<[u8]>>,
+    // extra headers replacing the extended area in level 2 and 3 headers, `None` if not mapped
+    area_extra: Option<Vec<(u8, Box<[u8]>)>>,
+    extra: Vec<(u8, Box<[u8]>)>,
+}
+
+impl Default for LhaHeaderBuilder {
+    fn default() -> Self {
+        LhaHeaderBuilder {
+            level: 2,
+            compression: *CompressionMethod::Lh0.as_identifier(),
+            compressed_size: 0,
+            original_size: 0,
+            file_crc: 0,
+            dirs: Vec::new(),
+            filename: Box::new([]),
+            last_modified: TimestampResult::None,
+            msdos_attrs: MsDosAttrs::ARCHIVE,
+            os_type: 0,
+            unix_perms: None,
+            unix_uid_gid: None,
+            comment: None,
+            extended_area: None,
+            area_extra: None,
+            extra: Vec::new(),
+        }
+    }
+}
+
+impl LhaHeaderBuilder {
+    /// Create a new builder of a level 2 header with the `-lh0-` compression method.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create a new builder initialized with the properties of the given header.
+    ///
+    /// The path, comment, timestamp, attributes and Unix permissions are extracted from the header,
+    /// so they can be stored in the format appropriate for another header level.
+    /// Other extra headers are retained as is and the extended area is retained for levels 0 and 1.
+    ///
+    /// The level 0 extended area of [`OsType::Unix`] and [`OsType::Osk`], and the extended area of
+    /// [`OsType::Os9`] are converted to the equivalent extra headers when building a level 2 or 3
+    /// header. Building such a header from any other non-empty extended area fails.
+    pub fn from_header(header: &LhaHeader) -> Self {
+        let mut builder = LhaHeaderBuilder {
+            level: header.level,
+            compression: header.compression,
+            compressed_size: header.compressed_size,
+            original_size: header.original_size,
+            file_crc: header.file_crc,
+            last_modified: header.parse_last_modified(),
+            msdos_attrs: header.msdos_attrs,
+            os_type: header.os_type,
+            ..Default::default()
+        };
+        if header.level < 2 && !header.extended_area.is_empty() {
+            let area = &header.extended_area;
+            builder.extended_area = Some(area.clone());
+            match header.parse_os_type() {
+                // minor version, last modified, mode, uid, gid
+                Ok(OsType::Unix) if header.level == 0 && area.len() == 11 => {
+                    builder.unix_perms = read_u16(&area[5..7]);
+                    if let (Some(uid), Some(gid)) = (read_u16(&area[7..9]), read_u16(&area[9..11])) {
+                        builder.unix_uid_gid = Some((uid, gid));
+                    }
+                    builder.area_extra = Some(Vec::new());
+                }
+                // minor version, last modified, unknown, attributes, user id, group id;
+                // LHA for OSK stores only the attributes and ids in the extra headers of level 1 and 2
+                Ok(OsType::Osk) if area.len() == 15 => {
+                    builder.unix_perms = read_u16(&area[9..11]);
+                    if let (Some(uid), Some(gid)) = (read_u16(&area[11..13]), read_u16(&area[13..15])) {
+                        builder.unix_uid_gid = Some((uid, gid));
+                    }
+                    builder.area_extra = Some(Vec::new());
+                }
+                // attributes, unknown, file descriptor type, an embedded OS-9 extra header;
+                // LHA for OS-9 stores only the latter in level 1 and 2 headers, it includes
+                // the attributes and the file descriptor type as well
+                Ok(OsType::Os9) => {
+                    if let Some(&[lo, hi, EXT_HEADER_OS9, ref data @ ..]) = area.get(6..) {
+                        if u16::from_le_bytes([lo, hi]) as usize == data.len() + 3 {
+                            builder.area_extra = Some(vec![(EXT_HEADER_OS9, data.into())]);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut filename = &header.filename[..];
+        if header.parse_os_type() == Ok(OsType::Amiga) {
+            let (name, comment) = split_data_at_nil_or_end(filename);
+            filename = name;
+            builder.comment = comment.map(Into::into);
+        }
+        let mut dirs = Vec::new();
+        for header in header.iter_extra() {
+            match header {
+                [EXT_HEADER_COMMON, ..]|[EXT_HEADER_MSDOS_ATTRS, ..]|
+                [EXT_HEADER_MSDOS_SIZE, ..]|[EXT_HEADER_UNIX_TIME, ..] => {}
+                [EXT_HEADER_FILENAME, data @ ..] => {
+                    filename = data;
+                }
+                [EXT_HEADER_PATH, data @ ..] => {
+                    dirs = data.split(|&c| c == 0xFF).filter(|s| !s.is_empty()).collect();
+                }
+                [EXT_HEADER_COMMENT, data @ ..] => {
+                    builder.comment = Some(data.into());
+                }
+                [EXT_HEADER_UNIX_PERM, data @ ..] if data.len() >= 2 => {
+                    builder.unix_perms = read_u16(&data[0..2]);
+                }
+                [EXT_HEADER_UNIX_UIDGID, data @ ..] if data.len() >= 4 => {
+                    if let (Some(gid), Some(uid)) = (read_u16(&data[0..2]), read_u16(&data[2..4])) {
+                        builder.unix_uid_gid = Some((uid, gid));
+                    }
+                }
+                [id, data @ ..] => {
+                    builder.extra.push((*id, data.into()));
+                }
+                [] => {}
+            }
+        }
+        // level 0 and 1 file names may contain directories
+        let mut parts = filename.split(|&c| c == b'/' || c == b'\\' || c == 0xFF)
+                                .filter(|s| !s.is_empty());
+        let name = parts.next_back();
+        builder.dirs = dirs.into_iter().chain(parts).map(Into::into).collect();
+        builder.filename = name.unwrap_or_default().into();
+        builder
+    }
+    /// Set the header level: 0, 1, 2 or 3.
+    pub fn level(mut self, level: u8) -> Self {
+        self.level = level;
+        self
+    }
+    /// Set the compression method.
+    pub fn compression(mut self, method: CompressionMethod) -> Self {
+        self.compression = *method.as_identifier();
+        self
+    }
+    /// Set the raw compression method identifier.
+    pub fn raw_compression(mut self, compression: [u8;5]) -> Self {
+        self.compression = compression;
+        self
+    }
+    /// Set the compressed and original file sizes.
+    pub fn sizes(mut self, compressed_size: u64, original_size: u64) -> Self {
+        self.compressed_size = compressed_size;
+        self.original_size = original_size;
+        self
+    }
+    /// Set the CRC-16 of the uncompressed file.
+    pub fn file_crc(mut self, crc: u16) -> Self {
+        self.file_crc = crc;
+        self
+    }
+    /// Set the raw path name, with path components separated by `/` or `\` characters.
+    ///
+    /// For directories (`-lhd-`) the last path component is the name of the directory.
+    pub fn path<P: AsRef<[u8]>>(mut self, path: P) -> Self {
+        let mut parts: Vec<Box<[u8]>> = path.as_ref()
+                                            .split(|&c| c == b'/' || c == b'\\')
+                                            .filter(|s| !s.is_empty())
+                                            .map(Into::into)
+                                            .collect();
+        self.filename = parts.pop().unwrap_or_default();
+        self.dirs = parts;
+        self
+    }
+    /// Set the last modified timestamp.
+    ///
+    /// Levels 0 and 1 store the timestamp in the MS-DOS format. In this instance the `Utc` variant is
+    /// converted to the naive date and time in the UTC time zone. A level 1 header additionally stores
+    /// the `Utc` variant in the ["Unix time"][EXT_HEADER_UNIX_TIME] extra header.
+    ///
+    /// Levels 2 and 3 store the Unix timestamp. In this instance the `Naive` variant is assumed to be
+    /// in the UTC time zone.
+    pub fn last_modified<T: Into<TimestampResult>>(mut self, ts: T) -> Self {
+        self.last_modified = ts.into();
+        self
+    }
+    /// Set MS-DOS attributes.
+    pub fn msdos_attrs(mut self, attrs: MsDosAttrs) -> Self {
+        self.msdos_attrs = attrs;
+        self
+    }
+    /// Set the OS type.
+    pub fn os_type(mut self, os_type: OsType) -> Self {
+        self.os_type = os_type.into();
+        self
+    }
+    /// Set the raw OS type.
+    pub fn raw_os_type(mut self, os_type: u8) -> Self {
+        self.os_type = os_type;
+        self
+    }
+    /// Set Unix file mode, including file type bits.
+    pub fn unix_permissions(mut self, mode: u16) -> Self {
+        self.unix_perms = Some(mode);
+        self
+    }
+    /// Set Unix user and group ids.
+    pub fn unix_uid_gid(mut self, uid: u16, gid: u16) -> Self {
+        self.unix_uid_gid = Some((uid, gid));
+        self
+    }
+    /// Set the raw file comment.
+    ///
+    /// The comment can't be stored in level 0 headers unless the OS type is [OsType::Amiga],
+    /// in which case it is stored after the `nul` character in the file name.
+    pub fn comment<C: AsRef<[u8]>>(mut self, comment: C) -> Self {
+        self.comment = Some(comment.as_ref().into());
+        self
+    }
+    /// Set the raw extended area, only stored in level 0 and 1 headers.
+    ///
+    /// For level 0 headers the extended area follows the OS type byte.
+    ///
+    /// A non-empty extended area set with this method can't be converted to extra headers, so
+    /// building a level 2 or 3 header fails.
+    pub fn extended_area<A: AsRef<[u8]>>(mut self, area: A) -> Self {
+        self.extended_area = Some(area.as_ref().into());
+        self.area_extra = None;
+        self
+    }
+    /// Append a raw extra header, excluding the next header length field.
+    ///
+    /// Extra headers can't be stored in level 0 headers.
+    pub fn extra_header<D: AsRef<[u8]>>(mut self, id: u8, data: D) -> Self {
+        self.extra.push((id, data.as_ref().into()));
+        self
+    }
+    /// Build the header.
+    ///
+    /// Properties that can't be represented by the selected header level are skipped, except for
+    /// the extended area.
+    ///
+    /// # Errors
+    /// Return an error if the header level is invalid, the file sizes or the timestamp can't be stored
+    /// in the selected header level or the extended area can't be converted to extra headers of
+    /// level 2 and 3 headers.
+    pub fn build(&self) -> Result<LhaHeader, HeaderEncodeError> {
+        let level = self.level;
+        if level > 3 {
+            return Err(HeaderEncodeError("unknown header level"))
+        }
+        let is_directory = matches!(CompressionMethod::try_from(&self.compression),
+                                    Ok(m) if m.is_directory());
+        let mut last_modified = 0;
+        let mut unix_time = None;
+        if level < 2 {
+            if let (1, TimestampResult::Utc(dt)) = (level, &self.last_modified) {
+                unix_time = Some(u32::try_from(dt.timestamp())
+                    .map_err(|_| HeaderEncodeError("timestamp out of range"))?);
+            }
+            if let Some(dt) = self.last_modified.to_naive_utc() {
+                // the timestamp out of the MS-DOS range is acceptable if stored in the extra header
+                last_modified = match to_msdos_datetime(&dt) {
+                    Some(ts) => ts,
+                    None if unix_time.is_some() => 0,
+                    None => return Err(HeaderEncodeError("timestamp out of MS-DOS range"))
+                };
+            }
+        }
+        else if let Some(dt) = self.last_modified.to_utc() {
+            last_modified = u32::try_from(dt.timestamp())
+                .map_err(|_| HeaderEncodeError("timestamp out of range"))?;
+        }
+
+        let mut filename = Vec::new();
+        let mut extended_area = Vec::new();
+        let mut extra: Vec<(u8, &[u8])> = Vec::new();
+        let mut dirs = Vec::new();
+        for dir in self.dirs.iter() {
+            dirs.extend_from_slice(dir);
+            dirs.push(0xFF);
+        }
+        let mut name = &self.filename[..];
+        if is_directory && !name.is_empty() {
+            dirs.extend_from_slice(name);
+            dirs.push(0xFF);
+            name = &[];
+        }
+        let mut sizes = [0u8;16];
+        sizes[..8].copy_from_slice(&self.compressed_size.to_le_bytes());
+        sizes[8..].copy_from_slice(&self.original_size.to_le_bytes());
+        let attrs = self.msdos_attrs.bits().to_le_bytes();
+        let mut uid_gid = [0u8;4];
+        let perms = self.unix_perms.map(u16::to_le_bytes);
+        let unix_time = unix_time.map(u32::to_le_bytes);
+        if let Some((uid, gid)) = self.unix_uid_gid {
+            uid_gid[..2].copy_from_slice(&gid.to_le_bytes());
+            uid_gid[2..].copy_from_slice(&uid.to_le_bytes());
+        }
+        if level == 0 {
+            if self.compressed_size > u32::MAX as u64 || self.original_size > u32::MAX as u64 {
+                return Err(HeaderEncodeError("file size too large for header level"))
+            }
+            // the directory path is stored in the file name
+            for part in dirs.split(|&c| c == 0xFF).filter(|s| !s.is_empty()) {
+                filename.extend_from_slice(part);
+                filename.push(b'\\');
+            }
+            filename.extend_from_slice(name);
+            if let (Some(comment), Ok(OsType::Amiga)) = (&self.comment, OsType::try_from(self.os_type)) {
+                filename.push(0);
+                filename.extend_from_slice(comment);
+            }
+            if let Some(area) = &self.extended_area {
+                extended_area.extend_from_slice(area);
+            }
+            else if OsType::try_from(self.os_type) == Ok(OsType::Unix) {
+                // minor version, last modified, mode, uid, gid
+                let ts = self.last_modified.to_utc()
+                             .and_then(|dt| u32::try_from(dt.timestamp()).ok())
+                             .unwrap_or_default();
+                let (uid, gid) = self.unix_uid_gid.unwrap_or_default();
+                extended_area.push(0);
+                extended_area.extend_from_slice(&ts.to_le_bytes());
+                extended_area.extend_from_slice(&self.unix_perms.unwrap_or_default().to_le_bytes());
+                extended_area.extend_from_slice(&uid.to_le_bytes());
+                extended_area.extend_from_slice(&gid.to_le_bytes());
+            }
+        }
+        else {
+            if level >= 2 {
+                extra.push((EXT_HEADER_COMMON, &[0, 0]));
+                extra.push((EXT_HEADER_FILENAME, name));
+                match (&self.extended_area, &self.area_extra) {
+                    (Some(area), None) if !area.is_empty() => {
+                        return Err(HeaderEncodeError("extended area can't be converted to extra headers"))
+                    }
+                    _ => {}
+                }
+            }
+            else {
+                if self.compressed_size > u32::MAX as u64 || self.original_size > u32::MAX as u64 {
+                    return Err(HeaderEncodeError("file size too large for header level"))
+                }
+                filename.extend_from_slice(name);
+                if let Some(area) = &self.extended_area {
+                    extended_area.extend_from_slice(area);
+                }
+            }
+            if !dirs.is_empty() {
+                extra.push((EXT_HEADER_PATH, &dirs));
+            }
+            if let Some(comment) = &self.comment {
+                extra.push((EXT_HEADER_COMMENT, comment));
+            }
+            if self.msdos_attrs.bits() > u8::MAX as u16 {
+                extra.push((EXT_HEADER_MSDOS_ATTRS, &attrs));
+            }
+            if level >= 2 && (self.compressed_size > u32::MAX as u64 ||
+                              self.original_size > u32::MAX as u64) {
+                extra.push((EXT_HEADER_MSDOS_SIZE, &sizes));
+            }
+            if let Some(perms) = &perms {
+                extra.push((EXT_HEADER_UNIX_PERM, perms));
+            }
+            if self.unix_uid_gid.is_some() {
+                extra.push((EXT_HEADER_UNIX_UIDGID, &uid_gid));
+            }
+            if let Some(ts) = &unix_time {
+                extra.push((EXT_HEADER_UNIX_TIME, ts));
+            }
+            if level >= 2 {
+                if let Some(area_extra) = &self.area_extra {
+                    extra.extend(area_extra.iter()
+                                           .filter(|(id, _)| self.extra.iter().all(|(other, _)| other != id))
+                                           .map(|(id, data)| (*id, &data[..])));
+                }
+            }
+            extra.extend(self.extra.iter().map(|(id, data)| (*id, &data[..])));
+        }
+
+        let len_size = if level == 3 { 4 } else { 2 };
+        let mut extra_headers = Vec::new();
+        let mut first_header_len = 0;
+        for (index, (id, data)) in extra.iter().enumerate() {
+            let header_len = 1 + data.len() + len_size;
+            if index == 0 {
+                first_header_len = header_len;
+            }
+            else if len_size == 2 {
+                let len = u16::try_from(header_len).map_err(|_| HeaderEncodeError("extra header too large"))?;
+                extra_headers.extend_from_slice(&len.to_le_bytes());
+            }
+            else {
+                extra_headers.extend_from_slice(&(header_len as u32).to_le_bytes());
+            }
+            extra_headers.push(*id);
+            extra_headers.extend_from_slice(data);
+        }
+        if !extra.is_empty() {
+            extra_headers.resize(extra_headers.len() + len_size, 0);
+        }
+        if len_size == 2 && first_header_len > u16::MAX as usize {
+            return Err(HeaderEncodeError("extra header too large"))
+        }
+
+        Ok(LhaHeader {
+            level,
+            compression: self.compression,
+            compressed_size: self.compressed_size,
+            original_size: self.original_size,
+            filename: filename.into_boxed_slice(),
+            msdos_attrs: self.msdos_attrs,
+            last_modified,
+            os_type: self.os_type,
+            file_crc: self.file_crc,
+            extended_area: extended_area.into_boxed_slice(),
+            first_header_len: first_header_len as u32,
+            extra_headers: extra_headers.into_boxed_slice()
+        })
+    }
+}
+
+impl LhaHeader {
+    /// Serialize the header to bytes in the format of its header level.
+    ///
+    /// The header length fields, the header checksum (levels 0 and 1) and the CRC-16 of the
+    /// ["Common"][EXT_HEADER_COMMON] extra header, if present, are calculated.
+    ///
+    /// Use [`LhaHeaderBuilder`] to assemble the header's extra headers properly.
+    ///
+    /// # Errors
+    /// Return an error if the header properties can't be stored in its header level.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, HeaderEncodeError> {
+        let level = self.level;
+        if level > 3 {
+            return Err(HeaderEncodeError("unknown header level"))
+        }
+        if level == 0 && !self.extra_headers.is_empty() {
+            return Err(HeaderEncodeError("level 0 header can't have extra headers"))
+        }
+        let mut compressed_size = self.compressed_size;
+        if level == 1 {
+            compressed_size += self.extra_headers.len() as u64;
+        }
+        if compressed_size > u32::MAX as u64 || self.original_size > u32::MAX as u64 {
+            let has_size_header = self.iter_extra().any(|header| header[0] == EXT_HEADER_MSDOS_SIZE);
+            if level < 2 || !has_size_header {
+                return Err(HeaderEncodeError("file size too large for header level"))
+            }
+        }
+        let mut buf = Vec::with_capacity(64 + self.filename.len() + self.extended_area.len() +
+                                         self.extra_headers.len());
+        // header length and checksum
+        buf.extend_from_slice(&[0, 0]);
+        buf.extend_from_slice(&self.compression);
+        buf.extend_from_slice(&(compressed_size as u32).to_le_bytes());
+        buf.extend_from_slice(&(self.original_size as u32).to_le_bytes());
+        buf.extend_from_slice(&self.last_modified.to_le_bytes());
+        buf.push(self.msdos_attrs.bits() as u8);
+        buf.push(level);
+        if level < 2 {
+            let filename_len = u8::try_from(self.filename.len())
+                .map_err(|_| HeaderEncodeError("file name too long"))?;
+            buf.push(filename_len);
+            buf.extend_from_slice(&self.filename);
+        }
+        buf.extend_from_slice(&self.file_crc.to_le_bytes());
+        match level {
+            0 => if self.os_type != 0 || !self.extended_area.is_empty() {
+                buf.push(self.os_type);
+                buf.extend_from_slice(&self.extended_area);
+            }
+            1 => {
+                buf.push(self.os_type);
+                buf.extend_from_slice(&self.extended_area);
+                let first_header_len = u16::try_from(self.first_header_len)
+                    .map_err(|_| HeaderEncodeError("extra header too large"))?;
+                buf.extend_from_slice(&first_header_len.to_le_bytes());
+            }
+            2 => {
+                buf.push(self.os_type);
+                let first_header_len = u16::try_from(self.first_header_len)
+                    .map_err(|_| HeaderEncodeError("extra header too large"))?;
+                buf.extend_from_slice(&first_header_len.to_le_bytes());
+            }
+            _ => {
+                buf.push(self.os_type);
+                // the total header length is updated below
+                buf.extend_from_slice(&[0;4]);
+                buf.extend_from_slice(&self.first_header_len.to_le_bytes());
+            }
+        }
+        if level < 2 {
+            let header_len = u8::try_from(buf.len() - 2)
+                .map_err(|_| HeaderEncodeError("header too large"))?;
+            let csum = buf[2..].iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+            buf[0] = header_len;
+            buf[1] = csum;
+        }
+        let extra_start = buf.len();
+        buf.extend_from_slice(&self.extra_headers);
+        match level {
+            2 => {
+                if buf.len() & 0xff == 0 {
+                    // the first byte of the header can't be 0, add a padding byte
+                    buf.push(0);
+                }
+                let header_len = u16::try_from(buf.len())
+                    .map_err(|_| HeaderEncodeError("header too large"))?;
+                buf[0..2].copy_from_slice(&header_len.to_le_bytes());
+            }
+            3 => {
+                let header_len = u32::try_from(buf.len())
+                    .map_err(|_| HeaderEncodeError("header too large"))?;
+                buf[0..2].copy_from_slice(&4u16.to_le_bytes());
+                buf[24..28].copy_from_slice(&header_len.to_le_bytes());
+            }
+            _ => {}
+        }
+        // find the "Common" header and update its CRC-16
+        let len_size = if level == 3 { 4 } else { 2 };
+        let mut offset = extra_start;
+        let mut header_len = self.first_header_len as usize;
+        let mut crc_offset = None;
+        while header_len != 0 {
+            let header = buf.get(offset..offset + header_len)
+                            .filter(|header| header.len() > len_size)
+                            .ok_or(HeaderEncodeError("wrong extra header size"))?;
+            if header[0] == EXT_HEADER_COMMON && header_len >= len_size + 3 {
+                crc_offset = Some(offset + 1);
+            }
+            let next = &header[header_len - len_size..];
+            offset += header_len;
+            header_len = if level == 3 {
+                u32::from_le_bytes([next[0], next[1], next[2], next[3]]) as usize
+            }
+            else {
+                u16::from_le_bytes([next[0], next[1]]) as usize
+            };
+        }
+        if offset != extra_start + self.extra_headers.len() {
+            return Err(HeaderEncodeError("wrong extra header size"))
+        }
+        if let Some(crc_offset) = crc_offset {
+            buf[crc_offset..crc_offset + 2].copy_from_slice(&[0, 0]);
+            let mut crc = Crc16::default();
+            crc.digest(&buf);
+            buf[crc_offset..crc_offset + 2].copy_from_slice(&crc.sum16().to_le_bytes());
+        }
+        Ok(buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for HeaderEncodeError {}
+
+impl fmt::Display for HeaderEncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "while encoding LHA header: {}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<HeaderEncodeError> for io::Error {
+    fn from(e: HeaderEncodeError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
@@ -6,6 +6,7 @@ use std::io;
 
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompressionMethod {
     /// Special "meta" method marking a directory. Also used for symlinks.
     Lhd,
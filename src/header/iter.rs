@@ -0,0 +1,170 @@
+//! Header-only walking through archives.
+#[cfg(feature = "std")]
+use std::io;
+use crate::error::{LhaError, LhaResult};
+use crate::limits::Limits;
+use crate::stub_io::Read;
+use super::LhaHeader;
+
+/// An iterator through the headers of an archive, that never instantiates decoders.
+///
+/// The compressed content of each file is being skipped by the header's
+/// [compressed size][LhaHeader::compressed_size] before reading the next header. The stream is
+/// either read and the content discarded to a stack-allocated buffer or, when the iterator was
+/// created with [`HeaderIter::new_seekable`], the stream position is moved with [`io::Seek`].
+///
+/// This is the preferred way to list archives' content. Unlike [`LhaDecodeReader`][crate::LhaDecodeReader]
+/// no decoder with its sliding window history is being created for each entry.
+///
+/// The iterator ends after yielding an error.
+#[cfg_attr(feature = "std", doc = r##"
+```no_run
+# fn main() -> std::io::Result<()> {
+use std::{fs::File, io::BufReader};
+use delharc::HeaderIter;
+
+let file = BufReader::new(File::open("archive.lzh")?);
+for header in HeaderIter::new_seekable(file) {
+    let header = header?;
+    println!("{} {}", header.parse_pathname().display(), header.original_size);
+}
+# Ok(())
+# }
+```
+"##)]
+pub struct HeaderIter<R: Read> {
+    rd: R,
+    limits: Limits,
+    skip: fn(&mut R, u64) -> Result<(), R::Error>,
+    remaining: u64,
+    entries: u64,
+    done: bool
+}
+
+impl<R: Read> HeaderIter<R> {
+    /// Return a new instance of `HeaderIter<R>` skipping the compressed content by reading it.
+    ///
+    /// Provide a stream reader as `rd`.
+    pub fn new(rd: R) -> Self {
+        Self::with_limits(rd, Limits::UNLIMITED)
+    }
+    /// Return a new instance of `HeaderIter<R>` skipping the compressed content by reading it,
+    /// enforcing the header related `limits` and the maximum number of entries.
+    pub fn with_limits(rd: R, limits: Limits) -> Self {
+        Self::with_skip(rd, limits, discard_skip)
+    }
+
+    fn with_skip(rd: R, limits: Limits, skip: fn(&mut R, u64) -> Result<(), R::Error>) -> Self {
+        HeaderIter { rd, limits, skip, remaining: 0, entries: 0, done: false }
+    }
+    /// Return the limits in effect.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+    /// Return a reference to the inner stream reader.
+    pub fn get_ref(&self) -> &R {
+        &self.rd
+    }
+    /// Return a mutable reference to the inner stream reader.
+    ///
+    /// The reader is positioned at the compressed content of the last read header, which is going
+    /// to be skipped by its compressed size before reading the next header.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.rd
+    }
+    /// Unwrap the inner stream reader.
+    pub fn into_inner(self) -> R {
+        self.rd
+    }
+    /// Attempt to skip the compressed content of the last read header and to read the next header.
+    /// Return `Ok(None)` if there are no more headers.
+    ///
+    /// # Errors
+    /// Returns an error if the content could not be skipped, if the header could not be read or parsed
+    /// or if any of the limits is exceeded.
+    pub fn next_header(&mut self) -> LhaResult<Option<LhaHeader>, R> {
+        if self.remaining != 0 {
+            (self.skip)(&mut self.rd, self.remaining).map_err(LhaError::Io)?;
+            self.remaining = 0;
+        }
+        let header = match LhaHeader::read_with_limits(&mut self.rd, &self.limits)? {
+            Some(header) => header,
+            None => return Ok(None)
+        };
+        if self.entries >= self.limits.max_entries {
+            return Err(LhaError::Limit("number of entries"))
+        }
+        self.entries += 1;
+        self.remaining = header.compressed_size;
+        Ok(Some(header))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read + io::Seek> HeaderIter<R> {
+    /// Return a new instance of `HeaderIter<R>` skipping the compressed content by seeking
+    /// the stream forward.
+    ///
+    /// Provide a seekable stream reader as `rd`.
+    pub fn new_seekable(rd: R) -> Self {
+        Self::seekable_with_limits(rd, Limits::UNLIMITED)
+    }
+    /// Return a new instance of `HeaderIter<R>` skipping the compressed content by seeking
+    /// the stream forward, enforcing the header related `limits` and the maximum number of entries.
+    pub fn seekable_with_limits(rd: R, limits: Limits) -> Self {
+        Self::with_skip(rd, limits, seek_skip)
+    }
+}
+
+impl<R: Read> Iterator for HeaderIter<R> {
+    type Item = LhaResult<LhaHeader, R>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None
+        }
+        let res = self.next_header().transpose();
+        if !matches!(res, Some(Ok(..))) {
+            self.done = true;
+        }
+        res
+    }
+}
+
+impl<R: Read> core::iter::FusedIterator for HeaderIter<R> {}
+
+impl<R: Read + core::fmt::Debug> core::fmt::Debug for HeaderIter<R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("HeaderIter")
+         .field("rd", &self.rd)
+         .field("limits", &self.limits)
+         .field("remaining", &self.remaining)
+         .field("entries", &self.entries)
+         .field("done", &self.done)
+         .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+const SINK_SIZE: usize = 8*1024;
+#[cfg(not(feature = "std"))]
+const SINK_SIZE: usize = 512;
+
+fn discard_skip<R: Read>(rd: &mut R, mut len: u64) -> Result<(), R::Error> {
+    let mut buf = [0u8; SINK_SIZE];
+    while len != 0 {
+        let chunk = len.min(SINK_SIZE as u64) as usize;
+        match rd.read_all(&mut buf[..chunk])? {
+            0 => break,
+            n => len -= n as u64
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+fn seek_skip<R: io::Seek>(rd: &mut R, len: u64) -> io::Result<()> {
+    let offset = i64::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput,
+                                                                "compressed size is too large"))?;
+    rd.seek(io::SeekFrom::Current(offset)).map(|_| ())
+}
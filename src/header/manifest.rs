@@ -0,0 +1,75 @@
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+use super::{LhaHeader, CompressionMethod, OsType, MsDosAttrs, TimestampResult, method_to_string};
+
+/// A high-level view of the [`LhaHeader`] with the parsed path name and typed metadata,
+/// see [`LhaHeader::manifest`].
+///
+/// With `serde` feature enabled, the manifest can be serialized and deserialized, e.g. for storing
+/// archive manifests in a database. Use [`LhaHeader`] itself if the byte-exact header data is needed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeaderManifest {
+    /// The path name with `/` as a directory separator, see [`LhaHeader::parse_link`].
+    ///
+    /// For symbolic links this is the path of the link itself.
+    pub path: String,
+    /// The target of the symbolic link.
+    pub link_target: Option<String>,
+    /// The file comment, see [`LhaHeader::parse_comment`].
+    pub comment: Option<String>,
+    /// The compression method identifier, e.g. `-lh5-`, with non-printable characters replaced by `?`.
+    pub method: String,
+    /// The compression method or `None` if not recognized.
+    pub compression: Option<CompressionMethod>,
+    /// Compressed file size.
+    pub compressed_size: u64,
+    /// Original file size.
+    pub original_size: u64,
+    /// Uncompressed file's CRC-16.
+    pub file_crc: u16,
+    /// Header level.
+    pub level: u8,
+    /// The OS type or `None` if not recognized.
+    pub os_type: Option<OsType>,
+    /// MS-DOS attributes.
+    pub msdos_attrs: MsDosAttrs,
+    /// Last modified date and time, see [`LhaHeader::parse_last_modified`].
+    pub last_modified: TimestampResult,
+    /// Unix file mode, see [`LhaHeader::parse_unix_mode`].
+    pub unix_mode: Option<u16>,
+    /// Unix user and group ids, see [`LhaHeader::parse_unix_uid_gid`].
+    pub unix_uid_gid: Option<(u16, u16)>,
+    /// Whether the entry is a directory or a symbolic link.
+    pub is_directory: bool,
+}
+
+impl LhaHeader {
+    /// Return the high-level view of this header.
+    pub fn manifest(&self) -> HeaderManifest {
+        HeaderManifest::from(self)
+    }
+}
+
+impl From<&LhaHeader> for HeaderManifest {
+    fn from(header: &LhaHeader) -> Self {
+        let (path, link_target) = header.parse_link();
+        HeaderManifest {
+            path,
+            link_target,
+            comment: header.parse_comment().map(String::from),
+            method: method_to_string(&header.compression),
+            compression: header.compression_method().ok(),
+            compressed_size: header.compressed_size,
+            original_size: header.original_size,
+            file_crc: header.file_crc,
+            level: header.level,
+            os_type: header.parse_os_type().ok(),
+            msdos_attrs: header.msdos_attrs,
+            last_modified: header.parse_last_modified(),
+            unix_mode: header.parse_unix_mode(),
+            unix_uid_gid: header.parse_unix_uid_gid(),
+            is_directory: header.is_directory()
+        }
+    }
+}
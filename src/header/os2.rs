@@ -0,0 +1,148 @@
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{boxed::Box, borrow::Cow, vec::Vec};
+use chrono::prelude::*;
+use super::{MsDosAttrs, parser::read_u16};
+#[cfg(feature = "alloc")]
+use super::parser::parse_str_nilterm;
+
+/// The extended attribute flag: the attribute is critical and must be understood by an application.
+pub const FEA_NEEDEA: u8 = 0x80;
+
+/// The content of the ["extended attributes"][super::ext::EXT_HEADER_EXT_ATTRS] extra header,
+/// see [`LhaHeader::parse_ext_attrs`][crate::LhaHeader::parse_ext_attrs].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExtAttrs {
+    /// MS-DOS (OS/2) file attributes.
+    pub attrs: MsDosAttrs,
+    /// Unix permission mode.
+    pub mode: u16,
+    /// The owner's group id.
+    pub group: u16,
+    /// The owner's user id.
+    pub owner: u16,
+    /// Creation time as a Unix timestamp, 0 if not set.
+    pub created: u32,
+    /// Last access time as a Unix timestamp, 0 if not set.
+    pub accessed: u32,
+}
+
+#[cfg(feature = "alloc")]
+/// A single OS/2 extended attribute, see [`LhaHeader::parse_os2_eas`][crate::LhaHeader::parse_os2_eas].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Os2Ea {
+    /// Extended attribute flags, e.g. [`FEA_NEEDEA`].
+    pub flags: u8,
+    /// Raw name of the attribute, e.g. `.TYPE` or `.LONGNAME`.
+    pub name: Box<[u8]>,
+    /// Raw value of the attribute, usually starting with a 16-bit value type.
+    pub value: Box<[u8]>,
+}
+
+impl ExtAttrs {
+    /// Parse the extra header data, excluding the header id.
+    ///
+    /// Return `None` if the data is too short.
+    pub fn parse(data: &[u8]) -> Option<ExtAttrs> {
+        if data.len() < 16 {
+            return None
+        }
+        let u32_at = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        Some(ExtAttrs {
+            attrs: MsDosAttrs::from_bits_retain(read_u16(&data[0..2])?),
+            mode: read_u16(&data[2..4])?,
+            group: read_u16(&data[4..6])?,
+            owner: read_u16(&data[6..8])?,
+            created: u32_at(8),
+            accessed: u32_at(12)
+        })
+    }
+    /// Return the creation time, if set.
+    pub fn parse_created(&self) -> Option<DateTime<Utc>> {
+        parse_unix_timestamp(self.created)
+    }
+    /// Return the last access time, if set.
+    pub fn parse_accessed(&self) -> Option<DateTime<Utc>> {
+        parse_unix_timestamp(self.accessed)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Os2Ea {
+    /// Parse an OS/2 `FEA2LIST` structure into a list of extended attributes.
+    ///
+    /// Return `None` if the data is malformed.
+    pub fn parse_fea2list(data: &[u8]) -> Option<Vec<Os2Ea>> {
+        let list_len = data.get(0..4)
+                           .map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]) as usize)?;
+        let mut data = data.get(4..list_len)?;
+        let mut eas = Vec::new();
+        while !data.is_empty() {
+            // next entry offset, flags, name length, value length, nil-terminated name, value
+            let (next, flags, name_len, value_len) = match data.get(0..8)? {
+                &[a, b, c, d, flags, name_len, e, f] => (
+                    u32::from_le_bytes([a, b, c, d]) as usize, flags,
+                    name_len as usize, u16::from_le_bytes([e, f]) as usize),
+                _ => unreachable!()
+            };
+            let name = data.get(8..8 + name_len)?;
+            let value = data.get(9 + name_len..9 + name_len + value_len)?;
+            eas.push(Os2Ea { flags, name: name.into(), value: value.into() });
+            if next == 0 {
+                break
+            }
+            if next < 9 + name_len + value_len {
+                return None
+            }
+            data = data.get(next..)?;
+        }
+        Some(eas)
+    }
+    /// Return the attribute name, converting all non-ASCII or control characters to `%xx` sequences.
+    pub fn parse_name(&self) -> Cow<'_, str> {
+        parse_str_nilterm(&self.name, false, false)
+    }
+    /// Return whether the attribute is flagged as critical.
+    pub fn is_critical(&self) -> bool {
+        self.flags & FEA_NEEDEA != 0
+    }
+    /// Return the value type, e.g. `0xFFFD` for `EAT_ASCII`.
+    pub fn value_type(&self) -> Option<u16> {
+        self.value.get(0..2).and_then(read_u16)
+    }
+}
+
+fn parse_unix_timestamp(ts: u32) -> Option<DateTime<Utc>> {
+    if ts == 0 {
+        return None
+    }
+    Utc.timestamp_opt(ts as i64, 0).single()
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn os2_eas_works() {
+        let data = [
+            46, 0, 0, 0,
+            20, 0, 0, 0,  0, 5, 6, 0,  b'.', b'T', b'Y', b'P', b'E', 0,
+            0xfd, 0xff, 2, 0, b'O', b'K',
+            0, 0, 0, 0,  0x80, 9, 4, 0,  b'.', b'L', b'O', b'N', b'G', b'N', b'A', b'M', b'E', 0,
+            0xfd, 0xff, 0, 0];
+        let eas = Os2Ea::parse_fea2list(&data).unwrap();
+        assert_eq!(eas.len(), 2);
+        assert_eq!(eas[0].parse_name(), ".TYPE");
+        assert!(!eas[0].is_critical());
+        assert_eq!(eas[0].value_type(), Some(0xfffd));
+        assert_eq!(&eas[0].value[..], &[0xfd, 0xff, 2, 0, b'O', b'K']);
+        assert_eq!(eas[1].parse_name(), ".LONGNAME");
+        assert!(eas[1].is_critical());
+        assert_eq!(&eas[1].value[..], &[0xfd, 0xff, 0, 0]);
+        assert!(Os2Ea::parse_fea2list(&data[..40]).is_none());
+        assert_eq!(Os2Ea::parse_fea2list(&[4, 0, 0, 0]), Some(Vec::new()));
+    }
+}
@@ -0,0 +1,88 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// OS-9 file attributes, displayed by OS-9 tools as `dsewrewr`.
+    ///
+    /// Only the lowest 8 bits have a meaning, the remaining bits are retained as found in the header.
+    #[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct Os9Attrs: u16 {
+        const READ         = 0b00000000_00000001;
+        const WRITE        = 0b00000000_00000010;
+        const EXEC         = 0b00000000_00000100;
+        const PUBLIC_READ  = 0b00000000_00001000;
+        const PUBLIC_WRITE = 0b00000000_00010000;
+        const PUBLIC_EXEC  = 0b00000000_00100000;
+        const SHARED       = 0b00000000_01000000;
+        const DIRECTORY    = 0b00000000_10000000;
+    }
+}
+
+/// OS-9 and OS-9/68k file metadata, see [`LhaHeader::parse_os9_metadata`][crate::LhaHeader::parse_os9_metadata].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Os9Metadata {
+    /// File attributes.
+    pub attrs: Os9Attrs,
+    /// The owner's user id, if stored in the header.
+    pub owner: Option<u16>,
+    /// The owner's group id, if stored in the header.
+    pub group: Option<u16>,
+    /// The raw file descriptor type, only stored by LHA for OS-9.
+    pub fd_type: Option<u16>,
+}
+
+impl Os9Attrs {
+    /// Return Unix permission bits (`0o777` mask) mapped from OS-9 attributes.
+    ///
+    /// Owner attributes map to the Unix owner permissions and public attributes map to both
+    /// the Unix group and other permissions.
+    pub fn to_unix_permissions(self) -> u16 {
+        let mut mode = 0;
+        for (attr, bits) in [(Os9Attrs::READ,         0o400),
+                             (Os9Attrs::WRITE,        0o200),
+                             (Os9Attrs::EXEC,         0o100),
+                             (Os9Attrs::PUBLIC_READ,  0o044),
+                             (Os9Attrs::PUBLIC_WRITE, 0o022),
+                             (Os9Attrs::PUBLIC_EXEC,  0o011)]
+        {
+            if self.contains(attr) {
+                mode |= bits;
+            }
+        }
+        mode
+    }
+}
+
+impl Os9Metadata {
+    /// Return whether the attributes designate a directory.
+    pub fn is_directory(&self) -> bool {
+        self.attrs.contains(Os9Attrs::DIRECTORY)
+    }
+    /// Return a Unix file mode, including the file type bits (`S_IFDIR` or `S_IFREG`),
+    /// mapped from OS-9 attributes.
+    pub fn to_unix_mode(&self) -> u32 {
+        let file_type = if self.is_directory() { 0o040000 } else { 0o100000 };
+        file_type | self.attrs.to_unix_permissions() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn os9_attrs_works() {
+        // ---w--wr
+        let attrs = Os9Attrs::from_bits_retain(0x13);
+        assert_eq!(attrs.to_unix_permissions(), 0o622);
+        // --e-r-wr
+        let attrs = Os9Attrs::from_bits_retain(0x2B);
+        assert_eq!(attrs.to_unix_permissions(), 0o655);
+        // d-ewrewr
+        let meta = Os9Metadata { attrs: Os9Attrs::from_bits_retain(0xffbf), ..Default::default() };
+        assert!(meta.is_directory());
+        assert_eq!(meta.to_unix_mode(), 0o040777);
+        let meta = Os9Metadata::default();
+        assert!(!meta.is_directory());
+        assert_eq!(meta.to_unix_mode(), 0o100000);
+    }
+}
@@ -11,6 +11,7 @@ pub struct UnrecognizedOsType(pub u8);
 #[non_exhaustive]
 #[repr(u8)]
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OsType {
     #[default]
     Generic =            0x00,
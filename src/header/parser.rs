@@ -1,11 +1,14 @@
 use core::num::Wrapping;
-use core::slice;
-use core::fmt::Write;
-#[cfg(not(feature = "std"))]
+use core::mem::size_of;
+use core::ops::Range;
+#[cfg(feature = "alloc")]
+use core::{fmt::Write, slice};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 use alloc::vec::Vec;
 use crate::error::{LhaError, LhaResult};
 use crate::stub_io::Read;
 use crate::crc::Crc16;
+use crate::limits::Limits;
 use super::*;
 
 /// Raw identifiers of extra headers.
@@ -26,6 +29,7 @@ pub mod ext {
     pub const EXT_HEADER_UNIX_OWNER:  u8 = 0x53;
     pub const EXT_HEADER_UNIX_TIME:   u8 = 0x54;
     pub const EXT_HEADER_OS9:         u8 = 0xCC;
+    pub const EXT_HEADER_OS2_EAS:     u8 = 0x7E;
     pub const EXT_HEADER_EXT_ATTRS:   u8 = 0x7F;
 }
 
@@ -43,10 +47,13 @@ impl<'a> Iterator for ExtraHeaderIter<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let header_length = self.header_length as usize;
-        if header_length == 0 {
+        let counter_size = if self.header_len32 { 4 } else { 2 };
+        // the parser validates the chain, but the header fields might have been modified since,
+        // so the iteration ends on the inconsistent data instead of panicking
+        if header_length <= counter_size || header_length > self.data.len() {
+            self.header_length = 0;
             return None
         }
-        let counter_size = if self.header_len32 { 4 } else { 2 };
         let (res, data) = self.data.split_at(header_length);
         let (res, len) = res.split_at(header_length - counter_size);
         let len = if self.header_len32 {
@@ -73,149 +80,247 @@ struct LhaRawBaseHeader {
     lha_level: u8
 }
 
-struct Parser<'a, R> {
+/// A source of the raw header data.
+trait HeaderSource {
+    type Error;
+    /// Read the first byte of the header or return `None` at the end of input.
+    fn read_first(&mut self) -> Result<Option<u8>, LhaError<Self::Error>>;
+    /// Read the next `len` bytes of the header and return them.
+    fn read(&mut self, len: usize) -> Result<&[u8], LhaError<Self::Error>>;
+    /// Hint that the header is at least `total_len` bytes long, so it can be read ahead.
+    fn prefetch(&mut self, _total_len: usize) -> Result<(), LhaError<Self::Error>> {
+        Ok(())
+    }
+    /// Return all header data read so far.
+    fn data(&self) -> &[u8];
+}
+
+/// Reads the header data from a stream into a buffer.
+///
+/// The header data is read ahead only up to the header length hinted by the parser, so no bytes
+/// past the header are being consumed from the stream. The buffer is the storage of the parsed
+/// header, not a cache kept between headers, see [`ReadAhead`][crate::stub_io::ReadAhead].
+#[cfg(feature = "alloc")]
+struct StreamSource<'a, R> {
     rd: &'a mut R,
-    crc: Crc16,
-    csum: Wrapping<u8>,
+    buf: Vec<u8>,
     len: usize
 }
 
-impl<R: Read> Parser<'_, R> {
-    // NOTE: does not update wrapping sum
-    fn read_u8_or_none(&mut self) -> LhaResult<Option<u8>, R> {
+/// Parses the header data in place.
+struct SliceSource<'a> {
+    data: &'a [u8],
+    len: usize
+}
+
+/// Header fields with the raw data parts given as ranges of the header data.
+struct ParsedHeader {
+    level: u8,
+    compression: [u8;5],
+    compressed_size: u64,
+    original_size: u64,
+    filename: Range<usize>,
+    msdos_attrs: MsDosAttrs,
+    last_modified: u32,
+    os_type: u8,
+    file_crc: u16,
+    extended_area: Range<usize>,
+    first_header_len: u32,
+    extra_headers: Range<usize>
+}
+
+struct Parser<'a, S> {
+    src: S,
+    limits: &'a Limits,
+    crc: Crc16,
+    csum: Wrapping<u8>
+}
+
+#[cfg(feature = "alloc")]
+impl<R: Read> HeaderSource for StreamSource<'_, R> {
+    type Error = R::Error;
+
+    fn read_first(&mut self) -> Result<Option<u8>, LhaError<R::Error>> {
         let mut byte = 0u8;
         if 0 == self.rd.read_all(slice::from_mut(&mut byte)).map_err(LhaError::Io)? {
             return Ok(None)
         }
-        // self.rd.by_ref().bytes().next().transpose().map(|mb|
-        //     mb.map(|byte| {
-                self.update_checksums_no_wrapping_sum(slice::from_ref(&byte));
-                // byte
-        //     })
-        // )
+        self.buf.push(byte);
+        self.len = 1;
         Ok(Some(byte))
     }
 
-    fn read_u8(&mut self) -> LhaResult<u8, R> {
-        let mut byte: u8 = 0;
-        self.read_exact(slice::from_mut(&mut byte))?;
-        Ok(byte)
+    fn read(&mut self, len: usize) -> Result<&[u8], LhaError<R::Error>> {
+        let start = self.len;
+        let end = start.saturating_add(len);
+        if end > self.buf.len() {
+            self.read_ahead(end - self.buf.len())?;
+            if end > self.buf.len() {
+                return Err(LhaError::Io(R::unexpected_eof()))
+            }
+        }
+        self.len = end;
+        Ok(&self.buf[start..end])
     }
 
-    fn read_u16(&mut self) -> LhaResult<u16, R> {
-        let mut buf = [0u8;2];
-        self.read_exact(&mut buf)?;
-        Ok(u16::from_le_bytes(buf))
+    fn prefetch(&mut self, total_len: usize) -> Result<(), LhaError<R::Error>> {
+        if total_len > self.buf.len() {
+            self.read_ahead(total_len - self.buf.len())?;
+        }
+        Ok(())
     }
 
-    fn read_u32(&mut self) -> LhaResult<u32, R> {
-        let mut buf = [0u8;4];
-        self.read_exact(&mut buf)?;
-        Ok(u32::from_le_bytes(buf))
+    fn data(&self) -> &[u8] {
+        &self.buf[..self.len]
     }
+}
 
-    fn read_exact(&mut self, buf: &mut [u8]) -> LhaResult<(), R> {
-        self.rd.read_exact(buf).map_err(LhaError::Io)?;
-        self.update_checksums(buf);
+#[cfg(feature = "alloc")]
+impl<R: Read> StreamSource<'_, R> {
+    // reads up to `len` bytes into the buffer, less only at the end of stream
+    fn read_ahead(&mut self, len: usize) -> Result<(), LhaError<R::Error>> {
+        let buf = &mut self.buf;
+        let start = buf.len();
+        buf.try_reserve(len).map_err(|_| LhaError::HeaderParse("memory allocation failed"))?;
+        // TODO: use BorrowedBuf once stabilized
+        let spare = unsafe {
+            core::mem::transmute::<&mut [core::mem::MaybeUninit<u8>], &mut [u8]>(&mut buf.spare_capacity_mut()[..len])
+        };
+        let n = self.rd.read_all(spare).map_err(LhaError::Io)?;
+        unsafe { buf.set_len(start + n); }
         Ok(())
     }
+}
+
+impl<'a> HeaderSource for SliceSource<'a> {
+    type Error = <&'a [u8] as Read>::Error;
 
-    fn read_limit(&mut self, limit: usize) -> LhaResult<Box<[u8]>, R> {
-        let mut buf = Vec::with_capacity(limit);
-        self.read_limit_no_checksums(limit, &mut buf)?;
-        self.update_checksums(&buf);
-        Ok(buf.into_boxed_slice())
+    fn read_first(&mut self) -> Result<Option<u8>, LhaError<Self::Error>> {
+        let byte = self.data.first().copied();
+        self.len = byte.is_some().into();
+        Ok(byte)
     }
 
-    fn update_checksums(&mut self, buf: &[u8]) {
-        self.update_checksums_no_wrapping_sum(buf);
-        self.csum = wrapping_csum(self.csum, buf);
+    fn read(&mut self, len: usize) -> Result<&[u8], LhaError<Self::Error>> {
+        let start = self.len;
+        match self.data.get(start..start.saturating_add(len)) {
+            Some(data) => {
+                self.len += len;
+                Ok(data)
+            }
+            None => Err(LhaError::Io(<&[u8] as Read>::unexpected_eof()))
+        }
     }
 
-    fn update_checksums_no_wrapping_sum(&mut self, buf: &[u8]) {
-        self.len += buf.len();
+    fn data(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+impl<S: HeaderSource> Parser<'_, S> {
+    fn new(src: S, limits: &Limits) -> Parser<'_, S> {
+        Parser { src, limits, crc: Crc16::default(), csum: Wrapping(0) }
+    }
+
+    fn len(&self) -> usize {
+        self.src.data().len()
+    }
+
+    // NOTE: does not update wrapping sum
+    fn read_u8_or_none(&mut self) -> Result<Option<u8>, LhaError<S::Error>> {
+        let byte = self.src.read_first()?;
+        if let Some(byte) = byte {
+            self.crc.digest(&[byte]);
+        }
+        Ok(byte)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&[u8], LhaError<S::Error>> {
+        let buf = self.src.read(len)?;
         self.crc.digest(buf);
+        self.csum = wrapping_csum(self.csum, buf);
+        Ok(buf)
     }
 
-    fn read_limit_no_checksums(&mut self, limit: usize, buf: &mut Vec<u8>) -> LhaResult<(), R> {
-        buf.try_reserve_exact(limit).map_err(|_| LhaError::HeaderParse("memory allocation failed"))?;
-        // TODO: use BorrowedBuf once stabilized
-        let spare = unsafe { core::mem::transmute::<_, &mut [u8]>(&mut buf.spare_capacity_mut()[..limit]) };
-        self.rd.read_exact(spare).map_err(LhaError::Io)?;
-        unsafe { buf.set_len(buf.len() + limit); }
-        // if self.rd.by_ref().take(limit as u64).read_to_end(buf)? != limit {
-        //     return Err(LhaError::HeaderParse("file is too short"))
-        // }
+    fn read_u8(&mut self) -> Result<u8, LhaError<S::Error>> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, LhaError<S::Error>> {
+        Ok(read_u16(self.read_bytes(2)?).unwrap())
+    }
+
+    fn read_u32(&mut self) -> Result<u32, LhaError<S::Error>> {
+        Ok(read_u32(self.read_bytes(4)?).unwrap())
+    }
+
+    // the read ahead is limited by the maximum header size
+    fn prefetch(&mut self, total_len: usize) -> Result<(), LhaError<S::Error>> {
+        self.src.prefetch(total_len.min(self.limits.max_header_size))
+    }
+
+    fn check_header_size(&self, len: usize) -> Result<(), LhaError<S::Error>> {
+        if self.len().saturating_add(len) > self.limits.max_header_size {
+            return Err(LhaError::Limit("header size"))
+        }
         Ok(())
     }
-}
 
-impl LhaHeader {
-    /// Attempt to parse the LHA header. Return `Ok(Some(LhaHeader))` on success. Return `Ok(None)`
-    /// if the end of archive marker (a `0` byte) was encountered.
-    ///
-    /// The method validates all length and checksum fields of the header, but does not parse extra
-    /// headers except:
-    /// * The ["Common"][EXT_HEADER_COMMON] header for validating the header's CRC-16 checksum.
-    /// * The ["MS-DOS Attributes"][EXT_HEADER_MSDOS_ATTRS] header for reading MS-DOS attributes.
-    /// * The ["MS-DOS Size"][EXT_HEADER_MSDOS_SIZE] header for reading 64-bit file size.
-    ///
-    /// All extra data is available as raw bytes and extra headers can be iterated with [LhaHeader::iter_extra].
-    ///
-    /// Instance methods can be further called on the parsed `LhaHeader` struct to attempt to parse the
-    /// name and path of the file or other file's meta-data.
-    ///
-    /// # Errors
-    /// Returns an error from the underlying reading operations or because a malformed header was encountered.
-    pub fn read<R: Read>(rd: &mut R) -> LhaResult<Option<LhaHeader>, R> {
-        let mut parser = Parser {
-            rd, 
-            crc: Crc16::default(),
-            csum: Wrapping(0),
-            len: 0
-        };
-        let header_len = match parser.read_u8_or_none()? {
+    fn read_range(&mut self, len: usize) -> Result<Range<usize>, LhaError<S::Error>> {
+        self.check_header_size(len)?;
+        let start = self.len();
+        self.read_bytes(len)?;
+        Ok(start..start + len)
+    }
+
+    fn parse(&mut self) -> Result<Option<ParsedHeader>, LhaError<S::Error>> {
+        let header_len = match self.read_u8_or_none()? {
             Some(0)|None => return Ok(None),
             Some(len) => len
         };
-        let csum = parser.read_u8()?;
+        self.prefetch(2 + size_of::<LhaRawBaseHeader>())?;
+        let csum = self.read_u8()?;
         // reset wrapping checksum which should not include the first 2 bytes
-        parser.csum = Wrapping(0);
+        self.csum = Wrapping(0);
 
         let mut raw_header = LhaRawBaseHeader::default();
-        parser.read_exact(unsafe {
+        unsafe {
             // safe because LhaRawBaseHeader is packed and contains only byte type members
             struct_slice_mut(&mut raw_header)
-        })?;
-        if raw_header.lha_level > 3 {
-            return Err(LhaError::HeaderParse("unknown header level"))
+        }.copy_from_slice(self.read_bytes(size_of::<LhaRawBaseHeader>())?);
+        // read ahead the header data as far as it is known to extend
+        match raw_header.lha_level {
+            0|1 => self.prefetch(header_len as usize + 2)?,
+            2 => self.prefetch(u16::from_le_bytes([header_len, csum]) as usize)?,
+            3 => self.prefetch(self.len() + 11)?,
+            _ => return Err(LhaError::HeaderParse("unknown header level"))
         }
 
         // read filename if level 0 or 1
         let filename = if raw_header.lha_level < 2 {
-            let filename_len = parser.read_u8()? as usize;
-            if (header_len as usize) < parser.len + filename_len {
+            let filename_len = self.read_u8()? as usize;
+            if (header_len as usize) < self.len() + filename_len {
                 return Err(LhaError::HeaderParse("wrong header size"))
             }
-            parser.read_limit(filename_len)?
+            self.read_range(filename_len)?
         }
         else {
-            Box::new([])
+            0..0
         };
 
         // file CRC-16
-        let file_crc = parser.read_u16()?;
+        let file_crc = self.read_u16()?;
 
         // OS-TYPE
         let mut os_type = 0;
         if raw_header.lha_level > 0 {
-            os_type = parser.read_u8()?;
+            os_type = self.read_u8()?;
         }
 
         // extended area, only 0 and 1 level
-        let mut extended_area: Box<[u8]> = Box::new([]);
+        let mut extended_area = 0..0;
         if raw_header.lha_level < 2 {
-            let mut min_len = parser.len;
+            let mut min_len = self.len();
             if raw_header.lha_level == 0 {
                 min_len -= 2; // no extra headers
             }
@@ -226,29 +331,28 @@ impl LhaHeader {
             if extended_len != 0 && raw_header.lha_level == 0  {
                 // get os_type from level 0 extended area
                 extended_len -= 1;
-                os_type = parser.read_u8()?;
+                os_type = self.read_u8()?;
             }
             if extended_len != 0 {
-                extended_area = parser.read_limit(extended_len)?;
+                extended_area = self.read_range(extended_len)?;
             }
         };
 
         // extra headers
         let mut long_header_len: u32 = 0; // a long header length found in level >= 2
         let mut first_header_len: u32 = 0;
-        let mut extra_headers = Vec::new();
         // establish the first extra header length and the long header length
         match raw_header.lha_level {
             1 => {
-                first_header_len = parser.read_u16()? as u32;
+                first_header_len = self.read_u16()? as u32;
             }
             2 => {
                 long_header_len = u16::from_le_bytes([header_len, csum]) as u32;
-                first_header_len = parser.read_u16()? as u32;
+                first_header_len = self.read_u16()? as u32;
             }
             3 => {
-                long_header_len = parser.read_u32()?;
-                first_header_len = parser.read_u32()?;
+                long_header_len = self.read_u32()?;
+                first_header_len = self.read_u32()?;
                 if header_len != 4 || csum != 0 {
                     return Err(LhaError::HeaderParse("invalid header"))
                 }
@@ -258,50 +362,61 @@ impl LhaHeader {
 
         // validate level 0 and 1 header checksum
         if raw_header.lha_level < 2 {
-            if csum != parser.csum.0 {
+            if csum != self.csum.0 {
                 return Err(LhaError::HeaderParse("invalid header level checksum"))
             }
         }
-        else if long_header_len < parser.len as u32 + first_header_len {
+        else if long_header_len < self.len() as u32 + first_header_len {
             return Err(LhaError::HeaderParse("wrong header size"))
         }
+        else if long_header_len as usize > self.limits.max_header_size {
+            return Err(LhaError::Limit("header size"))
+        }
+        else {
+            self.prefetch(long_header_len as usize)?;
+        }
 
         let mut msdos_attrs = MsDosAttrs::from_bits_retain(raw_header.msdos_attrs as u16);
         let mut original_size = u32::from_le_bytes(raw_header.original_size) as u64;
         let mut compressed_size = u32::from_le_bytes(raw_header.compressed_size) as u64;
         let mut header_crc: Option<u16> = None;
         // read extra headers
-        let min_header_len = if raw_header.lha_level == 3 { 5 } else { 3 };
+        let counter_size = if raw_header.lha_level == 3 { 4 } else { 2 };
+        let extra_start = self.len();
         let mut extra_header_len = first_header_len as usize;
+        let mut extra_header_count: usize = 0;
         while extra_header_len != 0 {
-            if extra_header_len < min_header_len {
+            if extra_header_len < 1 + counter_size {
                 return Err(LhaError::HeaderParse("wrong extra header size"))
             }
+            extra_header_count += 1;
+            if extra_header_count > self.limits.max_extra_headers {
+                return Err(LhaError::Limit("number of extra headers"))
+            }
             // check long header length (level 2, 3)
             if long_header_len != 0 {
-                if (long_header_len as usize) < parser.len + extra_header_len - 2 {
+                if (long_header_len as usize) < self.len() + extra_header_len - 2 {
                     return Err(LhaError::HeaderParse("wrong header size"))
                 }
             }
-            else if compressed_size < (extra_headers.len() + extra_header_len) as u64 {
+            else if compressed_size < (self.len() - extra_start + extra_header_len) as u64 {
                 // otherwise check skip size (level 1)
                 return Err(LhaError::HeaderParse("wrong header size"))
             }
-            parser.read_limit_no_checksums(extra_header_len, &mut extra_headers)?;
-            let start = extra_headers.len() - extra_header_len;
-            let header = &mut extra_headers[start..];
+            self.check_header_size(extra_header_len)?;
+            let header = self.src.read(extra_header_len)?;
             match header {
                 // we need to extract the CRC-16 from header and clear it in order to calculate checksum
                 [EXT_HEADER_COMMON, data @ ..] => {
                     if header_crc.is_some() {
                         return Err(LhaError::HeaderParse("double common CRC-16 header"))
                     }
-                    if let Some(crc) = data.get_mut(0..2) {
-                        header_crc = read_u16(crc);
-                        for p in crc.iter_mut() {
-                            *p = 0;
-                        }
+                    if data.len() < 2 + counter_size {
+                        return Err(LhaError::HeaderParse("wrong extra header size"))
                     }
+                    header_crc = read_u16(&data[0..2]);
+                    self.crc.digest(&[EXT_HEADER_COMMON, 0, 0]);
+                    self.crc.digest(&data[2..]);
                 }
                 [EXT_HEADER_MSDOS_ATTRS, data @ ..]|
                 [EXT_HEADER_EXT_ATTRS,   data @ ..] if data.len() >= 2 => {
@@ -317,25 +432,29 @@ impl LhaHeader {
                 }
                 _ => {}
             }
-            parser.update_checksums_no_wrapping_sum(header);
+            if header[0] != EXT_HEADER_COMMON {
+                self.crc.digest(header);
+            }
+            let next_len = &header[header.len() - counter_size..];
             extra_header_len = if raw_header.lha_level == 3 {
-                read_u32(&header[header.len() - 4..]).unwrap() as usize
+                read_u32(next_len).unwrap() as usize
             }
             else {
-                read_u16(&header[header.len() - 2..]).unwrap() as usize
+                read_u16(next_len).unwrap() as usize
             }
         }
+        let extra_headers = extra_start..self.len();
 
         // validate long header length
         if long_header_len != 0 &&
-           long_header_len != parser.len as u32
+           long_header_len != self.len() as u32
         {
-            if raw_header.lha_level == 2 && long_header_len == parser.len as u32 + 1
+            if raw_header.lha_level == 2 && long_header_len == self.len() as u32 + 1
             {
                 // read padding byte
-                parser.read_u8()?;
+                self.read_u8()?;
             }
-            else if raw_header.lha_level == 2 && long_header_len + 2 != parser.len as u32 {
+            else if raw_header.lha_level == 2 && long_header_len + 2 != self.len() as u32 {
                 // some packers (Osk) don't include self in the header length
                 return Err(LhaError::HeaderParse("wrong length of headers"))
             }
@@ -343,7 +462,7 @@ impl LhaHeader {
 
         // validate headers CRC
         if let Some(crc) = header_crc {
-            if crc != parser.crc.sum16() {
+            if crc != self.crc.sum16() {
                 return Err(LhaError::HeaderParse("wrong header CRC-16 checksum"))
             }
         }
@@ -356,41 +475,137 @@ impl LhaHeader {
             compressed_size -= extra_headers.len() as u64;
         }
 
-        let compression = raw_header.compression;
-        let last_modified = u32::from_le_bytes(raw_header.last_modified);
-        let extra_headers = extra_headers.into_boxed_slice();
+        self.limits.check_sizes(compressed_size, original_size)?;
 
-        Ok(Some(LhaHeader {
+        Ok(Some(ParsedHeader {
             level: raw_header.lha_level,
-            compression,
+            compression: raw_header.compression,
             compressed_size,
             original_size,
             filename,
-            os_type,
             msdos_attrs,
-            last_modified,
+            last_modified: u32::from_le_bytes(raw_header.last_modified),
+            os_type,
             file_crc,
             extended_area,
             first_header_len,
             extra_headers
         }))
     }
+}
+
+impl ParsedHeader {
+    fn into_header_ref(self, data: &[u8]) -> LhaHeaderRef<'_> {
+        LhaHeaderRef {
+            level: self.level,
+            compression: self.compression,
+            compressed_size: self.compressed_size,
+            original_size: self.original_size,
+            filename: &data[self.filename],
+            msdos_attrs: self.msdos_attrs,
+            last_modified: self.last_modified,
+            os_type: self.os_type,
+            file_crc: self.file_crc,
+            extended_area: &data[self.extended_area],
+            first_header_len: self.first_header_len,
+            extra_headers: &data[self.extra_headers]
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl LhaHeader {
+    /// Attempt to parse the LHA header. Return `Ok(Some(LhaHeader))` on success. Return `Ok(None)`
+    /// if the end of archive marker (a `0` byte) was encountered.
+    ///
+    /// The method validates all length and checksum fields of the header, but does not parse extra
+    /// headers except:
+    /// * The ["Common"][EXT_HEADER_COMMON] header for validating the header's CRC-16 checksum.
+    /// * The ["MS-DOS Attributes"][EXT_HEADER_MSDOS_ATTRS] header for reading MS-DOS attributes.
+    /// * The ["MS-DOS Size"][EXT_HEADER_MSDOS_SIZE] header for reading 64-bit file size.
+    ///
+    /// All extra data is available as raw bytes and extra headers can be iterated with [LhaHeader::iter_extra].
+    ///
+    /// Instance methods can be further called on the parsed `LhaHeader` struct to attempt to parse the
+    /// name and path of the file or other file's meta-data.
+    ///
+    /// # Errors
+    /// Returns an error from the underlying reading operations or because a malformed header was encountered.
+    pub fn read<R: Read>(rd: &mut R) -> LhaResult<Option<LhaHeader>, R> {
+        Self::read_with_limits(rd, &Limits::UNLIMITED)
+    }
+    /// Attempt to parse the LHA header exactly like [LhaHeader::read], but enforce the header related
+    /// `limits`: the maximum header size, the maximum number of extra headers, the maximum original size
+    /// and the maximum compression ratio.
+    ///
+    /// The header size limit is checked before any memory is being allocated for the header data.
+    ///
+    /// # Errors
+    /// Returns [LhaError::Limit] if any of the limits is exceeded. Other errors are returned exactly
+    /// like in [LhaHeader::read].
+    pub fn read_with_limits<R: Read>(rd: &mut R, limits: &Limits) -> LhaResult<Option<LhaHeader>, R> {
+        let mut parser = Parser::new(StreamSource { rd, buf: Vec::new(), len: 0 }, limits);
+        Ok(parser.parse()?.map(|parsed| parsed.into_header_ref(parser.src.data()).to_header()))
+    }
 
     /// Return an iterator that will iterate through extra headers, yielding the headers' raw
     /// data, excluding the next header length field.
     ///
     /// # Note
     /// Each iterated raw header will have at least the size of 1 byte containing the header identifier.
+    ///
+    /// The iteration ends at the first header not fitting in [`LhaHeader::extra_headers`], which may
+    /// happen when the public fields were modified or deserialized inconsistently.
     pub fn iter_extra(&self) -> ExtraHeaderIter<'_> {
+        self.as_header_ref().iter_extra()
+    }
+}
+
+impl<'a> LhaHeaderRef<'a> {
+    /// Attempt to parse the LHA header in place at the beginning of `data`. Return
+    /// `Ok(Some(LhaHeaderRef))` on success. Return `Ok(None)` if the end of archive marker
+    /// (a `0` byte) or the end of `data` was encountered.
+    ///
+    /// On success `data` is advanced past the parsed header, which leaves it at the start of
+    /// the compressed file data. On error `data` is left unchanged.
+    ///
+    /// The header is validated exactly like in [LhaHeader::read].
+    ///
+    /// # Errors
+    /// Returns an error if a malformed or a truncated header was encountered.
+    pub fn read(data: &mut &'a [u8]) -> LhaResult<Option<LhaHeaderRef<'a>>, &'a [u8]> {
+        Self::read_with_limits(data, &Limits::UNLIMITED)
+    }
+    /// Attempt to parse the LHA header in place exactly like [LhaHeaderRef::read], but enforce
+    /// the header related `limits`, see [LhaHeader::read_with_limits].
+    ///
+    /// # Errors
+    /// Returns [LhaError::Limit] if any of the limits is exceeded. Other errors are returned exactly
+    /// like in [LhaHeaderRef::read].
+    pub fn read_with_limits(
+            data: &mut &'a [u8],
+            limits: &Limits
+        ) -> LhaResult<Option<LhaHeaderRef<'a>>, &'a [u8]>
+    {
+        let input: &'a [u8] = data;
+        let mut parser = Parser::new(SliceSource { data: input, len: 0 }, limits);
+        let parsed = parser.parse()?;
+        let (header, rest) = input.split_at(parser.len());
+        *data = rest;
+        Ok(parsed.map(|parsed| parsed.into_header_ref(header)))
+    }
+
+    /// See [`LhaHeader::iter_extra`].
+    pub fn iter_extra(&self) -> ExtraHeaderIter<'a> {
         ExtraHeaderIter {
-            data: &self.extra_headers,
+            data: self.extra_headers,
             header_length: self.first_header_len,
             header_len32: self.level == 3
         }
     }
 }
 
-fn read_u16(slice: &[u8]) -> Option<u16> {
+pub(super) fn read_u16(slice: &[u8]) -> Option<u16> {
     match slice {
         &[lo, hi] => Some(u16::from_le_bytes([lo, hi])),
         _ => None
@@ -416,6 +631,7 @@ fn wrapping_csum(init: Wrapping<u8>, data: &[u8]) -> Wrapping<u8> {
     sum + init
 }
 
+#[cfg(feature = "alloc")]
 pub(super) fn split_data_at_nil_or_end(data: &[u8]) -> (&[u8], Option<&[u8]>) {
     match memchr::memchr(0, data) {
         Some(index) => (&data[0..index], Some(&data[index + 1..data.len()])),
@@ -435,6 +651,7 @@ pub(super) fn parse_pathname(data: &[u8], path: &mut PathBuf) {
     }
 }
 
+#[cfg(feature = "alloc")]
 pub(super) fn parse_pathname_to_str(data: &[u8], path: &mut String) {
     path.reserve(data.len());
     // split by all possible path separators
@@ -457,14 +674,15 @@ fn is_separator(c: char) -> bool {
     std::path::is_separator(c)
 }
 
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 fn is_separator(c: char) -> bool {
     c == '/' || c == '\\'
 }
 
-pub(super) fn parse_str_nilterm(
+#[cfg(feature = "alloc")]
+pub(crate) fn parse_str_nilterm(
         data: &[u8], nilterm: bool, ignore_sep: bool
-    ) -> Cow<str>
+    ) -> Cow<'_, str>
 {
     if let Some(index) = data.iter().position(|&c|
             !(0x20..0x7f).contains(&c) ||
@@ -517,7 +735,7 @@ mod tests {
     use super::*;
     use std::path::MAIN_SEPARATOR;
 
-    fn parse_filename(data: &[u8]) -> Cow<str> {
+    fn parse_filename(data: &[u8]) -> Cow<'_, str> {
         parse_str_nilterm(data, false, false)
     }
 
@@ -3,6 +3,7 @@ use chrono::{LocalResult, prelude::*};
 
 /// The type returned when parsing last modified timestamp.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TimestampResult {
     /// The timestamp could not be parsed.
     None,
@@ -3,7 +3,10 @@
 This library is for easy parsing of LHA headers and allows to read files compressed with some of the
 methods used by the archive format.
 
-This library does not provide high level methods for creating files or directories from the extracted archives.
+With `std` feature enabled, the [`Archive`] iterates over the archive's entries similarly to the `tar`
+crate and can unpack them into files and directories, the `write` module allows to rewrite archives by copying the already compressed
+content of files, and the `encode` module provides encoders for `-lh5-`, `-lh6-` and `-lh7-` methods which
+can be used to recompress archived files.
 
 There are many extensions to the base LHA headers, used by many different archive programs, in many different
 operating systems. This library only allows for parsing some basic properties of the archived files, such as
@@ -12,6 +15,9 @@ file path names and last modification timestamps.
 The [LhaHeader] exposes properties and methods to inspect the raw content of header extensions, as well as
 extended header data and may be explored by the user program in case extra archive properties are needed to be read.
 
+To list the archive's content without decoding files, walk the headers with [HeaderIter], which skips
+the compressed content of each file without creating decoders.
+
 LHA header levels: 0, 1, 2 and 3 are recognized.
 
 
@@ -48,6 +54,46 @@ compression method features. Otherwise the library will be compiled in the `no_s
 | `-pm1-`    | unsupported        | N/A     | PMarc, 8 Kb sliding window, static huffman
 | `-pm2-`    | unsupported        | N/A     | PMarc, 4 Kb sliding window, static huffman
 
+With the `alloc` feature, decoders of other compression methods, implementing the [`decode::Decoder`]
+trait, can be provided by registering a [`decode::DecoderFactory`] for the method's identifier with
+[`LhaDecodeReader::register_decoder`].
+
+## MacBinary
+
+With the optional `macbinary` feature enabled, the [`macbinary`](crate::macbinary) module provides a reader
+layer that unwraps MacBinary encoded files, often found in archives created on classic Mac OS, into the data
+and resource forks and Finder information.
+
+## Serde
+
+With the optional `serde` feature enabled, [`LhaHeader`] and related types implement `Serialize` and
+`Deserialize` traits. The raw header data is serialized byte-exact. The [`HeaderManifest`] provides
+a high-level view of the header with the parsed path name and typed metadata.
+
+## Limits
+
+By default neither the header parser nor the decoder impose any limits on the data read. When
+handling untrusted archives, provide [`Limits`] to [`LhaDecodeReader::with_limits`] or
+[`LhaHeader::read_with_limits`] to restrict header sizes, entry sizes, compression ratios, the total
+output size and the number of entries. Exceeding a limit results in the [`LhaError::Limit`] error.
+
+The checksum of the decoded files is only verified on request with [`LhaDecodeReader::crc_check`],
+unless the strict mode is enabled with [`LhaDecodeReader::set_strict`], in which case reading
+a corrupted file fails at its end with the [`LhaError::Checksum`] error.
+
+## Archives in memory
+
+[`LhaSliceArchive`] parses headers in place over a byte slice, e.g. a memory mapped file, yielding
+[`LhaSliceEntry`] entries with the metadata in [`LhaHeaderRef`] borrowed from the input and the
+compressed file data as a sub-slice. The entries can be accessed directly at their offsets and
+no memory is being allocated.
+
+When the whole decompressed file fits in memory, decode it in one go with
+[`LhaSliceEntry::decode_to_slice`], [`decode::decode_to_slice`] or [`LhaDecodeReader::read_entry_to_vec`].
+The output is written directly and verified with the checksum from the header. The `_in` variants,
+e.g. [`LhaSliceEntry::decode_to_slice_in`], reuse the decoder workspaces kept in
+a [`decode::WorkspacePool`].
+
 */
 #![cfg_attr(feature = "std", doc = r##"
 ## Example
@@ -101,13 +147,14 @@ and [`stub_io::Read`] is implemented for all types that implement [`std::io::Rea
 In this instance to read decompressed files, callers should access the [`LhaDecodeReader`] using
 [`std::io::Read`] trait methods.
 
-Without the `std` feature enabled `delharc` is compiled without the `std` library. 
-The extern crate `alloc` is still required though. Because [`std::io`] is missing,
+Without the `std` feature enabled `delharc` is compiled without the `std` library.
+The extern crate `alloc` is still required for parsing headers and decoding archives with
+[`LhaDecodeReader`], enabled by the `alloc` feature. Because [`std::io`] is missing,
 in this instance callers should interface the [`LhaDecodeReader`] using [`stub_io::Read`]
 trait directly. [`stub_io::Read`] is implemented initially for `&[u8]` slices and `Box<R: Read>`
 and can be implemented for other types.
 
-When compiled without `std`: [`stub_io::Read`], [`stub_io::Take`] and `UnexpectedEofError` are
+When compiled without `std`: [`stub_io::Read`], [`stub_io::BufRead`], [`stub_io::Take`] and `UnexpectedEofError` are
 re-exported directly under the crate root. `UnexpectedEofError` is only available in `no_std`.
 
 ```ignore
@@ -116,27 +163,62 @@ const DATA: &[u8] = include_bytes!("file.lzh");
 //...
 let lha_reader = LhaDecodeReader::new(DATA).unwrap();
 ```
+
+Without the `alloc` feature only the decoders from the [`decode`] module and the in-place header parser
+with [`LhaSliceArchive`] are available. Such decoders
+work in caller-provided workspaces holding the sliding window history and Huffman trees, e.g.
+[`decode::Lh5Workspace`], that can be placed in a `static` or on the stack:
+
+```ignore
+use delharc::decode::{Decoder, Lh5Decoder, Lh5Workspace};
+static mut WORKSPACE: Lh5Workspace = Lh5Workspace::new();
+//...
+let workspace: &'static mut Lh5Workspace = /* ... */;
+let mut decoder = Lh5Decoder::with_workspace(compressed_data, workspace);
+decoder.fill_buffer(&mut output[..original_size]).unwrap();
+```
 */
 // http://archive.gamedev.net/archive/reference/articles/article295.html
 #![cfg_attr(not(feature = "std"), no_std)]
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 extern crate alloc;
+#[cfg(feature = "std")]
+pub mod archive;
 pub mod crc;
 mod error;
+mod limits;
 pub mod stub_io;
 pub mod decode;
+#[cfg(feature = "std")]
+pub mod encode;
 pub mod header;
+#[cfg(feature = "alloc")]
+pub mod listing;
+pub mod slice;
+#[cfg(feature = "std")]
+pub mod write;
+#[cfg(feature = "macbinary")]
+pub mod macbinary;
 pub(crate) mod ringbuf;
 pub(crate) mod bitstream;
 pub(crate) mod statictree;
 
+#[cfg(feature = "std")]
+pub use archive::{Archive, Entries, Entry, EntryKind};
+#[cfg(feature = "alloc")]
 pub use decode::LhaDecodeReader;
-pub use header::{
-    LhaHeader, CompressionMethod, OsType, TimestampResult, MsDosAttrs
-};
+#[cfg(feature = "alloc")]
+pub use header::{LhaHeader, LhaHeaderBuilder, HeaderManifest, HeaderIter};
+pub use header::{LhaHeaderRef, CompressionMethod, OsType, TimestampResult, MsDosAttrs};
 pub use error::{LhaError, LhaResult};
+pub use limits::Limits;
+#[cfg(feature = "alloc")]
+pub use listing::{Listing, ListingFormat};
+pub use slice::{LhaSliceArchive, LhaSliceEntry};
+#[cfg(feature = "std")]
+pub use write::{LhaWriter, LhaRewriter};
 #[cfg(not(feature = "std"))]
-pub use stub_io::{Read, Take, UnexpectedEofError};
+pub use stub_io::{BufRead, Read, Take, UnexpectedEofError};
 
 #[cfg(feature = "std")]
 use std::path::Path;
@@ -0,0 +1,79 @@
+//! # Resource limits.
+use crate::error::LhaError;
+#[cfg(feature = "alloc")]
+use crate::header::LhaHeader;
+
+/// Resource limits protecting against hostile headers and decompression bombs.
+///
+/// The limits are enforced when parsing headers with [`LhaHeader::read_with_limits`] or
+/// [`LhaHeaderRef::read_with_limits`][crate::LhaHeaderRef::read_with_limits] and when
+/// decoding archives with [`LhaDecodeReader`][crate::LhaDecodeReader], see
+/// [`LhaDecodeReader::with_limits`][crate::LhaDecodeReader::with_limits].
+///
+/// Exceeding any of the limits results in the [`LhaError::Limit`] error.
+///
+/// The default value imposes no limits. Use the struct update syntax to set only some of them:
+///
+/// ```
+/// use delharc::Limits;
+/// let limits = Limits {
+///     max_header_size: 64 * 1024,
+///     max_total_output: 1 << 30,
+///     max_ratio: 1000,
+///     ..Limits::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// The maximum size of a single header in bytes, including the extended area and extra headers.
+    pub max_header_size: usize,
+    /// The maximum number of extra headers in a single header.
+    pub max_extra_headers: usize,
+    /// The maximum original size of a single entry in bytes, as declared in the header.
+    pub max_original_size: u64,
+    /// The maximum number of bytes decoded from all the entries read by the decoder.
+    pub max_total_output: u64,
+    /// The maximum ratio of the original size to the compressed size of a single entry, as declared
+    /// in the header.
+    ///
+    /// For the purpose of the ratio, the compressed size of empty entries is considered to be `1`.
+    pub max_ratio: u64,
+    /// The maximum number of entries read by the decoder.
+    pub max_entries: u64,
+}
+
+impl Limits {
+    /// No limits at all.
+    pub const UNLIMITED: Limits = Limits {
+        max_header_size: usize::MAX,
+        max_extra_headers: usize::MAX,
+        max_original_size: u64::MAX,
+        max_total_output: u64::MAX,
+        max_ratio: u64::MAX,
+        max_entries: u64::MAX,
+    };
+    /// Check the sizes declared in the parsed `header` against the limits.
+    ///
+    /// # Errors
+    /// Returns [`LhaError::Limit`] if the original size or the compression ratio exceeds the limits.
+    #[cfg(feature = "alloc")]
+    pub fn check_header<E>(&self, header: &LhaHeader) -> Result<(), LhaError<E>> {
+        self.check_sizes(header.compressed_size, header.original_size)
+    }
+
+    pub(crate) fn check_sizes<E>(&self, compressed_size: u64, original_size: u64) -> Result<(), LhaError<E>> {
+        if original_size > self.max_original_size {
+            return Err(LhaError::Limit("entry original size"))
+        }
+        if original_size > compressed_size.max(1).saturating_mul(self.max_ratio) {
+            return Err(LhaError::Limit("entry compression ratio"))
+        }
+        Ok(())
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits::UNLIMITED
+    }
+}
@@ -0,0 +1,521 @@
+/*! # Rendering archive listings.
+
+[`Listing`] collects [`ListingEntry`] rows from [`LhaHeader`]s and renders them in one of the
+[`ListingFormat`]s: compatible with the output of `lha l` and `lha v` commands of **LHa for UNIX**,
+or machine-readable CSV and TSV.
+
+Listings are written to any [`fmt::Write`] implementation, so they are also available in `no_std`.
+
+```
+use delharc::listing::{Listing, ListingFormat};
+# fn list(headers: &[delharc::LhaHeader]) -> core::fmt::Result {
+let mut listing = Listing::new();
+for header in headers {
+    listing.push(header);
+}
+let mut output = String::new();
+listing.write(&mut output, ListingFormat::Csv)?;
+# Ok(())
+# }
+```
+*/
+use core::fmt::{self, Write};
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use chrono::prelude::*;
+
+use crate::header::{LhaHeader, MsDosAttrs, OsType, method_to_string};
+
+/// The format of the rendered listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListingFormat {
+    /// The format of the `lha l` command: permissions, owner, size, ratio, timestamp and name.
+    Lha,
+    /// The format of the `lha v` command: adds the compressed size, method and CRC-16.
+    LhaVerbose,
+    /// Comma separated values with a header row.
+    Csv,
+    /// Tab separated values with a header row.
+    Tsv,
+}
+
+/// A single row of the archive listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingEntry {
+    /// The path name with `/` as a directory separator, see [`LhaHeader::parse_link`].
+    pub path: String,
+    /// The target of the symbolic link.
+    pub link_target: Option<String>,
+    /// Raw compression identifier.
+    pub compression: [u8;5],
+    /// Compressed file size.
+    pub compressed_size: u64,
+    /// Original file size.
+    pub original_size: u64,
+    /// Uncompressed file's CRC-16.
+    pub file_crc: u16,
+    /// Header level.
+    pub level: u8,
+    /// A raw OS-TYPE.
+    pub os_type: u8,
+    /// MS-DOS attributes.
+    pub msdos_attrs: MsDosAttrs,
+    /// Unix file mode, see [`LhaHeader::parse_unix_mode`].
+    pub unix_mode: Option<u16>,
+    /// Unix user and group ids, see [`LhaHeader::parse_unix_uid_gid`].
+    pub unix_uid_gid: Option<(u16, u16)>,
+    /// Last modified date and time.
+    pub last_modified: Option<NaiveDateTime>,
+    /// Whether the entry is a directory or a symbolic link.
+    pub is_directory: bool,
+}
+
+/// The archive totals.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ListingTotals {
+    /// The number of entries.
+    pub files: u64,
+    /// The sum of compressed sizes.
+    pub compressed_size: u64,
+    /// The sum of original sizes.
+    pub original_size: u64,
+    /// The latest modification date and time.
+    pub last_modified: Option<NaiveDateTime>,
+}
+
+/// Collects and renders the archive listing.
+#[derive(Debug, Clone, Default)]
+pub struct Listing {
+    entries: Vec<ListingEntry>,
+    #[cfg(feature = "std")]
+    local_time: bool,
+    now: Option<NaiveDateTime>,
+}
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun",
+                            "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+const LHA_SEPARATOR: &str =
+    "---------- ----------- ------- ------ ------------ --------------------";
+const LHA_VERBOSE_SEPARATOR: &str =
+    "---------- ----------- ------- ------- ------ ---------- ------------ -------------";
+const CSV_HEADER: [&str; 14] = ["path", "link_target", "method", "compressed_size", "original_size",
+                                "ratio", "crc", "level", "os", "attributes", "mode", "uid", "gid",
+                                "last_modified"];
+
+impl ListingEntry {
+    /// Create a listing row from the given header.
+    ///
+    /// The `Utc` timestamps are stored as date and time in the UTC time zone.
+    pub fn from_header(header: &LhaHeader) -> Self {
+        let (path, link_target) = header.parse_link();
+        ListingEntry {
+            path,
+            link_target,
+            compression: header.compression,
+            compressed_size: header.compressed_size,
+            original_size: header.original_size,
+            file_crc: header.file_crc,
+            level: header.level,
+            os_type: header.os_type,
+            msdos_attrs: header.msdos_attrs,
+            unix_mode: header.parse_unix_mode(),
+            unix_uid_gid: header.parse_unix_uid_gid(),
+            last_modified: header.parse_last_modified().to_naive_utc(),
+            is_directory: header.is_directory()
+        }
+    }
+    /// Return the compression ratio in per mille (compressed to original size).
+    ///
+    /// Return `None` for directories and empty files.
+    pub fn ratio_permille(&self) -> Option<u64> {
+        ratio_permille(self.compressed_size, self.original_size)
+            .filter(|_| !self.is_directory)
+    }
+    /// Return the compression method identifier as a string, e.g. `-lh5-`.
+    pub fn method(&self) -> String {
+        method_to_string(&self.compression)
+    }
+    /// Return the name of the operating system the entry was archived on, as displayed by **LHa for UNIX**.
+    pub fn os_name(&self) -> &'static str {
+        match OsType::try_from(self.os_type) {
+            Ok(OsType::Generic) => "generic",
+            Ok(OsType::MsDos) => "MS-DOS",
+            Ok(OsType::Win95) => "Win9x",
+            Ok(OsType::WinNt) => "WinNT",
+            Ok(OsType::Unix) => "Unix",
+            Ok(OsType::Os2) => "OS/2",
+            Ok(OsType::MacOs) => "Mac OS",
+            Ok(OsType::Amiga) => "Amiga",
+            Ok(OsType::Atari) => "Atari",
+            Ok(OsType::Java) => "Java",
+            Ok(OsType::Cpm) => "CP/M",
+            Ok(OsType::FlexOs) => "FLEX",
+            Ok(OsType::Runser) => "Runser",
+            Ok(OsType::TownsOs) => "TownsOS",
+            Ok(OsType::Os9) => "OS9",
+            Ok(OsType::Osk) => "OS9/68K",
+            Ok(OsType::Os386) => "OS386",
+            Ok(OsType::Human68k) => "Human68K",
+            Ok(OsType::Xosk) => "XOSK",
+            _ => "unknown"
+        }
+    }
+}
+
+impl ListingTotals {
+    /// Return the overall compression ratio in per mille or `None` if the total original size is `0`.
+    pub fn ratio_permille(&self) -> Option<u64> {
+        ratio_permille(self.compressed_size, self.original_size)
+    }
+}
+
+impl Listing {
+    /// Create a new and empty listing.
+    ///
+    /// With `std` feature enabled, the current local time is used to decide whether to display
+    /// the time or the year of recent entries in the **LHa for UNIX** formats. Without `std`,
+    /// the year is always displayed, unless [`Listing::set_now`] is called.
+    pub fn new() -> Self {
+        Listing {
+            #[cfg(feature = "std")]
+            now: Some(Local::now().naive_local()),
+            ..Default::default()
+        }
+    }
+    /// Set the current date and time used to decide whether the entry is recent (less than
+    /// 6 months old). Recent entries are displayed with the time instead of the year in the
+    /// **LHa for UNIX** formats. Pass `None` to always display the year.
+    pub fn set_now(&mut self, now: Option<NaiveDateTime>) {
+        self.now = now;
+    }
+    /// Convert timestamps of subsequently added entries stored in the UTC time zone to the local time,
+    /// like **LHa for UNIX** does.
+    ///
+    /// # `no_std`
+    /// Available only with `std` feature enabled.
+    #[cfg(feature = "std")]
+    pub fn set_local_time(&mut self, local_time: bool) {
+        self.local_time = local_time;
+    }
+    /// Append the row created from the given header to the listing.
+    pub fn push(&mut self, header: &LhaHeader) {
+        #[allow(unused_mut)]
+        let mut entry = ListingEntry::from_header(header);
+        #[cfg(feature = "std")]
+        if self.local_time {
+            entry.last_modified = header.parse_last_modified().to_local().map(|dt| dt.naive_local());
+        }
+        self.entries.push(entry);
+    }
+    /// Return the listing rows.
+    pub fn entries(&self) -> &[ListingEntry] {
+        &self.entries
+    }
+    /// Return the archive totals.
+    pub fn totals(&self) -> ListingTotals {
+        self.entries.iter().fold(ListingTotals::default(), |mut totals, entry| {
+            totals.files += 1;
+            totals.compressed_size += entry.compressed_size;
+            totals.original_size += entry.original_size;
+            totals.last_modified = totals.last_modified.max(entry.last_modified);
+            totals
+        })
+    }
+    /// Render the whole listing in the given `format`: the header, all the rows and the totals.
+    ///
+    /// The CSV and TSV formats don't include the totals.
+    pub fn write<W: Write>(&self, w: &mut W, format: ListingFormat) -> fmt::Result {
+        self.write_header(w, format)?;
+        for entry in self.entries.iter() {
+            self.write_entry(w, entry, format)?;
+        }
+        self.write_footer(w, format)
+    }
+    /// Render the column titles in the given `format`.
+    pub fn write_header<W: Write>(&self, w: &mut W, format: ListingFormat) -> fmt::Result {
+        match format {
+            ListingFormat::Lha => {
+                writeln!(w, "PERMISSION  UID  GID      SIZE  RATIO     STAMP           NAME")?;
+                writeln!(w, "{}", LHA_SEPARATOR)
+            }
+            ListingFormat::LhaVerbose => {
+                writeln!(w, "PERMISSION  UID  GID    PACKED    SIZE  RATIO METHOD CRC     STAMP          NAME")?;
+                writeln!(w, "{}", LHA_VERBOSE_SEPARATOR)
+            }
+            ListingFormat::Csv|ListingFormat::Tsv => {
+                let sep = separator(format);
+                for (i, title) in CSV_HEADER.iter().enumerate() {
+                    if i != 0 {
+                        w.write_char(sep)?;
+                    }
+                    w.write_str(title)?;
+                }
+                w.write_char('\n')
+            }
+        }
+    }
+    /// Render a single row in the given `format`.
+    ///
+    /// The row doesn't need to belong to this listing, in this instance the listing is only
+    /// used for its rendering options.
+    pub fn write_entry<W: Write>(&self, w: &mut W, entry: &ListingEntry, format: ListingFormat) -> fmt::Result {
+        match format {
+            ListingFormat::Lha|ListingFormat::LhaVerbose => self.write_lha_entry(w, entry, format),
+            ListingFormat::Csv|ListingFormat::Tsv => write_separated_entry(w, entry, format)
+        }
+    }
+    /// Render the archive totals in the given `format`.
+    ///
+    /// Nothing is written for the CSV and TSV formats.
+    pub fn write_footer<W: Write>(&self, w: &mut W, format: ListingFormat) -> fmt::Result {
+        let totals = self.totals();
+        let files = Plural(totals.files, "file");
+        let ratio = Ratio(totals.ratio_permille());
+        let stamp = Stamp(totals.last_modified, self.now);
+        match format {
+            ListingFormat::Lha => {
+                writeln!(w, "{}", LHA_SEPARATOR)?;
+                writeln!(w, " Total {:>15} {:>7} {} {}", files, totals.original_size, ratio, stamp)
+            }
+            ListingFormat::LhaVerbose => {
+                writeln!(w, "{}", LHA_VERBOSE_SEPARATOR)?;
+                writeln!(w, " Total {:>15} {:>7} {:>7} {}            {}",
+                         files, totals.compressed_size, totals.original_size, ratio, stamp)
+            }
+            ListingFormat::Csv|ListingFormat::Tsv => Ok(())
+        }
+    }
+
+    fn write_lha_entry<W: Write>(&self, w: &mut W, entry: &ListingEntry, format: ListingFormat) -> fmt::Result {
+        match entry.unix_mode {
+            Some(mode) => write!(w, "{}", Mode(mode, entry.is_directory))?,
+            None => {
+                let os_name = entry.os_name();
+                write!(w, "[{}]{:pad$}", os_name, "", pad = 8usize.saturating_sub(os_name.len()))?
+            }
+        }
+        match entry.unix_uid_gid {
+            Some((uid, gid)) => write!(w, " {:>5}/{:<5}", uid, gid)?,
+            None => write!(w, " {:11}", "")?
+        }
+        if format == ListingFormat::LhaVerbose {
+            write!(w, " {:>7}", entry.compressed_size)?;
+        }
+        write!(w, " {:>7} {}", entry.original_size, Ratio(entry.ratio_permille()))?;
+        if format == ListingFormat::LhaVerbose {
+            write!(w, " {} {:04x}", entry.method(), entry.file_crc)?;
+        }
+        write!(w, " {} {}", Stamp(entry.last_modified, self.now), entry.path)?;
+        if let Some(target) = &entry.link_target {
+            write!(w, " -> {}", target)?;
+        }
+        w.write_char('\n')
+    }
+}
+
+fn ratio_permille(compressed_size: u64, original_size: u64) -> Option<u64> {
+    if original_size == 0 {
+        return None
+    }
+    Some((compressed_size as u128 * 1000 / original_size as u128).min(u64::MAX as u128) as u64)
+}
+
+fn separator(format: ListingFormat) -> char {
+    if format == ListingFormat::Tsv { '\t' } else { ',' }
+}
+
+fn write_separated_entry<W: Write>(w: &mut W, entry: &ListingEntry, format: ListingFormat) -> fmt::Result {
+    let sep = separator(format);
+    let field = |w: &mut W, value: &str, first: bool| -> fmt::Result {
+        if !first {
+            w.write_char(sep)?;
+        }
+        if format == ListingFormat::Csv {
+            write_csv_field(w, value)
+        }
+        else {
+            write_tsv_field(w, value)
+        }
+    };
+    let mut buf = String::new();
+    let mut text = |w: &mut W, args: fmt::Arguments<'_>| -> fmt::Result {
+        buf.clear();
+        buf.write_fmt(args)?;
+        field(w, &buf, false)
+    };
+    field(w, &entry.path, true)?;
+    field(w, entry.link_target.as_deref().unwrap_or(""), false)?;
+    field(w, &entry.method(), false)?;
+    text(w, format_args!("{}", entry.compressed_size))?;
+    text(w, format_args!("{}", entry.original_size))?;
+    match entry.ratio_permille() {
+        Some(ratio) => text(w, format_args!("{}.{}", ratio / 10, ratio % 10))?,
+        None => text(w, format_args!(""))?
+    }
+    text(w, format_args!("{:04x}", entry.file_crc))?;
+    text(w, format_args!("{}", entry.level))?;
+    text(w, format_args!("{}", entry.os_name()))?;
+    text(w, format_args!("{}", Attrs(entry.msdos_attrs)))?;
+    match entry.unix_mode {
+        Some(mode) => text(w, format_args!("{:o}", mode))?,
+        None => text(w, format_args!(""))?
+    }
+    match entry.unix_uid_gid {
+        Some((uid, gid)) => {
+            text(w, format_args!("{}", uid))?;
+            text(w, format_args!("{}", gid))?;
+        }
+        None => {
+            text(w, format_args!(""))?;
+            text(w, format_args!(""))?;
+        }
+    }
+    match entry.last_modified {
+        Some(dt) => text(w, format_args!("{} {}", dt.date(), dt.time()))?,
+        None => text(w, format_args!(""))?
+    }
+    w.write_char('\n')
+}
+
+fn write_csv_field<W: Write>(w: &mut W, value: &str) -> fmt::Result {
+    if !value.contains([',', '"', '\r', '\n']) {
+        return w.write_str(value)
+    }
+    w.write_char('"')?;
+    for (i, part) in value.split('"').enumerate() {
+        if i != 0 {
+            w.write_str("\"\"")?;
+        }
+        w.write_str(part)?;
+    }
+    w.write_char('"')
+}
+
+fn write_tsv_field<W: Write>(w: &mut W, value: &str) -> fmt::Result {
+    for c in value.chars() {
+        match c {
+            '\t' => w.write_str("\\t")?,
+            '\n' => w.write_str("\\n")?,
+            '\r' => w.write_str("\\r")?,
+            '\\' => w.write_str("\\\\")?,
+            c => w.write_char(c)?
+        }
+    }
+    Ok(())
+}
+
+struct Plural(u64, &'static str);
+
+impl fmt::Display for Plural {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buf = String::new();
+        write!(buf, "{} {}{}", self.0, self.1, if self.0 == 1 { "" } else { "s" })?;
+        f.pad(&buf)
+    }
+}
+
+// the ratio in percent with one decimal digit, 6 characters
+struct Ratio(Option<u64>);
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(permille) => {
+                let permille = permille.min(99999);
+                write!(f, "{:>3}.{}%", permille / 10, permille % 10)
+            }
+            None => f.write_str("******")
+        }
+    }
+}
+
+// "Mon dd HH:MM" for recent entries or "Mon dd  YYYY", 12 characters
+struct Stamp(Option<NaiveDateTime>, Option<NaiveDateTime>);
+
+impl fmt::Display for Stamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dt = match self.0 {
+            Some(dt) => dt,
+            None => return f.write_str("            ")
+        };
+        let month = MONTHS[dt.month0() as usize];
+        let is_recent = self.1.map_or(false, |now| {
+            now.signed_duration_since(dt).num_days().abs() < 183
+        });
+        if is_recent {
+            write!(f, "{} {:>2} {:02}:{:02}", month, dt.day(), dt.hour(), dt.minute())
+        }
+        else {
+            write!(f, "{} {:>2}  {:04}", month, dt.day(), dt.year())
+        }
+    }
+}
+
+// Unix file mode, e.g. "drwxr-xr-x"
+struct Mode(u16, bool);
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Mode(mode, is_directory) = *self;
+        f.write_char(match mode & 0o170000 {
+            0o040000 => 'd',
+            0o120000 => 'l',
+            0 if is_directory => 'd',
+            _ => '-'
+        })?;
+        for (shift, special, special_ch) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+            let bits = mode >> shift;
+            f.write_char(if bits & 4 != 0 { 'r' } else { '-' })?;
+            f.write_char(if bits & 2 != 0 { 'w' } else { '-' })?;
+            f.write_char(match (bits & 1 != 0, mode & special != 0) {
+                (true, true) => special_ch,
+                (false, true) => special_ch.to_ascii_uppercase(),
+                (true, false) => 'x',
+                (false, false) => '-'
+            })?;
+        }
+        Ok(())
+    }
+}
+
+// MS-DOS attributes, e.g. "-----A"
+struct Attrs(MsDosAttrs);
+
+impl fmt::Display for Attrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (flag, c) in [(MsDosAttrs::READ_ONLY, 'R'), (MsDosAttrs::HIDDEN, 'H'), (MsDosAttrs::SYSTEM, 'S'),
+                          (MsDosAttrs::VOLUME, 'V'), (MsDosAttrs::SUBDIR, 'D'), (MsDosAttrs::ARCHIVE, 'A')]
+        {
+            f.write_char(if self.0.contains(flag) { c } else { '-' })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_format_works() {
+        let mut s = String::new();
+        write!(s, "{}|{}|{}", Mode(0o100644, false), Mode(0o040755, true), Mode(0o105754, false)).unwrap();
+        assert_eq!(s, "-rw-r--r--|drwxr-xr-x|-rwsr-xr-T");
+        s.clear();
+        write!(s, "{}|{}|{}", Ratio(Some(456)), Ratio(Some(1000)), Ratio(None)).unwrap();
+        assert_eq!(s, " 45.6%|100.0%|******");
+        s.clear();
+        let dt = NaiveDate::from_ymd_opt(2010, 1, 2).unwrap().and_hms_opt(3, 4, 5).unwrap();
+        let now = NaiveDate::from_ymd_opt(2010, 5, 2).unwrap().and_hms_opt(0, 0, 0);
+        write!(s, "{}|{}|{}", Stamp(Some(dt), None), Stamp(Some(dt), now), Stamp(None, now)).unwrap();
+        assert_eq!(s, "Jan  2  2010|Jan  2 03:04|            ");
+        s.clear();
+        write!(s, "{}|{:>8}", Attrs(MsDosAttrs::ARCHIVE|MsDosAttrs::READ_ONLY), Plural(1, "file")).unwrap();
+        assert_eq!(s, "R----A|  1 file");
+        s.clear();
+        write_csv_field(&mut s, "a,\"b\"").unwrap();
+        s.push(' ');
+        write_tsv_field(&mut s, "a\tb\\").unwrap();
+        assert_eq!(s, "\"a,\"\"b\"\"\" a\\tb\\\\");
+    }
+}
@@ -0,0 +1,445 @@
+/*! # MacBinary decoding layer.
+
+Archives created with MacLHA on classic Mac OS ([`OsType::MacOs`][crate::OsType::MacOs]) often store
+files encoded in the [MacBinary] format, which packs the data fork, the resource fork and the Finder
+information into a single stream.
+
+[`MacBinaryReader`] wraps the decompressed content of such a file, detects MacBinary I, II or III
+encoding and exposes the forks as separate streams, along with the [`MacBinaryHeader`] metadata.
+If the content is not MacBinary encoded, the reader passes it through unchanged as the data fork.
+
+With `std` feature enabled the resource fork along with the Finder information can be written as an
+[AppleDouble] `._name` sidecar file using [`MacBinaryReader::write_apple_double`].
+
+[MacBinary]: https://files.stairways.com/other/macbinaryii-standard-info.txt
+[AppleDouble]: https://datatracker.ietf.org/doc/html/rfc1740
+*/
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::{io, borrow::Cow};
+#[cfg(not(feature = "std"))]
+use alloc::borrow::Cow;
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use crate::stub_io::Read;
+use crate::header::parse_str_nilterm;
+
+/// The size of the MacBinary header and the block alignment of forks.
+pub const MACBINARY_BLOCK_SIZE: usize = 128;
+/// Seconds between the classic Mac OS epoch (1904-01-01) and the Unix epoch.
+const MAC_EPOCH_OFFSET: i64 = 2_082_844_800;
+/// The maximum length of a fork accepted in the MacBinary I header.
+const MAX_FORK_LEN: u32 = 0x7F_FFFF;
+
+/// The detected version of the MacBinary format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MacBinaryVersion {
+    MacBinary1,
+    MacBinary2,
+    MacBinary3,
+}
+
+/// Which fork is currently being read from [`MacBinaryReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fork {
+    /// The data fork, or the whole content if the stream is not MacBinary encoded.
+    Data,
+    /// The resource fork.
+    Resource,
+    /// Both forks have been read.
+    End,
+}
+
+/// Classic Mac OS Finder information (`FInfo`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FinderInfo {
+    /// The file type code, e.g. `APPL` or `TEXT`.
+    pub file_type: [u8;4],
+    /// The creator code.
+    pub creator: [u8;4],
+    /// The Finder flags.
+    pub flags: u16,
+    /// The vertical and horizontal location of the icon in its window.
+    pub location: (i16, i16),
+    /// The window or folder id.
+    pub folder: u16,
+}
+
+/// A parsed MacBinary header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacBinaryHeader {
+    /// The detected MacBinary version.
+    pub version: MacBinaryVersion,
+    /// The raw Mac OS file name.
+    pub filename: [u8;63],
+    /// The length of the file name.
+    pub filename_len: u8,
+    /// The Finder information.
+    pub finder_info: FinderInfo,
+    /// The extended Finder flags, only in MacBinary III.
+    pub extended_flags: u8,
+    /// The script code of the file name, only in MacBinary III.
+    pub script: u8,
+    /// Whether the file is protected.
+    pub protected: bool,
+    /// The length of the data fork in bytes.
+    pub data_fork_len: u32,
+    /// The length of the resource fork in bytes.
+    pub resource_fork_len: u32,
+    /// The creation date in seconds since 1904-01-01.
+    pub created: u32,
+    /// The modification date in seconds since 1904-01-01.
+    pub modified: u32,
+    /// The length of the "Get Info" comment following the forks.
+    pub comment_len: u16,
+    /// The length of the secondary header preceding the data fork.
+    pub secondary_header_len: u16,
+}
+
+/// A reader that splits a MacBinary encoded stream into forks.
+///
+/// Reading from this reader yields the content of the current [`Fork`]. When the current fork
+/// has been read, call [`MacBinaryReader::next_fork`] to proceed to the next one.
+///
+/// # `no_std`
+/// Without the `std` feature the crate's [`Read`] trait is implemented instead of [`std::io::Read`].
+#[derive(Debug)]
+pub struct MacBinaryReader<R> {
+    inner: R,
+    header: Option<MacBinaryHeader>,
+    head: [u8; MACBINARY_BLOCK_SIZE],
+    head_pos: usize,
+    head_len: usize,
+    fork: Fork,
+    remaining: u64,
+}
+
+impl MacBinaryHeader {
+    /// Attempt to parse the MacBinary header from the first 128 bytes of a file.
+    ///
+    /// Return `None` if the data does not look like a MacBinary header.
+    pub fn parse(block: &[u8; MACBINARY_BLOCK_SIZE]) -> Option<MacBinaryHeader> {
+        if block[0] != 0 || block[74] != 0 {
+            return None
+        }
+        let filename_len = block[1];
+        if !(1..=63).contains(&filename_len) {
+            return None
+        }
+        let data_fork_len = read_u32_be(&block[83..87]);
+        let resource_fork_len = read_u32_be(&block[87..91]);
+        let crc_ok = crc16_xmodem(&block[..124]) == read_u16_be(&block[124..126]);
+        let version = if &block[102..106] == b"mBIN" && crc_ok {
+            MacBinaryVersion::MacBinary3
+        }
+        else if crc_ok && block[123] >= 129 {
+            MacBinaryVersion::MacBinary2
+        }
+        else if block[82] == 0 && block[99..126].iter().all(|&b| b == 0) &&
+                data_fork_len <= MAX_FORK_LEN && resource_fork_len <= MAX_FORK_LEN
+        {
+            MacBinaryVersion::MacBinary1
+        }
+        else {
+            return None
+        };
+        let mut filename = [0u8;63];
+        filename.copy_from_slice(&block[2..65]);
+        let mut finder_info = FinderInfo::default();
+        finder_info.file_type.copy_from_slice(&block[65..69]);
+        finder_info.creator.copy_from_slice(&block[69..73]);
+        finder_info.flags = (block[73] as u16) << 8;
+        finder_info.location = (read_u16_be(&block[75..77]) as i16, read_u16_be(&block[77..79]) as i16);
+        finder_info.folder = read_u16_be(&block[79..81]);
+        let mut header = MacBinaryHeader {
+            version,
+            filename,
+            filename_len,
+            finder_info,
+            extended_flags: 0,
+            script: 0,
+            protected: block[81] & 1 == 1,
+            data_fork_len,
+            resource_fork_len,
+            created: read_u32_be(&block[91..95]),
+            modified: read_u32_be(&block[95..99]),
+            comment_len: 0,
+            secondary_header_len: 0
+        };
+        if version >= MacBinaryVersion::MacBinary2 {
+            header.finder_info.flags |= block[101] as u16;
+            header.comment_len = read_u16_be(&block[99..101]);
+            header.secondary_header_len = read_u16_be(&block[120..122]);
+        }
+        if version == MacBinaryVersion::MacBinary3 {
+            header.script = block[106];
+            header.extended_flags = block[107];
+        }
+        Some(header)
+    }
+    /// Return the raw Mac OS file name.
+    pub fn raw_filename(&self) -> &[u8] {
+        &self.filename[..self.filename_len as usize]
+    }
+    /// Return the Mac OS file name, converting all non-ASCII or control characters to `%xx` sequences.
+    pub fn parse_filename(&self) -> Cow<'_, str> {
+        parse_str_nilterm(self.raw_filename(), false, false)
+    }
+    /// Return the file name for the [AppleDouble] sidecar file: `._` followed by the given `name`.
+    ///
+    /// [AppleDouble]: https://datatracker.ietf.org/doc/html/rfc1740
+    pub fn apple_double_name(name: &str) -> String {
+        let mut res = String::with_capacity(name.len() + 2);
+        res.push_str("._");
+        res.push_str(name);
+        res
+    }
+    /// Return the creation date as naive date and time.
+    pub fn parse_created(&self) -> Option<NaiveDateTime> {
+        parse_mac_datetime(self.created)
+    }
+    /// Return the modification date as naive date and time.
+    pub fn parse_modified(&self) -> Option<NaiveDateTime> {
+        parse_mac_datetime(self.modified)
+    }
+    /// Return the number of bytes of the MacBinary stream preceding the data fork.
+    pub fn data_fork_offset(&self) -> u64 {
+        MACBINARY_BLOCK_SIZE as u64 + padded_len(self.secondary_header_len.into())
+    }
+    /// Return the number of bytes of the MacBinary stream preceding the resource fork.
+    pub fn resource_fork_offset(&self) -> u64 {
+        self.data_fork_offset() + padded_len(self.data_fork_len.into())
+    }
+}
+
+impl<R: Read> MacBinaryReader<R> {
+    /// Create a new `MacBinaryReader` reading the first 128 bytes from `inner` to detect the format.
+    ///
+    /// If the MacBinary header is detected, the reader is positioned at the beginning of the data fork.
+    /// Otherwise the whole stream is passed through as the data fork.
+    ///
+    /// # Errors
+    /// Return an error from the underlying reader.
+    pub fn new(mut inner: R) -> Result<Self, R::Error> {
+        let mut head = [0u8; MACBINARY_BLOCK_SIZE];
+        let head_len = inner.read_all(&mut head)?;
+        let header = if head_len == MACBINARY_BLOCK_SIZE {
+            MacBinaryHeader::parse(&head)
+        }
+        else {
+            None
+        };
+        let mut reader = MacBinaryReader {
+            inner,
+            head,
+            head_pos: 0,
+            head_len,
+            fork: Fork::Data,
+            remaining: 0,
+            header: None
+        };
+        if let Some(header) = header {
+            reader.skip(padded_len(header.secondary_header_len.into()))?;
+            reader.head_len = 0;
+            reader.remaining = header.data_fork_len.into();
+            reader.header = Some(header);
+        }
+        Ok(reader)
+    }
+    /// Return the MacBinary header if the stream has been recognized as MacBinary encoded.
+    pub fn header(&self) -> Option<&MacBinaryHeader> {
+        self.header.as_ref()
+    }
+    /// Return whether the stream has been recognized as MacBinary encoded.
+    pub fn is_macbinary(&self) -> bool {
+        self.header.is_some()
+    }
+    /// Return the fork currently being read.
+    pub fn fork(&self) -> Fork {
+        self.fork
+    }
+    /// Skip the remaining content of the current fork and proceed to the next one.
+    ///
+    /// Return `Ok(true)` if the resource fork is ready to be read and `Ok(false)` if there are
+    /// no more forks.
+    ///
+    /// # Errors
+    /// Return an error from the underlying reader, e.g. if the stream ends prematurely.
+    pub fn next_fork(&mut self) -> Result<bool, R::Error> {
+        match (self.fork, &self.header) {
+            (Fork::Data, Some(header)) => {
+                let padding = padded_len(header.data_fork_len.into()) - u64::from(header.data_fork_len);
+                let resource_fork_len = header.resource_fork_len.into();
+                self.skip(self.remaining + padding)?;
+                self.fork = Fork::Resource;
+                self.remaining = resource_fork_len;
+                Ok(true)
+            }
+            _ => {
+                self.fork = Fork::End;
+                self.remaining = 0;
+                self.head_len = 0;
+                Ok(false)
+            }
+        }
+    }
+    /// Unwrap the underlying reader and return it.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn skip(&mut self, len: u64) -> Result<(), R::Error> {
+        let mut buf = [0u8; MACBINARY_BLOCK_SIZE];
+        let mut len = len;
+        while len != 0 {
+            let chunk = len.min(MACBINARY_BLOCK_SIZE as u64) as usize;
+            self.inner.read_exact(&mut buf[..chunk])?;
+            len -= chunk as u64;
+        }
+        Ok(())
+    }
+
+    fn read_fork(&mut self, buf: &mut [u8]) -> Result<usize, R::Error> {
+        if self.header.is_none() {
+            if self.fork != Fork::Data {
+                return Ok(0)
+            }
+            if self.head_pos < self.head_len {
+                let head = &self.head[self.head_pos..self.head_len];
+                let len = head.len().min(buf.len());
+                buf[..len].copy_from_slice(&head[..len]);
+                self.head_pos += len;
+                return Ok(len)
+            }
+            return self.inner.read_all(buf)
+        }
+        let len = (buf.len() as u64).min(self.remaining) as usize;
+        if len == 0 {
+            return Ok(0)
+        }
+        self.inner.read_exact(&mut buf[..len])?;
+        self.remaining -= len as u64;
+        Ok(len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read<Error=io::Error>> MacBinaryReader<R> {
+    /// Write an [AppleDouble] file with the Finder information and the resource fork to `wr`.
+    ///
+    /// The resource fork is being read from the stream, so this method should be called after
+    /// the data fork has been read or skipped. The reader is advanced to the resource fork if
+    /// necessary. On success return the number of bytes written.
+    ///
+    /// # Errors
+    /// Return an error if the stream is not MacBinary encoded, the resource fork has been already
+    /// read or from the underlying reader and writer.
+    ///
+    /// # `no_std`
+    /// This method is only available with `std` feature enabled.
+    ///
+    /// [AppleDouble]: https://datatracker.ietf.org/doc/html/rfc1740
+    pub fn write_apple_double<W: io::Write>(&mut self, wr: &mut W) -> io::Result<u64> {
+        const HEADER_LEN: u32 = 26 + 2 * 12;
+        const FINDER_INFO_LEN: u32 = 32;
+        let header = self.header.clone().ok_or_else(||
+            io::Error::new(io::ErrorKind::InvalidInput, "not a MacBinary stream"))?;
+        if self.fork == Fork::Data {
+            self.next_fork()?;
+        }
+        if self.fork != Fork::Resource || self.remaining != header.resource_fork_len.into() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "resource fork already read"))
+        }
+        let mut head = Vec::with_capacity((HEADER_LEN + FINDER_INFO_LEN) as usize);
+        head.extend_from_slice(&0x0005_1607u32.to_be_bytes()); // magic
+        head.extend_from_slice(&0x0002_0000u32.to_be_bytes()); // version
+        head.extend_from_slice(&[0u8;16]); // filler
+        head.extend_from_slice(&2u16.to_be_bytes()); // number of entries
+        // entry id, offset, length
+        for (id, offset, len) in [(9u32, HEADER_LEN, FINDER_INFO_LEN),
+                                  (2u32, HEADER_LEN + FINDER_INFO_LEN, header.resource_fork_len)]
+        {
+            head.extend_from_slice(&id.to_be_bytes());
+            head.extend_from_slice(&offset.to_be_bytes());
+            head.extend_from_slice(&len.to_be_bytes());
+        }
+        let info = &header.finder_info;
+        head.extend_from_slice(&info.file_type);
+        head.extend_from_slice(&info.creator);
+        head.extend_from_slice(&info.flags.to_be_bytes());
+        head.extend_from_slice(&info.location.0.to_be_bytes());
+        head.extend_from_slice(&info.location.1.to_be_bytes());
+        head.extend_from_slice(&info.folder.to_be_bytes());
+        head.extend_from_slice(&[0u8;16]); // extended Finder info
+        wr.write_all(&head)?;
+        let len = io::copy(self, wr)?;
+        Ok(head.len() as u64 + len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read<Error=io::Error>> io::Read for MacBinaryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_fork(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<R: Read> Read for MacBinaryReader<R> {
+    type Error = R::Error;
+
+    fn unexpected_eof() -> Self::Error {
+        R::unexpected_eof()
+    }
+
+    fn read_all(&mut self, mut buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let orig_len = buf.len();
+        while !buf.is_empty() {
+            match self.read_fork(buf)? {
+                0 => break,
+                n => buf = &mut buf[n..]
+            }
+        }
+        Ok(orig_len - buf.len())
+    }
+}
+
+fn padded_len(len: u64) -> u64 {
+    let block = MACBINARY_BLOCK_SIZE as u64;
+    (len + block - 1) / block * block
+}
+
+fn parse_mac_datetime(ts: u32) -> Option<NaiveDateTime> {
+    Utc.timestamp_opt(ts as i64 - MAC_EPOCH_OFFSET, 0).single().map(|dt| dt.naive_utc())
+}
+
+fn read_u16_be(slice: &[u8]) -> u16 {
+    u16::from_be_bytes([slice[0], slice[1]])
+}
+
+fn read_u32_be(slice: &[u8]) -> u32 {
+    u32::from_be_bytes([slice[0], slice[1], slice[2], slice[3]])
+}
+
+/// CRC-16/XMODEM used by the MacBinary II header.
+fn crc16_xmodem(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { crc << 1 ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc16_xmodem_works() {
+        assert_eq!(crc16_xmodem(b""), 0);
+        assert_eq!(crc16_xmodem(b"123456789"), 0x31C3);
+    }
+}
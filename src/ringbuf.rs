@@ -7,23 +7,143 @@ use core::ops::Index;
 pub trait RingBuffer: Default + Index<usize, Output=u8> {
     /// The size of the buffer in bytes.
     const BUFFER_SIZE: usize;
+    /// A new buffer filled with spaces with the cursor set to 0.
+    const NEW: Self;
+    /// Fills the buffer with spaces and sets the cursor to 0.
+    fn reset(&mut self);
     /// The current value of the internal cursor.
     fn cursor(&self) -> usize;
     /// Allows to set the current value of the internal cursor.
     fn set_cursor(&mut self, pos: isize);
     /// Pushes the new byte value to the buffer, overwriting the oldest one.
     fn push(&mut self, byte: u8);
-    /// Returns an iterator which will yield consecutive bytes from the buffer starting at `-offset`
-    /// from the last element.
+    /// Pushes all the bytes from the given slice to the buffer, overwriting the oldest ones.
+    fn push_slice(&mut self, bytes: &[u8]);
+    /// Fills the `target` with consecutive bytes from the buffer starting at `-offset` from the last element.
     ///
     /// `offset` = 0 indicates the last element written to the buffer.
     ///
-    /// At each iteration the yielded value is also being pushed to the ring buffer.
-    fn iter_from_offset(&mut self, offset: usize) -> HistoryIter<'_, Self>;
-    /// Returns an iterator which will yield consecutive bytes from the buffer starting at `pos`.
+    /// The copied bytes are also being pushed to the ring buffer, so the `target` may be longer than
+    /// the `offset`, in which instance the copied sequence repeats itself.
+    fn copy_from_offset(&mut self, offset: usize, target: &mut [u8]);
+    /// Fills the `target` with consecutive bytes from the buffer starting at `pos`.
     ///
-    /// At each iteration the yielded value is also being pushed to the ring buffer.
-    fn iter_from_pos(&mut self, pos: usize) -> HistoryIter<'_, Self>;
+    /// The copied bytes are also being pushed to the ring buffer, so the copied sequence repeats
+    /// itself if the `target` reaches the cursor.
+    fn copy_from_pos(&mut self, pos: usize, target: &mut [u8]);
+    /// Fills `output` at `index` with up to `count` consecutive bytes starting at `-offset` from
+    /// the last element, as if `output` up to `index` has been already pushed to the buffer.
+    ///
+    /// The buffer is left intact, `output` should be pushed to the buffer afterwards.
+    /// Returns the index past the copied bytes.
+    fn copy_within_from_offset(&self, offset: usize, output: &mut [u8], index: usize, count: usize) -> usize;
+    /// Fills `output` at `index` with up to `count` consecutive bytes starting at `pos`, as if
+    /// `output` up to `index` has been already pushed to the buffer.
+    ///
+    /// The buffer is left intact, `output` should be pushed to the buffer afterwards.
+    /// Returns the index past the copied bytes.
+    fn copy_within_from_pos(&self, pos: usize, output: &mut [u8], index: usize, count: usize) -> usize;
+    /// The length of the longest span following the cursor without wrapping around the end of
+    /// the internal buffer.
+    fn span_capacity(&self) -> usize;
+    /// Returns `len` bytes preceding the cursor, they must not wrap around the end of the internal buffer.
+    fn recent(&self, len: usize) -> &[u8];
+    /// Advances the cursor by `len` bytes, not exceeding the [`RingBuffer::span_capacity`].
+    fn advance(&mut self, len: usize);
+    /// Sets the byte at `index` past the cursor, without moving the cursor.
+    fn set_ahead(&mut self, index: usize, byte: u8);
+    /// Fills the span of `len` bytes past the cursor at `index` with up to `count` consecutive bytes
+    /// starting at `-offset` from the last element, as if the span up to `index` has been already
+    /// pushed to the buffer.
+    ///
+    /// The cursor is left intact, the buffer should be [advanced][RingBuffer::advance] afterwards.
+    /// Returns the index past the copied bytes.
+    fn copy_ahead_from_offset(&mut self, offset: usize, len: usize, index: usize, count: usize) -> usize;
+    /// Fills the span of `len` bytes past the cursor at `index` with up to `count` consecutive bytes
+    /// starting at `pos`, as if the span up to `index` has been already pushed to the buffer.
+    ///
+    /// The cursor is left intact, the buffer should be [advanced][RingBuffer::advance] afterwards.
+    /// Returns the index past the copied bytes.
+    fn copy_ahead_from_pos(&mut self, pos: usize, len: usize, index: usize, count: usize) -> usize;
+}
+
+/// The output of the decoders filled with the decoded bytes and the history copied from the ring
+/// buffer `B`.
+///
+/// The output is either a buffer provided by the caller, pushed to the ring buffer once filled,
+/// or a [`RingSpan`] following the cursor of the ring buffer, filled in place.
+#[allow(dead_code)]
+pub trait HistoryTarget<B: RingBuffer> {
+    /// The number of bytes to fill.
+    fn size(&self) -> usize;
+    /// Sets the byte at `index`.
+    fn set(&mut self, ringbuf: &mut B, index: usize, byte: u8);
+    /// See [`RingBuffer::copy_within_from_offset`].
+    fn copy_from_offset(&mut self, ringbuf: &mut B, offset: usize, index: usize, count: usize) -> usize;
+    /// See [`RingBuffer::copy_within_from_pos`].
+    fn copy_from_pos(&mut self, ringbuf: &mut B, pos: usize, index: usize, count: usize) -> usize;
+    /// Adds the filled output to the history.
+    fn finish(self, ringbuf: &mut B);
+}
+
+/// The span of the given length following the cursor of the ring buffer, filled in place.
+///
+/// The length must not exceed the [`RingBuffer::span_capacity`].
+#[derive(Debug, Clone, Copy)]
+pub struct RingSpan(pub usize);
+
+impl<B: RingBuffer> HistoryTarget<B> for &mut [u8] {
+    #[inline(always)]
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    #[inline(always)]
+    fn set(&mut self, _ringbuf: &mut B, index: usize, byte: u8) {
+        self[index] = byte;
+    }
+
+    #[inline(always)]
+    fn copy_from_offset(&mut self, ringbuf: &mut B, offset: usize, index: usize, count: usize) -> usize {
+        ringbuf.copy_within_from_offset(offset, self, index, count)
+    }
+
+    #[inline(always)]
+    fn copy_from_pos(&mut self, ringbuf: &mut B, pos: usize, index: usize, count: usize) -> usize {
+        ringbuf.copy_within_from_pos(pos, self, index, count)
+    }
+
+    #[inline]
+    fn finish(self, ringbuf: &mut B) {
+        ringbuf.push_slice(self)
+    }
+}
+
+impl<B: RingBuffer> HistoryTarget<B> for RingSpan {
+    #[inline(always)]
+    fn size(&self) -> usize {
+        self.0
+    }
+
+    #[inline(always)]
+    fn set(&mut self, ringbuf: &mut B, index: usize, byte: u8) {
+        ringbuf.set_ahead(index, byte)
+    }
+
+    #[inline(always)]
+    fn copy_from_offset(&mut self, ringbuf: &mut B, offset: usize, index: usize, count: usize) -> usize {
+        ringbuf.copy_ahead_from_offset(offset, self.0, index, count)
+    }
+
+    #[inline(always)]
+    fn copy_from_pos(&mut self, ringbuf: &mut B, pos: usize, index: usize, count: usize) -> usize {
+        ringbuf.copy_ahead_from_pos(pos, self.0, index, count)
+    }
+
+    #[inline]
+    fn finish(self, ringbuf: &mut B) {
+        ringbuf.advance(self.0)
+    }
 }
 
 /// A generic ring buffer implementation using arrays of the size of the power of two as internal buffers.
@@ -44,34 +164,13 @@ impl<const N: usize> fmt::Debug for RingArrayBuf<N> {
     }
 }
 
-/// The ring buffer history iterator.
-pub struct HistoryIter<'a, T> {
-    index: usize,
-    ringbuf: &'a mut T
-}
-
-impl<'a, T: RingBuffer> Iterator for HistoryIter<'a, T> {
-    type Item = u8;
-
-    #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        let index = self.index;
-        let res = self.ringbuf[index];
-        self.index = index.wrapping_add(1);
-        self.ringbuf.push(res);
-        Some(res)
-    }
-}
-
 macro_rules! index_mask {
     ($size:expr) => { ($size - 1) };
 }
 
 impl<const N: usize> Default for RingArrayBuf<N> {
     fn default() -> Self {
-        assert!(N.is_power_of_two(), "invalid RingArrayBuf size: should be a power of two!");
-        let buffer = [b' '; N];
-        RingArrayBuf { buffer, cursor: 0 }
+        Self::new()
     }
 }
 
@@ -84,8 +183,100 @@ impl<const N: usize> Index<usize> for RingArrayBuf<N> {
     }
 }
 
+impl<const N: usize> RingArrayBuf<N> {
+    /// Creates a new buffer filled with spaces.
+    pub const fn new() -> Self {
+        assert!(N.is_power_of_two(), "invalid RingArrayBuf size: should be a power of two!");
+        let buffer = [b' '; N];
+        RingArrayBuf { buffer, cursor: 0 }
+    }
+
+    /// Copies bytes from the buffer starting at `index` to the `target`, the `target` must not be
+    /// longer than `N`.
+    #[inline]
+    fn read_slice(&self, index: usize, target: &mut [u8]) {
+        let index = index & index_mask!(N);
+        let len = target.len();
+        if let Some(source) = self.buffer.get(index..index + len) {
+            target.copy_from_slice(source);
+        }
+        else {
+            let (head, tail) = target.split_at_mut(N - index);
+            head.copy_from_slice(&self.buffer[index..]);
+            tail.copy_from_slice(&self.buffer[..tail.len()]);
+        }
+    }
+
+    /// Fills the `target` with bytes starting at `index` and pushes them to the buffer.
+    #[inline]
+    fn copy_from_index(&mut self, index: usize, target: &mut [u8]) {
+        // the distance between the source and the cursor
+        let distance = match self.cursor.wrapping_sub(index) & index_mask!(N) {
+            0 => N,
+            distance => distance
+        };
+        let len = target.len();
+        let mut filled = len.min(distance);
+        self.read_slice(index, &mut target[..filled]);
+        // the source overlaps with the pushed bytes, so the sequence of `distance` bytes repeats
+        while filled < len {
+            let count = filled.min(len - filled);
+            target.copy_within(..count, filled);
+            filled += count;
+        }
+        self.push_slice(target);
+    }
+
+    /// Fills `output` at `index` with up to `count` bytes starting `distance` bytes back, reading
+    /// the bytes preceding `output` from the buffer. Returns the index past the copied bytes.
+    #[inline]
+    fn copy_within_at_distance(&self, distance: usize, output: &mut [u8], index: usize, count: usize) -> usize {
+        debug_assert!(distance != 0 && distance <= N);
+        let end = output.len().min(index + count);
+        let mut pos = index;
+        if distance > pos {
+            let len = (distance - pos).min(end - pos);
+            self.read_slice(self.cursor + N + pos - distance, &mut output[pos..pos + len]);
+            pos += len;
+        }
+        // the source overlaps with the copied bytes, so the sequence of `distance` bytes repeats
+        let source = pos.saturating_sub(distance);
+        while pos < end {
+            let len = (pos - source).min(end - pos);
+            output.copy_within(source..source + len, pos);
+            pos += len;
+        }
+        end
+    }
+
+    /// Fills the span of `len` bytes past the cursor at `index` with up to `count` bytes starting
+    /// `distance` bytes back. Returns the index past the copied bytes.
+    #[inline]
+    fn copy_ahead_at_distance(&mut self, distance: usize, len: usize, index: usize, count: usize) -> usize {
+        debug_assert!(distance != 0 && distance <= N && self.cursor + len <= N);
+        let end = self.cursor + len.min(index + count);
+        let mut pos = self.cursor + index;
+        let mut source = (pos + N - distance) & index_mask!(N);
+        // the source preceding the target repeats every `distance` bytes, the source following
+        // the target is the oldest history not overwritten yet
+        while pos < end {
+            let count = (end - pos).min(distance).min(N - source);
+            self.buffer.copy_within(source..source + count, pos);
+            pos += count;
+            source = (source + count) & index_mask!(N);
+        }
+        end - self.cursor
+    }
+}
+
 impl<const N: usize> RingBuffer for RingArrayBuf<N> {
     const BUFFER_SIZE: usize = N;
+    const NEW: Self = Self::new();
+
+    fn reset(&mut self) {
+        self.buffer.fill(b' ');
+        self.cursor = 0;
+    }
 
     #[inline(always)]
     fn cursor(&self) -> usize {
@@ -102,15 +293,89 @@ impl<const N: usize> RingBuffer for RingArrayBuf<N> {
         self.cursor = (index + 1) & index_mask!(N);
     }
 
-    fn iter_from_offset(&mut self, offset: usize) -> HistoryIter<'_, Self> {
+    #[inline]
+    fn push_slice(&mut self, mut bytes: &[u8]) {
+        if bytes.len() > N {
+            // only the last N bytes are retained
+            self.cursor = (self.cursor + bytes.len() - N) & index_mask!(N);
+            bytes = &bytes[bytes.len() - N..];
+        }
+        let cursor = self.cursor;
+        let len = bytes.len();
+        if let Some(target) = self.buffer.get_mut(cursor..cursor + len) {
+            target.copy_from_slice(bytes);
+        }
+        else {
+            let (head, tail) = bytes.split_at(N - cursor);
+            self.buffer[cursor..].copy_from_slice(head);
+            self.buffer[..tail.len()].copy_from_slice(tail);
+        }
+        self.cursor = (cursor + len) & index_mask!(N);
+    }
+
+    #[inline]
+    fn copy_from_offset(&mut self, offset: usize, target: &mut [u8]) {
         let offset = (offset & index_mask!(N)) + 1;
         let index = self.cursor + N - offset;
-        HistoryIter { index, ringbuf: self }
+        self.copy_from_index(index, target)
+    }
+
+    #[inline]
+    fn copy_from_pos(&mut self, pos: usize, target: &mut [u8]) {
+        self.copy_from_index(pos, target)
+    }
+
+    #[inline]
+    fn copy_within_from_offset(&self, offset: usize, output: &mut [u8], index: usize, count: usize) -> usize {
+        let distance = (offset & index_mask!(N)) + 1;
+        self.copy_within_at_distance(distance, output, index, count)
+    }
+
+    #[inline]
+    fn copy_within_from_pos(&self, pos: usize, output: &mut [u8], index: usize, count: usize) -> usize {
+        let distance = match (self.cursor + index).wrapping_sub(pos) & index_mask!(N) {
+            0 => N,
+            distance => distance
+        };
+        self.copy_within_at_distance(distance, output, index, count)
+    }
+
+    #[inline(always)]
+    fn span_capacity(&self) -> usize {
+        N - self.cursor
+    }
+
+    fn recent(&self, len: usize) -> &[u8] {
+        let end = match self.cursor {
+            0 => N,
+            cursor => cursor
+        };
+        &self.buffer[end - len..end]
+    }
+
+    fn advance(&mut self, len: usize) {
+        debug_assert!(len <= N - self.cursor);
+        self.cursor = (self.cursor + len) & index_mask!(N);
+    }
+
+    #[inline(always)]
+    fn set_ahead(&mut self, index: usize, byte: u8) {
+        self.buffer[self.cursor + index] = byte;
     }
 
-    fn iter_from_pos(&mut self, pos: usize) -> HistoryIter<'_, Self> {
-        let index = pos & index_mask!(N);
-        HistoryIter { index, ringbuf: self }
+    #[inline]
+    fn copy_ahead_from_offset(&mut self, offset: usize, len: usize, index: usize, count: usize) -> usize {
+        let distance = (offset & index_mask!(N)) + 1;
+        self.copy_ahead_at_distance(distance, len, index, count)
+    }
+
+    #[inline]
+    fn copy_ahead_from_pos(&mut self, pos: usize, len: usize, index: usize, count: usize) -> usize {
+        let distance = match (self.cursor + index).wrapping_sub(pos) & index_mask!(N) {
+            0 => N,
+            distance => distance
+        };
+        self.copy_ahead_at_distance(distance, len, index, count)
     }
 }
 
@@ -148,17 +413,153 @@ mod tests {
             }
             assert_eq!(buffer.cursor(), i);
             for _ in 0..2 {
-                let vec: Vec<_> = buffer.iter_from_offset(31).take(32).collect();
+                let mut vec = vec![0; 32];
+                buffer.copy_from_offset(31, &mut vec);
                 assert_eq!(buffer.cursor(), i);
                 assert_eq!(vec, (0..32).collect::<Vec<u8>>());
             }
-            let mut hist = buffer.iter_from_offset(15);
-            let mut vec = Vec::new();
-            vec.extend(hist.by_ref().take(5));
+            let mut vec = vec![0; 5];
+            buffer.copy_from_offset(15, &mut vec);
             assert_eq!(vec, (16..16+5).collect::<Vec<u8>>());
-            let mut hist = buffer.iter_from_offset(15).take(11);
-            vec.extend(hist.by_ref());
+            vec.resize(16, 0);
+            buffer.copy_from_offset(15, &mut vec[5..]);
             assert_eq!(vec, (16..32).collect::<Vec<u8>>());
+            let mut vec = vec![0; 7];
+            buffer.copy_from_offset(2, &mut vec);
+            assert_eq!(vec, [29, 30, 31, 29, 30, 31, 29]);
+            buffer.copy_from_pos((i + 3) % 32, &mut vec);
+            assert_eq!(vec, [19, 20, 21, 22, 23, 24, 25]);
+            assert_eq!(buffer.cursor(), (i + 30) % 32);
+            buffer.reset();
+            assert_eq!(buffer.cursor(), 0);
+            assert_eq!(buffer.buffer, TestRingBuffer::NEW.buffer);
+        }
+    }
+
+    #[test]
+    fn ringbuf_copy_works() {
+        use rand::{Rng, thread_rng};
+        // the reference implementation copying byte by byte
+        fn copy_bytes(buffer: &mut TestRingBuffer, index: usize, target: &mut [u8]) {
+            for (i, t) in target.iter_mut().enumerate() {
+                *t = buffer[index + i];
+                buffer.push(*t);
+            }
+        }
+        let mut rng = thread_rng();
+        let mut buffer = TestRingBuffer::default();
+        let mut expected = TestRingBuffer::default();
+        for _ in 0..10000 {
+            let len = rng.gen_range(0..80);
+            let mut target = vec![0; len];
+            let mut target_expected = vec![0; len];
+            match rng.gen_range(0..3) {
+                0 => {
+                    rng.fill(&mut target[..]);
+                    target_expected.copy_from_slice(&target);
+                    buffer.push_slice(&target);
+                    for &byte in target.iter() {
+                        expected.push(byte);
+                    }
+                }
+                1 => {
+                    let offset = rng.gen_range(0..40);
+                    buffer.copy_from_offset(offset, &mut target);
+                    let index = expected.cursor() + 64 - (offset % 32) - 1;
+                    copy_bytes(&mut expected, index, &mut target_expected);
+                }
+                _ => {
+                    let pos = rng.gen_range(0..40);
+                    buffer.copy_from_pos(pos, &mut target);
+                    copy_bytes(&mut expected, pos, &mut target_expected);
+                }
+            }
+            assert_eq!(target, target_expected);
+            assert_eq!(buffer.cursor(), expected.cursor());
+            assert_eq!(buffer.buffer, expected.buffer);
+        }
+    }
+
+    #[test]
+    fn ringbuf_copy_within_works() {
+        use rand::{Rng, thread_rng};
+        let mut rng = thread_rng();
+        let mut buffer = TestRingBuffer::default();
+        let mut expected = TestRingBuffer::default();
+        for _ in 0..2000 {
+            let len = rng.gen_range(0..100);
+            let mut output = vec![0; len];
+            let mut output_expected = vec![0; len];
+            let mut index = 0;
+            while index < len {
+                let count = rng.gen_range(1..40);
+                let end = len.min(index + count);
+                match rng.gen_range(0..3) {
+                    0 => {
+                        rng.fill(&mut output[index..end]);
+                        output_expected[index..end].copy_from_slice(&output[index..end]);
+                        expected.push_slice(&output[index..end]);
+                        index = end;
+                    }
+                    1 => {
+                        let offset = rng.gen_range(0..40);
+                        expected.copy_from_offset(offset, &mut output_expected[index..end]);
+                        index = buffer.copy_within_from_offset(offset, &mut output, index, count);
+                    }
+                    _ => {
+                        let pos = rng.gen_range(0..40);
+                        expected.copy_from_pos(pos, &mut output_expected[index..end]);
+                        index = buffer.copy_within_from_pos(pos, &mut output, index, count);
+                    }
+                }
+                assert_eq!(index, end);
+            }
+            assert_eq!(output, output_expected);
+            buffer.push_slice(&output);
+            assert_eq!(buffer.cursor(), expected.cursor());
+            assert_eq!(buffer.buffer, expected.buffer);
+        }
+    }
+
+    #[test]
+    fn ringbuf_copy_ahead_works() {
+        use rand::{Rng, thread_rng};
+        let mut rng = thread_rng();
+        let mut buffer = TestRingBuffer::default();
+        let mut expected = TestRingBuffer::default();
+        for _ in 0..2000 {
+            let len = rng.gen_range(0..=buffer.span_capacity());
+            let mut output_expected = vec![0; len];
+            let mut index = 0;
+            while index < len {
+                let count = rng.gen_range(1..40);
+                let end = len.min(index + count);
+                match rng.gen_range(0..3) {
+                    0 => {
+                        for (i, out) in output_expected[index..end].iter_mut().enumerate() {
+                            *out = rng.gen();
+                            buffer.set_ahead(index + i, *out);
+                            expected.push(*out);
+                        }
+                        index = end;
+                    }
+                    1 => {
+                        let offset = rng.gen_range(0..40);
+                        expected.copy_from_offset(offset, &mut output_expected[index..end]);
+                        index = buffer.copy_ahead_from_offset(offset, len, index, count);
+                    }
+                    _ => {
+                        let pos = rng.gen_range(0..40);
+                        expected.copy_from_pos(pos, &mut output_expected[index..end]);
+                        index = buffer.copy_ahead_from_pos(pos, len, index, count);
+                    }
+                }
+                assert_eq!(index, end);
+            }
+            buffer.advance(len);
+            assert_eq!(buffer.recent(len), output_expected);
+            assert_eq!(buffer.cursor(), expected.cursor());
+            assert_eq!(buffer.buffer, expected.buffer);
         }
     }
 }
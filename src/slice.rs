@@ -0,0 +1,191 @@
+/*! # Archives in memory.
+
+[`LhaSliceArchive`] parses headers in place over a byte slice, e.g. a memory mapped file or an
+archive embedded in the program's image. The parsed [`LhaSliceEntry`] borrows its metadata from
+the input and provides the compressed file data as a sub-slice, so no memory is being allocated.
+
+The offset of each entry can be remembered and used later to access the entry directly with
+[`LhaSliceArchive::entry_at`].
+
+```
+use delharc::LhaSliceArchive;
+# fn list(data: &[u8]) -> delharc::LhaResult<(), &[u8]> {
+let archive = LhaSliceArchive::new(data);
+for entry in archive.entries() {
+    let entry = entry?;
+    println!("{:?} at {}: {} bytes", entry.header.filename, entry.offset, entry.data.len());
+}
+# Ok(())
+# }
+```
+*/
+use crate::error::{LhaError, LhaResult};
+use crate::header::LhaHeaderRef;
+use crate::limits::Limits;
+use crate::stub_io::Read;
+#[cfg(feature = "alloc")]
+use crate::decode::{Decoder, DecoderAny, DecoderRegistry, UnsupportedDecoder, WorkspacePool};
+
+/// An LHA archive in memory.
+#[derive(Debug, Clone, Copy)]
+pub struct LhaSliceArchive<'a> {
+    data: &'a [u8],
+    limits: Limits
+}
+
+/// An archive entry parsed in place by [`LhaSliceArchive`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LhaSliceEntry<'a> {
+    /// The entry's header.
+    pub header: LhaHeaderRef<'a>,
+    /// The offset of the entry's header from the beginning of the archive.
+    pub offset: usize,
+    /// The offset of the compressed file data from the beginning of the archive.
+    pub data_offset: usize,
+    /// The compressed file data.
+    pub data: &'a [u8],
+}
+
+/// An iterator over the entries of [`LhaSliceArchive`].
+///
+/// The iterator ends at the end of archive marker, at the end of data or after yielding an error.
+#[derive(Debug, Clone)]
+pub struct LhaSliceEntries<'a> {
+    archive: LhaSliceArchive<'a>,
+    offset: Option<usize>
+}
+
+impl<'a> LhaSliceArchive<'a> {
+    /// Create a new archive from the given `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::with_limits(data, Limits::UNLIMITED)
+    }
+    /// Create a new archive from the given `data`, enforcing the header related `limits`
+    /// when parsing entries, see [`LhaHeaderRef::read_with_limits`].
+    pub fn with_limits(data: &'a [u8], limits: Limits) -> Self {
+        LhaSliceArchive { data, limits }
+    }
+    /// Return the archive data.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+    /// Return the limits in effect.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+    /// Return an iterator over the archive entries.
+    pub fn entries(&self) -> LhaSliceEntries<'a> {
+        LhaSliceEntries { archive: *self, offset: Some(0) }
+    }
+    /// Parse the entry with the header at the given `offset` from the beginning of the archive.
+    ///
+    /// Return `Ok(None)` if the end of archive marker or the end of data was encountered.
+    ///
+    /// # Errors
+    /// Returns an error if a malformed header was encountered or if the header or the compressed
+    /// file data exceed the archive data.
+    pub fn entry_at(&self, offset: usize) -> LhaResult<Option<LhaSliceEntry<'a>>, &'a [u8]> {
+        let mut data = self.data.get(offset..).ok_or_else(unexpected_eof)?;
+        let header = match LhaHeaderRef::read_with_limits(&mut data, &self.limits)? {
+            Some(header) => header,
+            None => return Ok(None)
+        };
+        let data_offset = self.data.len() - data.len();
+        let data = usize::try_from(header.compressed_size).ok()
+                   .and_then(|size| data.get(..size))
+                   .ok_or_else(unexpected_eof)?;
+        Ok(Some(LhaSliceEntry { header, offset, data_offset, data }))
+    }
+}
+
+impl<'a> LhaSliceEntry<'a> {
+    /// Return the offset of the next entry's header from the beginning of the archive.
+    pub fn next_offset(&self) -> usize {
+        self.data_offset + self.data.len()
+    }
+    /// Return a decoder of the compressed file data.
+    ///
+    /// The decoded data should be verified against the [`LhaHeaderRef::file_crc`] checksum.
+    #[cfg(feature = "alloc")]
+    pub fn decoder(&self) -> DecoderAny<&'a [u8]> {
+        self.decoder_in(&mut WorkspacePool::default(), &DecoderRegistry::default())
+    }
+    /// Return a decoder of the compressed file data, created by the factory from the `registry` if one
+    /// is registered for the entry's compression method, otherwise taking the workspace from the `pool`.
+    ///
+    /// Return the workspace to the pool with [`DecoderAny::into_inner_in`].
+    #[cfg(feature = "alloc")]
+    pub fn decoder_in(
+            &self,
+            pool: &mut WorkspacePool,
+            registry: &DecoderRegistry<&'a [u8]>
+        ) -> DecoderAny<&'a [u8]>
+    {
+        if let Some(factory) = registry.get(&self.header.compression) {
+            return DecoderAny::BoxedDecoder(factory.new_decoder(&self.header.to_header(), self.data))
+        }
+        match self.header.compression_method() {
+            Ok(compression) => DecoderAny::new_from_compression_in(compression, self.data, pool),
+            Err(..) => DecoderAny::UnsupportedDecoder(UnsupportedDecoder::new(self.data))
+        }
+    }
+    /// Decode the whole compressed file data into `out` and verify the checksum.
+    ///
+    /// See [`decode_to_slice`][crate::decode::decode_to_slice].
+    ///
+    /// # Errors
+    /// Returns an error if the length of `out` is not the original size of the file, if the data
+    /// could not be decoded or if the computed CRC-16 checksum doesn't match the one in the header.
+    #[cfg(feature = "alloc")]
+    pub fn decode_to_slice(&self, out: &mut [u8]) -> LhaResult<(), &'a [u8]> {
+        self.decode_to_slice_in(out, &mut WorkspacePool::default(), &DecoderRegistry::default())
+    }
+    /// Decode the whole compressed file data into `out` with the decoder returned by
+    /// [`LhaSliceEntry::decoder_in`] and verify the checksum. The decoder's workspace is returned
+    /// to the `pool` afterwards.
+    ///
+    /// # Errors
+    /// Returns an error if the length of `out` is not the original size of the file, if the data
+    /// could not be decoded or if the computed CRC-16 checksum doesn't match the one in the header.
+    #[cfg(feature = "alloc")]
+    pub fn decode_to_slice_in(
+            &self,
+            out: &mut [u8],
+            pool: &mut WorkspacePool,
+            registry: &DecoderRegistry<&'a [u8]>
+        ) -> LhaResult<(), &'a [u8]>
+    {
+        if out.len() as u64 != self.header.original_size {
+            return Err(LhaError::Decompress("output size mismatch"))
+        }
+        let mut decoder = self.decoder_in(pool, registry);
+        let res = decoder.fill_buffer(out);
+        decoder.into_inner_in(pool);
+        res?;
+        let mut crc = crate::crc::Crc16::default();
+        crc.digest(out);
+        if crc.sum16() != self.header.file_crc {
+            return Err(LhaError::Checksum("crc16 mismatch"))
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for LhaSliceEntries<'a> {
+    type Item = LhaResult<LhaSliceEntry<'a>, &'a [u8]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset.take()?;
+        let res = self.archive.entry_at(offset).transpose()?;
+        if let Ok(entry) = &res {
+            self.offset = Some(entry.next_offset());
+        }
+        Some(res)
+    }
+}
+
+impl core::iter::FusedIterator for LhaSliceEntries<'_> {}
+
+fn unexpected_eof<'a>() -> LhaError<<&'a [u8] as Read>::Error> {
+    LhaError::Io(<&[u8] as Read>::unexpected_eof())
+}
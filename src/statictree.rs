@@ -44,36 +44,88 @@ When reading, the following bit paths will result in finding the particular leav
 11110 -> h
 11111 -> i
 ```
+
+## Lookup table
+
+To avoid walking the tree one bit at a time, the tree is also indexed with a lookup table of up to
+`1 << TABLE_BITS` entries. The table is indexed with the next `TABLE_BITS` bits of the path, peeked
+from the bit reader. Each table entry holds the node reached after following the path and the number
+of bits actually needed to reach it. Leaves at depths lower than `TABLE_BITS` occupy multiple table
+entries. If the node found in the table is a branch, the remaining path is followed through the tree.
 */
 use core::cmp::Ordering;
 use core::fmt;
 use crate::error::LhaError;
-use crate::bitstream::BitRead;
-#[cfg(not(feature = "std"))]
-use alloc::{vec::Vec, string::String};
+use crate::bitstream::{BitRead, MAX_PEEK_BITS};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::String;
 
 pub mod entry;
 use entry::*;
 
-/// A static Huffman tree.
-#[derive(Debug, Clone)]
-pub struct HuffTree {
-    tree: Vec<TreeEntry>
+/// The maximum number of path bits resolved with a single table lookup.
+const TABLE_BITS: u32 = 10;
+const TABLE_SIZE: usize = 1 << TABLE_BITS;
+
+const _: () = assert!(TABLE_BITS <= MAX_PEEK_BITS);
+
+/// A static Huffman tree with the capacity of `N` nodes.
+///
+/// A complete tree with `n` leaves consists of `2 * n - 1` nodes.
+#[derive(Clone)]
+pub struct HuffTree<const N: usize> {
+    tree: [TreeEntry; N],
+    tree_len: usize,
+    table: [TableEntry; TABLE_SIZE],
+    table_bits: u32
+}
+
+/// The lookup table entry: the node reached after following `length` bits of the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TableEntry {
+    node: TreeEntry,
+    length: u8
+}
+
+impl TableEntry {
+    const EMPTY: TableEntry = TableEntry { node: TreeEntry::leaf(0), length: 0 };
+}
+
+impl<const N: usize> fmt::Debug for HuffTree<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HuffTree")
+         .field("tree", &&self.tree[..self.tree_len])
+         .field("table", &&self.table[..1 << self.table_bits])
+         .field("table_bits", &self.table_bits)
+         .finish()
+    }
+}
+
+impl<const N: usize> Default for HuffTree<N> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl HuffTree {
-    /// Creates a new and empty `HuffTree` with the reserved node capacity.
+impl<const N: usize> HuffTree<N> {
+    /// Creates a new and empty `HuffTree`.
     ///
-    /// Any attempt to read from a new tree will result in panic.
-    pub fn with_capacity(capacity: usize) -> Self {
-        let tree = Vec::with_capacity(capacity);
-        HuffTree { tree }
+    /// Reading from a new tree will always result in `0`, without reading any position bits.
+    pub const fn new() -> Self {
+        assert!(N > 0 && N <= TreeEntry::MAX_INDEX + 1, "invalid HuffTree capacity");
+        HuffTree {
+            tree: [TreeEntry::leaf(0); N],
+            tree_len: 1,
+            table: [TableEntry::EMPTY; TABLE_SIZE],
+            table_bits: 0
+        }
     }
     /// Initializes a `HuffTree` in such a way that any attept to read from it will always
     /// result in the given value, without even reading any position bits.
     pub fn set_single(&mut self, value: u16) {
-        self.tree.clear();
-        self.tree.push(TreeEntry::leaf(value));
+        self.tree[0] = TreeEntry::leaf(value);
+        self.tree_len = 1;
+        self.build_table(0);
     }
     /// Builds the tree from the given array of lengths.
     ///
@@ -83,68 +135,94 @@ impl HuffTree {
     /// * Entries containing `0` are being ignored.
     /// * If too many entries contain the same `length`, exceeding the given `length` capacity, an error
     ///   is being returned.
-    /// * If the size of the argument slice is larger than or equal to the [TreeEntry::MAX_INDEX] / 2,
-    ///   an error is being returned.
-    /// * If the number of created nodes would exceed [TreeEntry::MAX_INDEX], an error is being returned.
+    /// * If the size of the argument slice is larger than `N / 2`, an error is being returned.
+    /// * If the number of created nodes would exceed `N`, an error is being returned.
     /// * An error is returned if a built tree is incomplete.
+    ///
+    /// On error the tree is left in the state of [HuffTree::set_single] with `0`.
     pub fn build_tree(&mut self, value_lengths: &[u8]) -> Result<(), &'static str> {
+        let res = self.build_nodes(value_lengths);
+        if res.is_err() {
+            self.set_single(0);
+        }
+        res
+    }
+
+    fn build_nodes(&mut self, value_lengths: &[u8]) -> Result<(), &'static str> {
         // println!("({}) {:?}", value_lengths.len(), value_lengths);
-        if value_lengths.len() > TreeEntry::MAX_INDEX / 2 {
+        if value_lengths.len() > N / 2 {
             return Err("too many code lengths");
         }
         let tree = &mut self.tree;
-
-        tree.clear();
+        // the number of created tree nodes
+        let mut tree_len = 0;
         // the number of allocated tree indices
         // the tree size should be equal to the value of this variable
         let mut max_allocated: usize = 1; // start with a single (root) node
         for current_len in 1u8.. {
+            if max_allocated > N {
+                return Err("too many tree nodes");
+            }
             // add missing branches
             let max_limit = max_allocated;
-            for _ in  tree.len()..max_limit {
-                match TreeEntry::branch(max_allocated) {
-                    Ok(branch) => tree.push(branch),
-                    Err(e) => {
-                        // make sure no outstanding branch indices exist
-                        tree.clear();
-                        return Err(e);
-                    }
-                }
+            for node in tree[tree_len..max_limit].iter_mut() {
                 // for every branch node, two new child nodes are required
+                *node = TreeEntry::branch(max_allocated)?;
                 max_allocated += 2;
             }
+            tree_len = max_limit;
             // fill tree with leaves found in the lengths table at the current length
-            let more_leaves = value_lengths.iter().copied().zip(0..)
-                              .fold(false, |mut more, (len, value)| {
+            let mut more_leaves = false;
+            for (len, value) in value_lengths.iter().copied().zip(0..) {
                 match len.cmp(&current_len) {
                     Ordering::Equal => {
-                        tree.push(TreeEntry::leaf(value));
+                        if tree_len == max_allocated {
+                            return Err("too many leaves");
+                        }
+                        tree[tree_len] = TreeEntry::leaf(value);
+                        tree_len += 1;
                     }
                     Ordering::Greater => {
                         // there are more leaves to process
-                        more = true;
+                        more_leaves = true;
                     }
                     Ordering::Less => {}
                 }
-                more
-            });
-            if tree.len() > max_allocated {
-                return Err("too many leaves");
             }
             if !more_leaves {
                 break;
             }
         }
-        // println!("tree missing leaves: {}", max_allocated - tree.len());
-        if tree.len() != max_allocated {
+        // println!("tree missing leaves: {}", max_allocated - tree_len);
+        if tree_len != max_allocated {
             return Err("missing some leaves")
         }
-        // // make sure no outstanding indices exist, perhaps this should be reported as an error
-        // tree.extend(
-        //     (tree.len()..max_allocated).map(|_| TreeEntry::leaf(0) )
-        // );
+        self.tree_len = tree_len;
+        let max_len = value_lengths.iter().copied().max().unwrap_or(0);
+        self.build_table(TABLE_BITS.min(max_len.into()));
         Ok(())
     }
+
+    fn build_table(&mut self, table_bits: u32) {
+        fn fill_step(tree: &[TreeEntry], table: &mut [TableEntry], index: usize, depth: u32, prefix: usize) {
+            let node = tree[index];
+            let table_bits = table.len().trailing_zeros();
+            match node.as_type() {
+                NodeType::Branch(index) if depth < table_bits => {
+                    fill_step(tree, table, index as usize, depth + 1, prefix << 1);
+                    fill_step(tree, table, index as usize + 1, depth + 1, prefix << 1 | 1);
+                }
+                _ => {
+                    let shift = table_bits - depth;
+                    let entry = TableEntry { node, length: depth as u8 };
+                    table[prefix << shift..(prefix + 1) << shift].fill(entry);
+                }
+            }
+        }
+
+        self.table_bits = table_bits;
+        fill_step(&self.tree[..self.tree_len], &mut self.table[..1 << table_bits], 0, 0, 0);
+    }
     /// Returns the `value` of the leaf by following the bit `path` read from the given bit reader.
     ///
     /// Bits are being read from the stream until a leaf is being encountered. The `value` stored in that
@@ -156,11 +234,17 @@ impl HuffTree {
     /// If a tree has been initialized with [HuffTree::set_single] this method will always return the
     /// single `value`, without reading any bits from the stream.
     ///
-    /// # Panics
-    /// Panics if a tree has not been built or otherwise initialized as a single value tree.
+    /// The first bits of the path are resolved at once with the lookup table.
+    #[inline]
     pub fn read_entry<R: BitRead>(&self, mut path: R) -> Result<u16, LhaError<R::Error>> {
-        let tree = &self.tree;
-        let mut node = &tree[0]; // panics if tree uninitialized
+        let bits = path.peek_bits(self.table_bits)?;
+        let entry = self.table[bits];
+        path.skip_bits(entry.length.into())?;
+        if let NodeType::Leaf(code) = entry.node.as_type() {
+            return Ok(code)
+        }
+        let tree = &self.tree[..self.tree_len];
+        let mut node = &entry.node;
         loop {
             match node.as_type() {
                 NodeType::Leaf(code) => return Ok(code),
@@ -178,10 +262,11 @@ impl HuffTree {
     }
 }
 
-impl fmt::Display for HuffTree {
+#[cfg(feature = "alloc")]
+impl<const N: usize> fmt::Display for HuffTree<N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
-        fn fmt_step(tree: &Vec<TreeEntry>, index: usize, f: &mut fmt::Formatter<'_>, prefix: &mut String) -> fmt::Result {
+        fn fmt_step(tree: &[TreeEntry], index: usize, f: &mut fmt::Formatter<'_>, prefix: &mut String) -> fmt::Result {
             match tree[index].as_type() {
                 NodeType::Leaf(code) => writeln!(f, "{} -> {}", prefix, code)?,
                 NodeType::Branch(index) => {
@@ -196,11 +281,8 @@ impl fmt::Display for HuffTree {
             Ok(())
         }
 
-        if !self.tree.is_empty() {
-            let mut prefix = String::new();
-            fmt_step(&self.tree, 0, f, &mut prefix)?;
-        }
-        Ok(())
+        let mut prefix = String::new();
+        fmt_step(&self.tree[..self.tree_len], 0, f, &mut prefix)
     }
 }
 
@@ -211,10 +293,13 @@ mod tests {
     use std::collections::{HashSet, HashMap};
     use super::*;
 
-    fn validate_tree(tree: &HuffTree, num_leaves: usize) {
+    type TestHuffTree = HuffTree<64>;
+
+    fn validate_tree(tree: &TestHuffTree, num_leaves: usize) {
         let mut leaves: HashMap<u16, usize> = HashMap::with_capacity(num_leaves);
-        let mut children: HashSet<u16> = HashSet::with_capacity(tree.tree.len());
-        for (index, node) in tree.tree.iter().enumerate() {
+        let nodes = &tree.tree[..tree.tree_len];
+        let mut children: HashSet<u16> = HashSet::with_capacity(nodes.len());
+        for (index, node) in nodes.iter().enumerate() {
             match node.as_type() {
                 NodeType::Leaf(value) => {
                     // all leaves should be unique
@@ -224,7 +309,7 @@ mod tests {
                     // invalid (default) node should not be present
                     assert!(child_index != 0);
                     // child_index should not exceed the tree length
-                    assert!((child_index as usize) < tree.tree.len() - 1);
+                    assert!((child_index as usize) < nodes.len() - 1);
                     // all child indexes should be odd
                     assert!(child_index & 1 == 1);
                     // there must be no duplicate parents of the same children
@@ -247,14 +332,16 @@ mod tests {
             }
         }
         let mut leaves: HashSet<u16> = HashSet::with_capacity(num_leaves);
-        into_branch(&tree.tree, 0, &mut leaves);
+        into_branch(nodes, 0, &mut leaves);
         assert_eq!(leaves.len(), num_leaves);
     }
 
     #[test]
     fn hufftree_works() {
-        let mut tree = HuffTree::with_capacity(0);
+        let mut tree = TestHuffTree::new();
         println!("{}", tree);
+        let path = BitStream::new([].as_ref());
+        assert_eq!(tree.read_entry(path).unwrap(), 0);
         tree.set_single(42);
         validate_tree(&tree, 1);
         let path = BitStream::new([].as_ref());
@@ -268,7 +355,7 @@ mod tests {
         tree.build_tree(&[0, 0, 0, 1, 0, 3, 3, 0, 4, 4, 5, 0, 0, 5, 5, 5]).unwrap();
         println!("{}", tree);
         validate_tree(&tree, 9);
-        assert_eq!(tree.tree.len(), 9 + 8);
+        assert_eq!(tree.tree_len, 9 + 8);
         let bits: &[u8] = &[0b01001011, 0b10011011, 0b11001110, 0b11111011, 0b11100000];
         let mut path = BitStream::new(bits);
         let mut res = Vec::new();
@@ -279,5 +366,26 @@ mod tests {
 
         assert!(tree.build_tree(&[0, 1, 0, 1, 1]).is_err());
         assert!(tree.build_tree(&[0, 1, 0, 1, 10]).is_err());
+        // a failed tree is reset to a single 0
+        let path = BitStream::new([].as_ref());
+        assert_eq!(tree.read_entry(path).unwrap(), 0);
+        // exceeds the capacity
+        assert_eq!(tree.build_tree(&[0; 33]), Err("too many code lengths"));
+        assert_eq!(tree.build_tree(&[7; 32]), Err("too many tree nodes"));
+
+        // codes longer than TABLE_BITS
+        let lengths: Vec<u8> = (1..=14).chain([14]).collect();
+        tree.build_tree(&lengths).unwrap();
+        validate_tree(&tree, lengths.len());
+        assert_eq!(tree.table_bits, TABLE_BITS);
+        let bits: &[u8] = &[0b01011111, 0b11111110, 0b11111111, 0b11111111, 0b11111111, 0b11101111];
+        let mut path = BitStream::new(bits);
+        let mut res = Vec::new();
+        for _ in 0..5 {
+            res.push(tree.read_entry(&mut path).unwrap());
+        }
+        assert_eq!(res, [0, 1, 12, 14, 13]);
+        // the path ends prematurely
+        assert!(tree.read_entry(&mut path).is_err());
     }
 }
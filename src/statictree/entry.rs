@@ -12,9 +12,12 @@ pub enum NodeType {
 
 impl TreeEntry {
     pub const MAX_INDEX: usize = LEAF_BIT as usize - 1;
+    /// A branch pointing to the root node.
+    #[allow(dead_code)]
+    pub const ROOT_BRANCH: TreeEntry = TreeEntry(0);
 
     #[inline]
-    pub fn leaf(value: u16) -> TreeEntry {
+    pub const fn leaf(value: u16) -> TreeEntry {
         TreeEntry(value | LEAF_BIT)
     }
 
@@ -1,7 +1,5 @@
 //! Proxy `I/O` tools.
-use core::cmp;
-#[cfg(not(feature = "std"))]
-use core::fmt;
+use core::{cmp, fmt};
 #[cfg(feature = "std")]
 use std::io;
 
@@ -49,6 +47,21 @@ pub trait Read {
     }
 }
 
+/// A buffered counterpart of [`Read`], bridging the `std` and `no_std` version of this library.
+///
+/// With `std` feature enabled this trait is implemented for all types that implement
+/// [`io::BufRead`] and should be ignored by user code in favour of [`io::BufRead`].
+///
+/// Without `std` this trait is implemented for `&[u8]` and [`crate::LhaDecodeReader`], giving
+/// access to the decompressed content without copying it to another buffer.
+pub trait BufRead: Read {
+    /// Exactly like [`io::BufRead::fill_buf`].
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error>;
+    /// Exactly like [`io::BufRead::consume`].
+    fn consume(&mut self, amt: usize);
+}
+
+#[cfg(feature = "alloc")]
 pub(crate) fn discard_to_end<R: Read, const BUF: usize>(rd: &mut R) -> Result<(), R::Error> {
     use core::mem::{self, MaybeUninit};
     assert!(BUF != 0);
@@ -91,6 +104,11 @@ impl<R> Take<R> {
     pub fn get_mut(&mut self) -> &mut R {
         &mut self.inner
     }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn set_limit(&mut self, limit: u64) {
+        self.limit = limit;
+    }
 }
 
 impl<R: Read> Read for Take<R> {
@@ -126,6 +144,107 @@ impl<R: Read> Read for Take<R> {
     }
 }
 
+/// The size of the read-ahead buffer used by decoders.
+#[cfg(feature = "std")]
+pub(crate) const READ_AHEAD_SIZE: usize = 1024;
+/// The size of the read-ahead buffer used by decoders.
+#[cfg(not(feature = "std"))]
+pub(crate) const READ_AHEAD_SIZE: usize = 128;
+
+/// An owned read-ahead buffer over a stream reader, used internally by decoders to reduce the number
+/// of reads from the underlying stream.
+///
+/// The buffer is always filled as much as the underlying reader provides, so when wrapped over a reader
+/// limited with [`Take`], e.g. to the compressed size of an archived file, it never consumes more
+/// than the limit.
+///
+/// # Why every decoder owns its buffer
+///
+/// Reading ahead is split between the layers on purpose, instead of keeping a single buffer below
+/// [`Take`] shared with the header parser:
+///
+/// * The buffer must sit above [`Take`]. A buffer below it would consume the stream past the end of
+///   the compressed data, so the next header, the seeking in [`crate::HeaderIter`] and the reader
+///   returned by `into_inner` would all have to account for it, and the decoders' inner reader type
+///   `Take<R>`, which is public, would change.
+/// * The header parser doesn't buffer ahead of the header. It reads the header in as few calls as the
+///   declared header length allows, directly into the storage of the parsed header, and never past
+///   the header's end.
+/// * Only decoders reading the compressed data a few bits or bytes at a time, i.e. those built on
+///   [`crate::bitstream::BitStream`] and the `-lz5-` decoder, wrap their reader in `ReadAhead`.
+///   [`crate::decode::PassthroughDecoder`] reads directly into the caller's buffer, which is already
+///   as large as the requested data, so a buffer would only add a copy.
+///
+/// The cost is [`READ_AHEAD_SIZE`] bytes held inline in each of these decoders and therefore in
+/// [`crate::decode::DecoderAny`], so no allocation is needed when a decoder is created for an entry.
+/// The buffer is discarded together with the decoder at the end of an entry, when the data left in it
+/// would be skipped anyway.
+pub(crate) struct ReadAhead<R> {
+    inner: R,
+    pos: usize,
+    len: usize,
+    buf: [u8; READ_AHEAD_SIZE]
+}
+
+impl<R> ReadAhead<R> {
+    pub fn new(inner: R) -> Self {
+        ReadAhead { inner, pos: 0, len: 0, buf: [0; READ_AHEAD_SIZE] }
+    }
+    /// Unwraps the inner reader. Any data left in the buffer is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for ReadAhead<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadAhead")
+         .field("inner", &self.inner)
+         .field("buffered", &(self.len - self.pos))
+         .finish()
+    }
+}
+
+impl<R: Read> ReadAhead<R> {
+    #[inline(never)]
+    fn read_all_refill(&mut self, buf: &mut [u8]) -> Result<usize, R::Error> {
+        let buffered = self.len - self.pos;
+        let (head, rest) = buf.split_at_mut(buffered);
+        head.copy_from_slice(&self.buf[self.pos..self.len]);
+        self.pos = 0;
+        self.len = 0;
+        if rest.len() >= READ_AHEAD_SIZE {
+            return Ok(buffered + self.inner.read_all(rest)?)
+        }
+        self.len = self.inner.read_all(&mut self.buf)?;
+        let amt = rest.len().min(self.len);
+        rest[..amt].copy_from_slice(&self.buf[..amt]);
+        self.pos = amt;
+        Ok(buffered + amt)
+    }
+}
+
+impl<R: Read> Read for ReadAhead<R> {
+    type Error = R::Error;
+
+    #[inline]
+    fn unexpected_eof() -> Self::Error {
+        R::unexpected_eof()
+    }
+
+    #[inline]
+    fn read_all(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        match self.buf[self.pos..self.len].get(..buf.len()) {
+            Some(data) => {
+                buf.copy_from_slice(data);
+                self.pos += buf.len();
+                Ok(buf.len())
+            }
+            None => self.read_all_refill(buf)
+        }
+    }
+}
+
 #[cfg(feature = "std")]
 impl<R: io::Read> Read for R {
     type Error = io::Error;
@@ -152,6 +271,17 @@ impl<R: io::Read> Read for R {
     }
 }
 
+#[cfg(feature = "std")]
+impl<R: io::BufRead> BufRead for R {
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        io::BufRead::fill_buf(self)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        io::BufRead::consume(self, amt)
+    }
+}
+
 /// An error when reading from slice without `std`.
 #[cfg(not(feature = "std"))]
 #[derive(Debug)]
@@ -175,6 +305,17 @@ impl<R: Read + ?Sized> Read for &mut R {
 }
 
 #[cfg(not(feature = "std"))]
+impl<R: BufRead + ?Sized> BufRead for &mut R {
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        R::fill_buf(*self)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        R::consume(*self, amt)
+    }
+}
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 impl<R: Read + ?Sized> Read for alloc::boxed::Box<R> {
     type Error = R::Error;
 
@@ -191,6 +332,17 @@ impl<R: Read + ?Sized> Read for alloc::boxed::Box<R> {
     }
 }
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+impl<R: BufRead + ?Sized> BufRead for alloc::boxed::Box<R> {
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        R::fill_buf(self)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        R::consume(self, amt)
+    }
+}
+
 #[cfg(not(feature = "std"))]
 impl fmt::Display for UnexpectedEofError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -234,3 +386,16 @@ impl Read for &'_[u8] {
         Ok(())
     }
 }
+
+#[cfg(not(feature = "std"))]
+impl BufRead for &'_[u8] {
+    #[inline]
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        Ok(*self)
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        *self = &self[amt.min(self.len())..];
+    }
+}
@@ -0,0 +1,369 @@
+/*! # Writing and rewriting **LHA** archives.
+
+[`LhaWriter`] writes archive entries consisting of [`LhaHeader`]s followed by the already compressed
+content of files and terminates the archive with the end of archive marker.
+
+[`LhaRewriter`] copies entries from a source archive to a new one, allowing to delete entries,
+modify their headers (e.g. rename files or edit metadata) and append new entries, without
+decompressing the content of the files. Entries can also be recompressed with one of the methods
+supported by the [`encode`][crate::encode] module, see [`LhaRewriter::transcode`].
+
+# `no_std`
+This module is only available with `std` feature enabled.
+*/
+use std::io::{self, Read, Write};
+use crate::crc::Crc16;
+use crate::decode::{Decoder, DecoderAny};
+use crate::encode::EncoderAny;
+use crate::error::LhaError;
+use crate::header::{CompressionMethod, LhaHeader, LhaHeaderBuilder};
+use crate::stub_io::{self, discard_to_end};
+
+/// Writes entries of the **LHA** archive to the underlying writer.
+///
+/// Call [`LhaWriter::finish`] after all entries have been written to append the end of archive marker.
+#[derive(Debug)]
+pub struct LhaWriter<W> {
+    inner: W
+}
+
+/// Rewrites the **LHA** archive from the source stream to the underlying writer entry by entry.
+///
+/// The compressed content of files is copied verbatim. The headers of entries that are kept
+/// unmodified are copied verbatim as well.
+///
+/// For each entry of the source archive, available via [`LhaRewriter::header`], call one of:
+/// [`LhaRewriter::keep`], [`LhaRewriter::keep_with_header`], [`LhaRewriter::rename`] or
+/// [`LhaRewriter::delete`]. New entries can be inserted at any time with [`LhaRewriter::append`].
+/// Finally call [`LhaRewriter::finish`], which copies the remaining entries and appends the end
+/// of archive marker.
+///
+/// ```no_run
+/// use std::{fs::File, io};
+/// use delharc::write::LhaRewriter;
+///
+/// # fn main() -> io::Result<()> {
+/// let source = io::BufReader::new(File::open("archive.lzh")?);
+/// let target = io::BufWriter::new(File::create("new_archive.lzh")?);
+/// let mut rewriter = LhaRewriter::new(source, target)?;
+/// while let Some(header) = rewriter.header() {
+///     match header.parse_pathname_to_str().as_str() {
+///         "obsolete.txt" => rewriter.delete()?,
+///         "readme" => rewriter.rename("README.TXT")?,
+///         _ => rewriter.keep()?
+///     }
+/// }
+/// rewriter.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LhaRewriter<R, W> {
+    rd: R,
+    writer: LhaWriter<W>,
+    header: Option<LhaHeader>,
+    raw_header: Vec<u8>
+}
+
+/// Records bytes read by the header parser.
+struct RecordingReader<'a, R> {
+    inner: &'a mut R,
+    record: &'a mut Vec<u8>
+}
+
+impl<R: Read> Read for RecordingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.record.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+}
+
+impl<W: Write> LhaWriter<W> {
+    /// Create a new instance of `LhaWriter<W>` from the given writer.
+    pub fn new(inner: W) -> Self {
+        LhaWriter { inner }
+    }
+    /// Serialize and write the header.
+    ///
+    /// The header must be followed by exactly [`LhaHeader::compressed_size`] bytes of the
+    /// compressed file's content.
+    ///
+    /// # Errors
+    /// Return an error if the header can't be serialized or from the underlying writer.
+    pub fn write_header(&mut self, header: &LhaHeader) -> io::Result<()> {
+        let bytes = header.to_bytes()?;
+        self.inner.write_all(&bytes)
+    }
+    /// Write the header followed by the compressed file's content read from `payload`.
+    ///
+    /// Exactly [`LhaHeader::compressed_size`] bytes are copied from `payload`.
+    ///
+    /// # Errors
+    /// Return an error if the header can't be serialized, the payload is too short or from
+    /// the underlying reader or writer.
+    pub fn write_entry<P: Read>(&mut self, header: &LhaHeader, payload: P) -> io::Result<()> {
+        self.write_header(header)?;
+        self.copy_payload(payload, header.compressed_size)
+    }
+    /// Write the end of archive marker and return the underlying writer.
+    ///
+    /// # Errors
+    /// Return an error from the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(&[0])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+    /// Return a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+    /// Return a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    fn copy_payload<P: Read>(&mut self, payload: P, size: u64) -> io::Result<()> {
+        if io::copy(&mut payload.take(size), &mut self.inner)? != size {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read, W: Write> LhaRewriter<R, W> {
+    /// Create a new instance of `LhaRewriter<R, W>` and read the first header from the source stream.
+    ///
+    /// # Errors
+    /// Return an error if the header could not be read or parsed.
+    pub fn new(rd: R, wr: W) -> io::Result<Self> {
+        let mut rewriter = LhaRewriter {
+            rd,
+            writer: LhaWriter::new(wr),
+            header: None,
+            raw_header: Vec::new()
+        };
+        rewriter.read_header()?;
+        Ok(rewriter)
+    }
+    /// Return the header of the current source archive entry or `None` if there are no more entries.
+    pub fn header(&self) -> Option<&LhaHeader> {
+        self.header.as_ref()
+    }
+    /// Copy the current entry verbatim and proceed to the next one.
+    ///
+    /// # Errors
+    /// Return an error if there are no more entries, from the underlying reader or writer
+    /// or if the next header could not be parsed.
+    pub fn keep(&mut self) -> io::Result<()> {
+        let size = self.current()?.compressed_size;
+        self.writer.inner.write_all(&self.raw_header)?;
+        self.writer.copy_payload(&mut self.rd, size)?;
+        self.read_header()
+    }
+    /// Copy the current entry with its header replaced by the given `header` and proceed to the next one.
+    ///
+    /// # Errors
+    /// Return an error if the `compressed_size` of the new header differs from the current one,
+    /// if the header can't be serialized, if there are no more entries, from the underlying reader or
+    /// writer or if the next header could not be parsed.
+    pub fn keep_with_header(&mut self, header: &LhaHeader) -> io::Result<()> {
+        let size = self.current()?.compressed_size;
+        if header.compressed_size != size {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "compressed size mismatch"))
+        }
+        self.writer.write_header(header)?;
+        self.writer.copy_payload(&mut self.rd, size)?;
+        self.read_header()
+    }
+    /// Copy the current entry with the path name changed to the given `path` and proceed to the next one.
+    ///
+    /// The path components should be separated by `/` or `\` characters. The header level is preserved.
+    ///
+    /// # Errors
+    /// See [`LhaRewriter::keep_with_header`].
+    pub fn rename<P: AsRef<[u8]>>(&mut self, path: P) -> io::Result<()> {
+        let header = LhaHeaderBuilder::from_header(self.current()?).path(path).build()?;
+        self.keep_with_header(&header)
+    }
+    /// Copy the current entry with its header converted to the given header `level` and proceed
+    /// to the next one.
+    ///
+    /// The header is converted with [`LhaHeaderBuilder::from_header`]. If the header is already
+    /// at the requested level, the entry is copied verbatim.
+    ///
+    /// # Errors
+    /// Return an error if the header can't be represented in the requested level.
+    /// See also [`LhaRewriter::keep_with_header`].
+    pub fn keep_with_level(&mut self, level: u8) -> io::Result<()> {
+        let header = self.current()?;
+        if header.level == level {
+            return self.keep()
+        }
+        let header = LhaHeaderBuilder::from_header(header).level(level).build()?;
+        self.keep_with_header(&header)
+    }
+    /// Recompress the current entry with the given compression `method` and proceed to the next one.
+    ///
+    /// The content of the file is decoded, verified against the CRC-16 checksum found in the header,
+    /// encoded with the new method, and verified again by decoding the encoded data. The header is
+    /// rebuilt with [`LhaHeaderBuilder::from_header`] with the compression method and the compressed
+    /// size updated, so the size extra header is regenerated as well.
+    ///
+    /// Directory entries are copied verbatim.
+    ///
+    /// The whole compressed content of the file is buffered in memory before being written.
+    ///
+    /// # Errors
+    /// Return an error if the current entry's compression method is not supported by the decoder,
+    /// if the target `method` is not supported by [`EncoderAny`], if the file's content is corrupted,
+    /// if there are no more entries, from the underlying reader or writer or if the next header could
+    /// not be parsed. If the error occurs while decoding, the source stream might be left in the middle
+    /// of the entry.
+    pub fn transcode(&mut self, method: CompressionMethod) -> io::Result<()> {
+        let header = self.current()?.clone();
+        if header.is_directory() {
+            return self.keep()
+        }
+        let mut encoder = EncoderAny::new_from_compression(method, Vec::new()).ok_or_else(||
+            io::Error::new(io::ErrorKind::InvalidInput, "unsupported target compression method"))?;
+        let mut decoder = DecoderAny::new_from_header(&header, &mut self.rd);
+        if !decoder.is_supported() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "unsupported compression method"))
+        }
+        let crc = decode_content(&mut decoder, header.original_size, &mut encoder)?;
+        discard_to_end::<_, {8*1024}>(&mut decoder.into_inner())?;
+        if crc != header.file_crc {
+            return Err(LhaError::Checksum("crc16 mismatch").into())
+        }
+        let payload = encoder.finish()?;
+        let mut decoder = DecoderAny::new_from_compression(method, &payload[..]);
+        if decode_content(&mut decoder, header.original_size, &mut io::sink())? != crc {
+            return Err(LhaError::Checksum("crc16 mismatch after encoding").into())
+        }
+        let header = LhaHeaderBuilder::from_header(&header)
+                     .compression(method)
+                     .sizes(payload.len() as u64, header.original_size)
+                     .build()?;
+        self.writer.write_entry(&header, &payload[..])?;
+        self.read_header()
+    }
+    /// Skip the current entry and proceed to the next one.
+    ///
+    /// # Errors
+    /// Return an error if there are no more entries, from the underlying reader
+    /// or if the next header could not be parsed.
+    pub fn delete(&mut self) -> io::Result<()> {
+        let size = self.current()?.compressed_size;
+        if io::copy(&mut self.rd.by_ref().take(size), &mut io::sink())? != size {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
+        self.read_header()
+    }
+    /// Write a new entry before the current one, see [`LhaWriter::write_entry`].
+    pub fn append<P: Read>(&mut self, header: &LhaHeader, payload: P) -> io::Result<()> {
+        self.writer.write_entry(header, payload)
+    }
+    /// Copy all the remaining entries verbatim, write the end of archive marker and return
+    /// the source reader and the underlying writer.
+    ///
+    /// # Errors
+    /// Return an error from the underlying reader or writer or if a header could not be parsed.
+    pub fn finish(mut self) -> io::Result<(R, W)> {
+        while self.header.is_some() {
+            self.keep()?;
+        }
+        let wr = self.writer.finish()?;
+        Ok((self.rd, wr))
+    }
+
+    fn current(&self) -> io::Result<&LhaHeader> {
+        self.header.as_ref().ok_or_else(||
+            io::Error::new(io::ErrorKind::InvalidInput, "no more entries"))
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        self.raw_header.clear();
+        let mut rd = RecordingReader { inner: &mut self.rd, record: &mut self.raw_header };
+        self.header = LhaHeader::read(&mut rd)?;
+        Ok(())
+    }
+}
+
+/// Rewrite the whole archive from `rd` to `wr` recompressing entries stored with the legacy
+/// `-lh1-`, `-lzs-` and `-lz5-` methods with the given compression `method`.
+///
+/// All the other entries are copied verbatim. See [`LhaRewriter::transcode`].
+///
+/// Return the source reader and the underlying writer on success.
+///
+/// # Errors
+/// Return an error if any of the legacy entries could not be decoded, e.g. because the decoder is not
+/// enabled by the crate features, if any of the headers could not be parsed or from the underlying reader
+/// or writer.
+pub fn transcode_legacy<R: Read, W: Write>(rd: R, wr: W, method: CompressionMethod) -> io::Result<(R, W)> {
+    let mut rewriter = LhaRewriter::new(rd, wr)?;
+    while let Some(header) = rewriter.header() {
+        match header.compression_method() {
+            Ok(CompressionMethod::Lh1|CompressionMethod::Lzs|CompressionMethod::Lz5) => {
+                rewriter.transcode(method)?
+            }
+            _ => rewriter.keep()?
+        }
+    }
+    rewriter.finish()
+}
+
+/// Rewrite the whole archive from `rd` to `wr` converting all headers to the given header `level`.
+///
+/// The compressed content of files is copied verbatim, see [`LhaRewriter::keep_with_level`].
+///
+/// Converting level 0 and 1 headers to level 2 or 3 carries over:
+/// * the file name and the directory path,
+/// * the comment, including the one embedded in the file name by [`OsType::Amiga`][crate::OsType::Amiga],
+/// * MS-DOS attributes and the OS type,
+/// * Unix permissions, owner and group ids, including those found in the level 0 extended area,
+/// * all extra headers of level 1 not regenerated by the builder.
+///
+/// The MS-DOS timestamps of level 0 and 1 headers have no time zone and are stored as if they were
+/// in the UTC time zone, so [`TimestampResult::to_naive_utc`][crate::TimestampResult::to_naive_utc]
+/// of the converted header returns the original date and time.
+///
+/// The extended area of level 0 and 1 headers written for [`OsType::Unix`][crate::OsType::Unix],
+/// [`OsType::Os9`][crate::OsType::Os9] and [`OsType::Osk`][crate::OsType::Osk] is converted to the
+/// equivalent extra headers, see [`LhaHeaderBuilder::from_header`]. Any other non-empty extended area
+/// can't be stored in level 2 and 3 headers, so the conversion fails.
+///
+/// Return the source reader and the underlying writer on success.
+///
+/// # Errors
+/// Return an error if any of the headers could not be parsed or converted or from the underlying reader
+/// or writer.
+pub fn convert_level<R: Read, W: Write>(rd: R, wr: W, level: u8) -> io::Result<(R, W)> {
+    let mut rewriter = LhaRewriter::new(rd, wr)?;
+    while rewriter.header().is_some() {
+        rewriter.keep_with_level(level)?;
+    }
+    rewriter.finish()
+}
+
+// decodes `size` bytes into `wr` and returns the CRC-16 checksum of the decoded data
+fn decode_content<R: stub_io::Read<Error=io::Error>, W: Write>(
+        decoder: &mut DecoderAny<R>,
+        size: u64,
+        wr: &mut W
+    ) -> io::Result<u16>
+{
+    let mut crc = Crc16::default();
+    let mut buf = [0u8; 8*1024];
+    let mut remaining = size;
+    while remaining != 0 {
+        let chunk = &mut buf[..remaining.min(8*1024) as usize];
+        decoder.fill_buffer(chunk)?;
+        crc.digest(chunk);
+        wr.write_all(chunk)?;
+        remaining -= chunk.len() as u64;
+    }
+    Ok(crc.sum16())
+}
@@ -0,0 +1,244 @@
+use std::{cell::Cell, env, fs, io::{self, Read}, path::PathBuf, rc::Rc, time::SystemTime};
+use delharc::crc::Crc16;
+use delharc::decode::{BoxedDecoder, Decoder, DecoderRegistry};
+use delharc::header::{CompressionMethod, LhaHeaderBuilder};
+use delharc::{Archive, EntryKind, LhaError, LhaHeader, LhaWriter, Limits};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("delharc-archive-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn stored_archive(files: &[(&str, &[u8])]) -> io::Result<Vec<u8>> {
+    let mut writer = LhaWriter::new(Vec::new());
+    for (path, content) in files {
+        let mut crc = Crc16::default();
+        crc.digest(content);
+        let header = LhaHeaderBuilder::new()
+            .path(path)
+            .compression(CompressionMethod::Lh0)
+            .sizes(content.len() as u64, content.len() as u64)
+            .file_crc(crc.sum16())
+            .build().unwrap();
+        writer.write_entry(&header, *content)?;
+    }
+    writer.finish()
+}
+
+#[test]
+fn test_archive_entries() -> io::Result<()> {
+    for name in ["tests/lha_unix114i/h2_subdir.lzh", "tests/lha_unix114i/h1_symlink.lzh",
+                 "tests/lha213/lh5_long.lzh", "tests/regression/multiple.lzh"]
+    {
+        let mut expected = delharc::parse_file(name)?;
+        let mut archive = Archive::new(fs::File::open(name)?);
+        let mut count = 0;
+        let mut more = true;
+        for entry in archive.entries() {
+            assert!(more);
+            let mut entry = entry?;
+            let header = expected.header();
+            assert_eq!(entry.header().as_header_ref(), header.as_header_ref());
+            let manifest = entry.manifest();
+            assert_eq!(entry.path().to_str().unwrap(), manifest.path);
+            assert_eq!(entry.link_target(), manifest.link_target.as_deref());
+            assert_eq!(entry.size(), header.original_size);
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            match entry.kind() {
+                EntryKind::File => {
+                    assert!(!header.is_directory());
+                    let mut data = Vec::new();
+                    expected.read_to_end(&mut data)?;
+                    assert_eq!(content, data, "{}", name);
+                }
+                EntryKind::Directory => assert!(header.is_directory() && manifest.link_target.is_none()),
+                EntryKind::Symlink => assert!(header.is_directory() && manifest.link_target.is_some()),
+                _ => unreachable!()
+            }
+            count += 1;
+            more = expected.next_file()?;
+        }
+        assert!(!more);
+        assert!(archive.entries().next().is_none());
+        assert!(count > 0);
+    }
+    // an empty archive
+    assert!(Archive::new(&[0u8][..]).entries().next().is_none());
+    assert!(Archive::new(&[][..]).entries().next().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_archive_entry_skip() -> io::Result<()> {
+    let data = stored_archive(&[("one", b"first file"), ("two", b"second file"), ("three", b"")])?;
+    let mut archive = Archive::new(&data[..]);
+    let mut entries = archive.entries();
+    let mut one = entries.next().unwrap()?;
+    let mut buf = [0u8; 5];
+    one.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"first");
+    // the rest of the first entry is skipped
+    let mut two = entries.next().unwrap()?;
+    assert_eq!(two.path().to_str(), Some("two"));
+    assert_eq!(one.read(&mut buf).unwrap_err().kind(), io::ErrorKind::Other);
+    let mut content = String::new();
+    two.read_to_string(&mut content)?;
+    assert_eq!(content, "second file");
+    drop(two);
+    let mut three = entries.next().unwrap()?;
+    assert_eq!(three.read(&mut buf)?, 0);
+    assert!(entries.next().is_none());
+    // limits
+    let limits = Limits { max_entries: 2, ..Limits::default() };
+    let mut archive = Archive::with_limits(&data[..], limits);
+    let entries = archive.entries().collect::<Vec<_>>();
+    assert_eq!(entries.len(), 3);
+    assert!(entries[2].as_ref().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_archive_entry_checksum() -> io::Result<()> {
+    let mut data = stored_archive(&[("file", b"checked content")])?;
+    let pos = data.windows(7).position(|w| w == b"content").unwrap();
+    data[pos] ^= 1;
+    let mut archive = Archive::new(&data[..]);
+    let mut entry = archive.entries().next().unwrap()?;
+    let mut content = Vec::new();
+    assert_eq!(entry.read_to_end(&mut content).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(content.len(), 15);
+    Ok(())
+}
+
+#[derive(Debug)]
+struct StoredDecoder<R> {
+    inner: R
+}
+
+impl<R: delharc::stub_io::Read<Error=io::Error>> Decoder<R> for StoredDecoder<R> {
+    type Error = io::Error;
+
+    fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> Result<(), LhaError<Self::Error>> {
+        self.inner.read_exact(buf).map_err(LhaError::Io)
+    }
+}
+
+#[test]
+fn test_archive_decoder_registry() -> io::Result<()> {
+    let data = stored_archive(&[("one", b"first"), ("two", b"second")])?;
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    let mut archive = Archive::new(io::Cursor::new(data.clone()));
+    assert!(!archive.register_decoder(*b"-lh0-", move |_: &LhaHeader, rd| -> BoxedDecoder<_> {
+        counter.set(counter.get() + 1);
+        Box::new(StoredDecoder { inner: rd })
+    }));
+    let mut contents = Vec::new();
+    for entry in archive.entries() {
+        let mut content = String::new();
+        entry?.read_to_string(&mut content)?;
+        contents.push(content);
+    }
+    assert_eq!(contents, ["first", "second"]);
+    assert_eq!(calls.get(), 2);
+    // the registry given to the constructor is used from the first entry
+    let mut registry = DecoderRegistry::new();
+    let counter = calls.clone();
+    registry.register(*b"-lh0-", move |_: &LhaHeader, rd| -> BoxedDecoder<_> {
+        counter.set(counter.get() + 1);
+        Box::new(StoredDecoder { inner: rd })
+    });
+    let mut archive = Archive::with_limits_and_registry(io::Cursor::new(data), Limits::UNLIMITED, registry);
+    assert_eq!(archive.entries().count(), 2);
+    assert_eq!(calls.get(), 4);
+    Ok(())
+}
+
+#[test]
+fn test_archive_unpack() -> io::Result<()> {
+    let dir = temp_dir("unpack");
+    let mut archive = Archive::new(fs::File::open("tests/lha_unix114i/h2_subdir.lzh")?);
+    archive.unpack(&dir)?;
+    let mut expected = delharc::parse_file("tests/lha_unix114i/h2_subdir.lzh")?;
+    let mut files = 0;
+    loop {
+        let path = dir.join(expected.header().parse_pathname());
+        if expected.header().is_directory() {
+            assert!(path.is_dir());
+        }
+        else {
+            let mut content = Vec::new();
+            expected.read_to_end(&mut content)?;
+            assert_eq!(fs::read(&path)?, content);
+            let modified = expected.header().parse_last_modified().to_local().map(SystemTime::from);
+            assert_eq!(fs::metadata(&path)?.modified().ok(), modified);
+            files += 1;
+        }
+        if !expected.next_file()? {
+            break
+        }
+    }
+    assert_eq!(files, 1);
+    fs::remove_dir_all(&dir)?;
+    // unsafe paths are kept inside the directory
+    let dir = temp_dir("unsafe");
+    let data = stored_archive(&[("../escaped", b"outside"), ("/absolute", b"inside"), ("sub/file", b"")])?;
+    let mut archive = Archive::new(&data[..]);
+    let mut unpacked = Vec::new();
+    for entry in archive.entries() {
+        unpacked.push(entry?.unpack_in(&dir)?);
+    }
+    assert_eq!(unpacked, [true, true, true]);
+    assert_eq!(fs::read(dir.join("escaped"))?, b"outside");
+    assert_eq!(fs::read(dir.join("absolute"))?, b"inside");
+    assert_eq!(fs::read(dir.join("sub").join("file"))?, b"");
+    assert!(!dir.parent().unwrap().join("escaped").exists());
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_archive_sanitize_path() {
+    use delharc::archive::sanitize_path;
+    assert_eq!(sanitize_path("/abs/./path"), Some(PathBuf::from("abs/path")));
+    assert_eq!(sanitize_path("dir/../../etc"), None);
+    assert_eq!(sanitize_path("/"), Some(PathBuf::new()));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_archive_traverses_symlink() -> io::Result<()> {
+    use delharc::archive::traverses_symlink;
+    let dir = temp_dir("traverses");
+    fs::create_dir_all(dir.join("sub"))?;
+    std::os::unix::fs::symlink("sub", dir.join("link"))?;
+    assert!(!traverses_symlink(&dir, "sub/file".as_ref()));
+    assert!(!traverses_symlink(&dir, "link".as_ref()));
+    assert!(traverses_symlink(&dir, "link/file".as_ref()));
+    fs::remove_dir_all(&dir)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_archive_unpack_symlink() -> io::Result<()> {
+    let dir = temp_dir("symlink");
+    Archive::new(fs::File::open("tests/lha_unix114i/h1_symlink.lzh")?).unpack(&dir)?;
+    let mut archive = Archive::new(fs::File::open("tests/lha_unix114i/h1_symlink.lzh")?);
+    let mut links = 0;
+    for entry in archive.entries() {
+        let entry = entry?;
+        if let Some(target) = entry.link_target() {
+            assert_eq!(fs::read_link(dir.join(entry.path()))?.to_str(), Some(target));
+            links += 1;
+        }
+    }
+    assert!(links > 0);
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
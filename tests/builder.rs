@@ -0,0 +1,201 @@
+use std::{io, fs};
+use chrono::prelude::*;
+use delharc::header::*;
+
+// archives with headers that can't be reproduced exactly
+const QUIRKS: &[&str] = &[
+    "tests/lha_osk_201", // the level 2 header length doesn't include the length field
+    "tests/lha_os9_211c/h2_", // level 2 headers are padded
+    "subdir.lzh", // the MS-DOS attributes byte is ignored if the attributes header is present
+];
+
+fn read_headers(path: &str) -> io::Result<Vec<(Vec<u8>, LhaHeader)>> {
+    let data = fs::read(path)?;
+    let mut headers = Vec::new();
+    let mut offset = 0;
+    loop {
+        let mut rd = &data[offset..];
+        let header = match LhaHeader::read(&mut rd)? {
+            Some(header) => header,
+            None => break
+        };
+        let end = data.len() - rd.len();
+        let next = end + header.compressed_size as usize;
+        headers.push((data[offset..end].to_vec(), header));
+        if next >= data.len() {
+            break
+        }
+        offset = next;
+    }
+    Ok(headers)
+}
+
+fn archives(dir: &str) -> io::Result<Vec<String>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str())
+               .map_or(false, |ext| ext.eq_ignore_ascii_case("lzh")) {
+            paths.push(path.to_str().unwrap().replace('\\', "/"));
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[test]
+fn test_header_reserialize() -> io::Result<()> {
+    let mut count = 0;
+    for dir in ["tests/lha213", "tests/lha255e", "tests/lha_amiga_122", "tests/lha_amiga_212",
+                "tests/lha_os2_208", "tests/lha_os9_211c", "tests/lha_osk_201", "tests/lha_unix114i",
+                "tests/lha_x68k_213", "tests/lharc113", "tests/lhmelt_16536", "tests/unlha32"]
+    {
+        for path in archives(dir)? {
+            println!("-------------\n{:?}", path);
+            let is_quirk = QUIRKS.iter().any(|q| path.contains(q));
+            for (raw, header) in read_headers(&path)? {
+                let bytes = header.to_bytes()?;
+                if !is_quirk {
+                    assert_eq!(bytes, raw);
+                    count += 1;
+                }
+                let parsed = LhaHeader::read(&mut &bytes[..])?.unwrap();
+                assert_eq!(parsed.parse_pathname_to_str(), header.parse_pathname_to_str());
+                assert_eq!(parsed.extra_headers, header.extra_headers);
+            }
+        }
+    }
+    assert_eq!(count, 72);
+    Ok(())
+}
+
+#[test]
+fn test_header_level_conversion() -> io::Result<()> {
+    for dir in ["tests/lha213", "tests/lha_amiga_212", "tests/lha_os2_208", "tests/lha_os9_211c",
+                "tests/lha_unix114i", "tests/lhmelt_16536", "tests/unlha32"]
+    {
+        for path in archives(dir)? {
+            println!("-------------\n{:?}", path);
+            for (_, header) in read_headers(&path)? {
+                for level in 0..=3 {
+                    let converted = match LhaHeaderBuilder::from_header(&header).level(level).build() {
+                        Ok(converted) => converted,
+                        Err(HeaderEncodeError("timestamp out of MS-DOS range")) if level == 0 => continue,
+                        Err(e) => panic!("{}", e)
+                    };
+                    let bytes = converted.to_bytes()?;
+                    let parsed = LhaHeader::read(&mut &bytes[..])?.unwrap();
+                    assert_eq!(parsed.level, level);
+                    assert_eq!(parsed.parse_pathname_to_str(), header.parse_pathname_to_str());
+                    assert_eq!(parsed.compression, header.compression);
+                    assert_eq!(parsed.compressed_size, header.compressed_size);
+                    assert_eq!(parsed.original_size, header.original_size);
+                    assert_eq!(parsed.file_crc, header.file_crc);
+                    assert_eq!(parsed.os_type, header.os_type);
+                    assert_eq!(parsed.msdos_attrs.bits() as u8, header.msdos_attrs.bits() as u8);
+                    if level != 0 {
+                        assert_eq!(parsed.parse_comment(), header.parse_comment());
+                    }
+                    let (ts0, ts1) = (header.parse_last_modified(), parsed.parse_last_modified());
+                    if level == 0 && ts0.is_utc() && ts1.is_naive() {
+                        // MS-DOS format has the resolution of 2 seconds
+                        let ts0 = ts0.to_naive_utc().unwrap();
+                        assert_eq!(ts1.to_naive_utc().unwrap(), ts0.with_second(ts0.second() & !1).unwrap());
+                    }
+                    else {
+                        assert_eq!(ts1.to_naive_utc(), ts0.to_naive_utc());
+                    }
+                    // round trip
+                    let back = LhaHeaderBuilder::from_header(&parsed).level(header.level).build()?;
+                    assert_eq!(back.parse_pathname_to_str(), header.parse_pathname_to_str());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_header_builder() -> io::Result<()> {
+    let modified = Utc.with_ymd_and_hms(2024, 5, 17, 12, 34, 56).unwrap();
+    let builder = LhaHeaderBuilder::new()
+        .path("dir/sub/file.txt")
+        .compression(CompressionMethod::Lh5)
+        .sizes(0x1_2345_6789, 0x2_3456_789A)
+        .file_crc(0xBEEF)
+        .last_modified(modified)
+        .msdos_attrs(MsDosAttrs::ARCHIVE|MsDosAttrs::READ_ONLY)
+        .os_type(OsType::Unix)
+        .unix_permissions(0o100640)
+        .unix_uid_gid(1000, 100)
+        .comment("a comment")
+        .extra_header(0x99, [1, 2, 3]);
+    for level in 2..=3 {
+        let bytes = builder.clone().level(level).build()?.to_bytes()?;
+        let mut rd = &bytes[..];
+        let header = LhaHeader::read(&mut rd)?.unwrap();
+        assert!(rd.is_empty());
+        assert_eq!(header.level, level);
+        assert_eq!(header.parse_pathname_to_str(), "dir/sub/file.txt");
+        assert_eq!(header.compression_method().unwrap(), CompressionMethod::Lh5);
+        assert_eq!(header.compressed_size, 0x1_2345_6789);
+        assert_eq!(header.original_size, 0x2_3456_789A);
+        assert_eq!(header.file_crc, 0xBEEF);
+        assert_eq!(header.parse_last_modified(), modified.into());
+        assert_eq!(header.msdos_attrs, MsDosAttrs::ARCHIVE|MsDosAttrs::READ_ONLY);
+        assert_eq!(header.parse_os_type()?, OsType::Unix);
+        assert_eq!(header.parse_comment().unwrap(), "a comment");
+        let extra: Vec<_> = header.iter_extra().map(|h| h[0]).collect();
+        assert_eq!(extra, [0x00, 0x01, 0x02, 0x3F, 0x42, 0x50, 0x51, 0x99]);
+        assert!(header.iter_extra().any(|h| h == [0x50, 0xA0, 0x81]));
+        assert!(header.iter_extra().any(|h| h == [0x51, 100, 0, 0xE8, 0x03]));
+        assert!(header.iter_extra().any(|h| h == [0x99, 1, 2, 3]));
+    }
+    for level in 0..=1 {
+        assert_eq!(builder.clone().level(level).build().unwrap_err(),
+                   HeaderEncodeError("file size too large for header level"));
+    }
+    let builder = builder.sizes(100, 200);
+    let header = builder.clone().level(1).build()?;
+    let bytes = header.to_bytes()?;
+    let header = LhaHeader::read(&mut &bytes[..])?.unwrap();
+    assert_eq!(header.compressed_size, 100);
+    assert_eq!(header.parse_last_modified(), modified.into());
+    assert_eq!(&header.filename[..], b"file.txt");
+    let bytes = builder.clone().level(0).build()?.to_bytes()?;
+    let header = LhaHeader::read(&mut &bytes[..])?.unwrap();
+    assert_eq!(&header.filename[..], b"dir\\sub\\file.txt");
+    assert_eq!(header.parse_last_modified(), modified.into());
+    assert_eq!(&header.extended_area[5..11], &[0xA0, 0x81, 0xE8, 0x03, 100, 0]);
+    assert_eq!(header.parse_comment(), None);
+    // directories
+    for level in 0..=3 {
+        let bytes = LhaHeaderBuilder::new()
+            .level(level)
+            .path("dir/sub/")
+            .compression(CompressionMethod::Lhd)
+            .last_modified(NaiveDate::from_ymd_opt(2001, 2, 3).unwrap().and_hms_opt(4, 5, 6).unwrap())
+            .build()?.to_bytes()?;
+        let header = LhaHeader::read(&mut &bytes[..])?.unwrap();
+        assert!(header.is_directory());
+        assert_eq!(header.parse_pathname_to_str(), "dir/sub");
+        assert_eq!(header.parse_last_modified().to_naive_utc().unwrap().to_string(), "2001-02-03 04:05:06");
+    }
+    // level 2 header length with the low byte equal to 0 is padded
+    let bytes = LhaHeaderBuilder::new().path([b'a'; 223]).build()?.to_bytes()?;
+    assert_eq!(bytes.len(), 257);
+    let mut rd = &bytes[..];
+    let header = LhaHeader::read(&mut rd)?.unwrap();
+    assert!(rd.is_empty());
+    assert_eq!(header.parse_pathname_to_str().len(), 223);
+    // errors
+    assert_eq!(LhaHeaderBuilder::new().level(4).build().unwrap_err(),
+               HeaderEncodeError("unknown header level"));
+    assert_eq!(LhaHeaderBuilder::new().level(0).path([b'a'; 256]).build()?.to_bytes().unwrap_err(),
+               HeaderEncodeError("file name too long"));
+    assert_eq!(LhaHeaderBuilder::new().level(2)
+                .last_modified(Utc.with_ymd_and_hms(1960, 1, 1, 0, 0, 0).unwrap())
+                .build().unwrap_err(),
+               HeaderEncodeError("timestamp out of range"));
+    Ok(())
+}
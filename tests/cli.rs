@@ -0,0 +1,160 @@
+use std::{env, fs, io::{self, Read}, process::{Command, Output}};
+use std::path::{Path, PathBuf};
+
+fn delharc<I: IntoIterator<Item=S>, S: AsRef<std::ffi::OsStr>>(args: I) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_delharc")).args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("delharc-cli-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn archived_files(archive: &str) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let mut lha_reader = delharc::parse_file(archive)?;
+    loop {
+        if lha_reader.is_decoder_supported() {
+            let mut content = Vec::new();
+            lha_reader.read_to_end(&mut content)?;
+            files.push((lha_reader.header().parse_pathname_to_str(), content));
+        }
+        if !lha_reader.next_file()? {
+            break
+        }
+    }
+    Ok(files)
+}
+
+#[test]
+fn test_cli_list() {
+    let output = delharc(["l", "tests/lha_unix114i/h2_subdir.lzh"]);
+    assert!(output.status.success());
+    let lines: Vec<_> = stdout(&output).lines().map(String::from).collect();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[0], "PERMISSION  UID  GID      SIZE  RATIO     STAMP           NAME");
+    assert_eq!(lines[2], "drwx------  1000/1000        0 ****** Apr 24  2012 subdir");
+    assert_eq!(lines[3], "dr-xr-xr-x  1000/1000        0 ****** Apr 24  2012 subdir/subdir2");
+    assert_eq!(lines[4], "-rw-r--r--  1000/1000       12 100.0% Jan  1  2010 subdir/subdir2/hello.txt");
+    assert_eq!(lines[6], " Total         3 files      12 100.0% Apr 24  2012");
+    let output = delharc(["v", "tests/lha_unix114i/h2_symlink3.lzh"]);
+    assert!(output.status.success());
+    let lines: Vec<_> = stdout(&output).lines().map(String::from).collect();
+    assert_eq!(lines[2],
+        "lrwxrwxrwx  1000/1000        0       0 ****** -lhd- 0000 Feb  3  2013 subdir/symlink -> /absolute/path");
+    let output = delharc(["vq", "tests/lha_os2_208/h3_subdir.lzh", "*.TXT"]);
+    assert_eq!(stdout(&output),
+        "[OS/2]                      14      14 100.0% -lh0- 3197 Dec  3  2011 subdir/subdir2/HELLO.TXT\n");
+    let output = delharc(["lq", "tests/lha_os2_208/h3_subdir.lzh", "-x", "subdir2"]);
+    assert_eq!(stdout(&output), "[OS/2]                       0 ****** Dec  3  2011 subdir\n");
+}
+
+#[test]
+fn test_cli_test() -> io::Result<()> {
+    for archive in ["tests/lha213/lh5_long.lzh", "tests/lharc113/long.lzh", "tests/larc333/lz5.lzs",
+                    "tests/lha_unix114i/h2_subdir.lzh"]
+    {
+        let output = delharc(["t", archive]);
+        assert!(output.status.success(), "{}", archive);
+        for (path, _) in archived_files(archive)? {
+            assert!(stdout(&output).contains(&format!("{} - Tested\n", path)));
+        }
+        let output = delharc(["tq", archive]);
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+    let dir = temp_dir("test");
+    let mut data = fs::read("tests/lha213/lh5_long.lzh")?;
+    let len = data.len();
+    data[len / 2] ^= 0x55;
+    let corrupted = dir.join("corrupted.lzh");
+    fs::write(&corrupted, data)?;
+    let output = delharc([Path::new("t"), &corrupted]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("LONG.TXT - "));
+    fs::remove_dir_all(dir)
+}
+
+#[test]
+fn test_cli_extract() -> io::Result<()> {
+    let dir = temp_dir("extract");
+    for archive in ["tests/lhmelt_16536/h2_subdir.lzh", "tests/lha_amiga_122/subdir.lzh",
+                    "tests/lha_unix114i/h1_lh7.lzh"]
+    {
+        let output = delharc([Path::new("x"), Path::new(archive), Path::new("-w"), &dir]);
+        assert!(output.status.success(), "{}", archive);
+        for (path, content) in archived_files(archive)? {
+            assert_eq!(fs::read(dir.join(&path))?, content, "{}", path);
+        }
+    }
+    // overwriting
+    let archive = "tests/lha_unix114i/h1_lh7.lzh";
+    let (path, content) = archived_files(archive)?.remove(0);
+    let target = dir.join(path);
+    fs::write(&target, "junk")?;
+    let output = delharc([Path::new("xq"), Path::new(archive), Path::new("-w"), &dir]);
+    assert!(output.status.success());
+    assert_eq!(fs::read(&target)?, b"junk");
+    let mut cmd = "xfw=".to_string();
+    cmd.push_str(dir.to_str().unwrap());
+    let output = delharc([&cmd, archive]);
+    assert!(output.status.success());
+    assert_eq!(fs::read(&target)?, content);
+    // overwriting never writes through a symbolic link
+    #[cfg(unix)]
+    {
+        let outside = dir.join("outside");
+        fs::write(&outside, "junk")?;
+        fs::remove_file(&target)?;
+        std::os::unix::fs::symlink(&outside, &target)?;
+        let output = delharc([&cmd, archive]);
+        assert!(output.status.success());
+        assert!(!fs::symlink_metadata(&target)?.file_type().is_symlink());
+        assert_eq!(fs::read(&target)?, content);
+        assert_eq!(fs::read(&outside)?, b"junk");
+    }
+    // patterns and ignoring paths
+    let dir2 = temp_dir("extract2");
+    let output = delharc([Path::new("xi"), Path::new("tests/lha_os2_208/h3_subdir.lzh"),
+                          Path::new("--output"), &dir2, Path::new("*.TXT")]);
+    assert!(output.status.success());
+    let names: Vec<_> = fs::read_dir(&dir2)?.map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(names, ["HELLO.TXT"]);
+    fs::remove_dir_all(dir)?;
+    fs::remove_dir_all(dir2)
+}
+
+#[test]
+fn test_cli_print() -> io::Result<()> {
+    let archive = "tests/lha_unix114i/h1_subdir.lzh";
+    let output = delharc(["p", archive]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "::::::::\nsubdir/subdir2/hello.txt\n::::::::\nhello world\n");
+    let archive = "tests/lha213/lh5_long.lzh";
+    let output = delharc(["pq", archive, "long.txt", "LONG.TXT"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, archived_files(archive)?[0].1);
+    let output = delharc(["pq", archive, "-x", "*"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_cli_usage() {
+    assert_eq!(delharc([""; 0]).status.code(), Some(2));
+    assert_eq!(delharc(["l"]).status.code(), Some(2));
+    assert_eq!(delharc(["k", "tests/lha213/lh5_long.lzh"]).status.code(), Some(2));
+    assert_eq!(delharc(["l", "-x"]).status.code(), Some(2));
+    assert_eq!(delharc(["l", "--bogus", "tests/lha213/lh5_long.lzh"]).status.code(), Some(2));
+    assert_eq!(delharc(["l", "tests/missing.lzh"]).status.code(), Some(1));
+    let output = delharc(["-h"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("usage: delharc"));
+}
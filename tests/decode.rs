@@ -43,6 +43,476 @@ fn test_decode() {
     }
 }
 
+#[test]
+fn test_decode_with_workspace() {
+    const CRC32: u32 = 0x4e46f4a1;
+    let size = fs::metadata("tests/decode/lh0.bin").unwrap().len() as usize;
+    #[cfg(feature = "lz")]
+    let mut lzs_ws = LzsWorkspace::new();
+    #[cfg(feature = "lz")]
+    let mut lz5_ws = Lz5Workspace::new();
+    #[cfg(feature = "lh1")]
+    let mut lh1_ws = Lh1Workspace::new();
+    let mut lh5_ws = Lh5Workspace::new();
+    let mut lh7_ws = Box::new(Lh7Workspace::new());
+
+    // each workspace is being reused
+    for limit in [usize::MAX, 128, 31, 3, 2, 1].iter().copied() {
+        #[cfg(feature = "lz")]
+        {
+            let data = fs::read("tests/decode/lzs.bin").unwrap();
+            let mut decoder = LzsDecoder::with_workspace(&data[..], &mut lzs_ws);
+            test_decoder(&mut decoder, size, CRC32, limit);
+            let data = fs::read("tests/decode/lz5.bin").unwrap();
+            let mut decoder = Lz5Decoder::with_workspace(&data[..], &mut lz5_ws);
+            test_decoder(&mut decoder, size, CRC32, limit);
+        }
+        #[cfg(feature = "lh1")]
+        {
+            let data = fs::read("tests/decode/lh1.bin").unwrap();
+            let mut decoder = Lh1Decoder::with_workspace(&data[..], &mut lh1_ws);
+            test_decoder(&mut decoder, size, CRC32, limit);
+        }
+        let data = fs::read("tests/decode/lh5.bin").unwrap();
+        let mut decoder = Lh5Decoder::with_workspace(&data[..], &mut lh5_ws);
+        test_decoder(&mut decoder, size, CRC32, limit);
+        for name in ["lh6.bin", "lh7.bin"] {
+            let data = fs::read(format!("tests/decode/{}", name)).unwrap();
+            let mut decoder = Lh7Decoder::with_workspace(&data[..], lh7_ws);
+            test_decoder(&mut decoder, size, CRC32, limit);
+            let (_, ws) = decoder.into_parts();
+            lh7_ws = ws;
+        }
+    }
+}
+
+#[test]
+fn test_buf_decoder() {
+    let content = fs::read("tests/decode/lh0.bin").unwrap();
+    fn check<R, D: BufDecoder<R>>(mut decoder: D, content: &[u8], max: usize) {
+        let mut output = Vec::new();
+        let mut step = 0;
+        while output.len() < content.len() {
+            let remaining = content.len() - output.len();
+            step += 1;
+            if step % 3 == 0 {
+                // the data not consumed yet goes first
+                let mut buf = vec![0u8; remaining.min(max / 2 + 1)];
+                decoder.fill_buffer(&mut buf).unwrap();
+                output.extend_from_slice(&buf);
+            }
+            else {
+                let chunk = decoder.fill_buf(remaining.min(max)).unwrap();
+                assert!(!chunk.is_empty() && chunk.len() <= max);
+                // consume a part of the chunk only every other time
+                let chunk_len = chunk.len();
+                let len = if step % 2 == 0 { chunk_len } else { (chunk_len + 1) / 2 };
+                output.extend_from_slice(&chunk[..len]);
+                decoder.consume(len);
+                assert_eq!(decoder.buffer().len(), chunk_len - len);
+            }
+        }
+        assert_eq!(output, content);
+        assert!(decoder.buffer().is_empty());
+        assert!(decoder.fill_buf(0).unwrap().is_empty());
+    }
+    for max in [usize::MAX, 5000, 128, 31, 1] {
+        #[cfg(feature = "lz")]
+        {
+            let data = fs::read("tests/decode/lzs.bin").unwrap();
+            check(LzsDecoder::new(&data[..]), &content, max);
+            let data = fs::read("tests/decode/lz5.bin").unwrap();
+            check(Lz5Decoder::new(&data[..]), &content, max);
+        }
+        #[cfg(feature = "lh1")]
+        {
+            let data = fs::read("tests/decode/lh1.bin").unwrap();
+            check(Lh1Decoder::new(&data[..]), &content, max);
+        }
+        let data = fs::read("tests/decode/lh5.bin").unwrap();
+        check(Lh5Decoder::new(&data[..]), &content, max);
+        for name in ["lh6.bin", "lh7.bin"] {
+            let data = fs::read(format!("tests/decode/{}", name)).unwrap();
+            check(Lh7Decoder::new(&data[..]), &content, max);
+        }
+    }
+}
+
+#[test]
+fn test_decode_reader_reuses_workspaces() {
+    // concatenate archives without end markers, so the decoders of the same family follow each other
+    let names = [
+        #[cfg(feature = "lz")]
+        "tests/lzs/lzs.lzs",
+        #[cfg(feature = "lz")]
+        "tests/larc333/lz5.lzs",
+        #[cfg(feature = "lh1")]
+        "tests/lha_amiga_212/lh1.lzh",
+        "tests/lha_unix114i/h2_subdir.lzh", "tests/lha_unix114i/h1_lh6.lzh",
+        "tests/lha_unix114i/h0_lh7.lzh", "tests/lha213/lh5_long.lzh"];
+    let mut data = Vec::new();
+    let mut count = 0;
+    let mut files = 0;
+    for name in names.iter().cycle().take(names.len() * 3) {
+        let archive = fs::read(name).unwrap();
+        let mut headers = delharc::HeaderIter::new_seekable(io::Cursor::new(&archive));
+        let mut end = 0;
+        while let Some(header) = headers.next_header().unwrap() {
+            end = headers.get_ref().position() + header.compressed_size;
+            count += 1;
+            if !header.is_directory() {
+                files += 1;
+            }
+        }
+        data.extend_from_slice(&archive[..end as usize]);
+    }
+    let mut lha_reader = delharc::LhaDecodeReader::new(&data[..]).unwrap();
+    let mut decoded = 0;
+    loop {
+        if lha_reader.is_decoder_supported() {
+            io::copy(&mut lha_reader, &mut io::sink()).unwrap();
+            lha_reader.crc_check().unwrap();
+            decoded += 1;
+        }
+        count -= 1;
+        if !lha_reader.next_file().unwrap() {
+            break
+        }
+    }
+    assert_eq!(count, 0);
+    assert_eq!(decoded, files);
+}
+
+#[test]
+fn test_decode_to_slice() {
+    const CRC32: u32 = 0x4e46f4a1;
+    let size = fs::metadata("tests/decode/lh0.bin").unwrap().len() as usize;
+    for (name, compression, offset) in TESTS_CASES {
+        let data = fs::read(format!("tests/decode/{}", name)).unwrap();
+        let mut out = vec![0u8; size];
+        decode_to_slice(*compression, &data[..data.len() - *offset as usize], &mut out).unwrap();
+        let mut sink = SinkSum::new();
+        sink.write_all(&out).unwrap();
+        assert_eq!(sink.crc32.get_crc(), CRC32, "{}", name);
+        // the compressed data ends too early
+        assert!(matches!(decode_to_slice(*compression, &data[..data.len() / 2], &mut out),
+                         Err(delharc::LhaError::Io(..))));
+    }
+    assert!(matches!(decode_to_slice(CompressionMethod::Pm2, &[0u8; 10], &mut [0u8; 10]),
+                     Err(delharc::LhaError::Decompress("unsupported compression method"))));
+    // the workspaces are reused, also after an error
+    let mut pool = WorkspacePool::new();
+    for (name, compression, offset) in TESTS_CASES.iter().chain(TESTS_CASES) {
+        let data = fs::read(format!("tests/decode/{}", name)).unwrap();
+        let mut out = vec![0u8; size];
+        assert!(decode_to_slice_in(*compression, &data[..data.len() / 2], &mut out, &mut pool).is_err());
+        decode_to_slice_in(*compression, &data[..data.len() - *offset as usize], &mut out, &mut pool).unwrap();
+        let mut sink = SinkSum::new();
+        sink.write_all(&out).unwrap();
+        assert_eq!(sink.crc32.get_crc(), CRC32, "{}", name);
+    }
+}
+
+#[test]
+fn test_read_entry_to_vec_alloc_error() {
+    let data = fs::read("tests/lha213/lh5_long.lzh").unwrap();
+    let mut rd = &data[..];
+    let mut header = LhaHeader::read(&mut rd).unwrap().unwrap();
+    header.original_size = isize::MAX as u64;
+    let mut lha_reader = delharc::LhaDecodeReader::<&[u8]>::default();
+    let decoder = DecoderAny::new_from_header(&header, rd);
+    lha_reader.begin_with_header_and_decoder(header, decoder);
+    assert!(matches!(lha_reader.read_entry_to_vec(), Err(delharc::LhaError::Limit("entry original size"))));
+}
+
+#[test]
+fn test_read_entry_to_vec() {
+    for name in ["tests/lha_unix114i/h2_subdir.lzh", "tests/lha213/lh5_long.lzh",
+                 "tests/lha_amiga_212/lh1.lzh", "tests/larc333/subdir.lzs"]
+    {
+        let data = fs::read(name).unwrap();
+        let mut expected = delharc::LhaDecodeReader::new(&data[..]).unwrap();
+        let mut lha_reader = delharc::LhaDecodeReader::new(&data[..]).unwrap();
+        loop {
+            if lha_reader.is_decoder_supported() {
+                let mut buf = Vec::new();
+                io::Read::read_to_end(&mut expected, &mut buf).unwrap();
+                assert_eq!(lha_reader.read_entry_to_vec().unwrap(), buf, "{}", name);
+                assert!(lha_reader.is_empty());
+                assert_eq!(lha_reader.read_entry_to_vec().unwrap(), [], "{}", name);
+            }
+            let more = lha_reader.next_file().unwrap();
+            assert_eq!(expected.next_file().unwrap(), more);
+            if !more {
+                break
+            }
+        }
+    }
+    // the checksum is verified
+    let data = fs::read("tests/lha_unix114i/h0_lh7.lzh").unwrap();
+    let mut rest = &data[..];
+    let mut header = LhaHeader::read(&mut rest).unwrap().unwrap();
+    header.file_crc ^= 1;
+    let mut lha_reader = delharc::LhaDecodeReader::<&[u8]>::default();
+    let decoder = DecoderAny::new_from_header(&header, rest);
+    lha_reader.begin_with_header_and_decoder(header, decoder);
+    assert!(matches!(lha_reader.read_entry_to_vec(), Err(delharc::LhaError::Checksum(..))));
+}
+
+#[test]
+fn test_copy_entry_to() {
+    let path = std::env::temp_dir().join(format!("delharc-copy-entry-{}", std::process::id()));
+    for name in ["tests/lha213/lh0.lzh", "tests/larc333/lz4.lzs", "tests/lha_os2_208/h3_lh0.lzh",
+                 "tests/lha_unix114i/h2_subdir.lzh", "tests/lha213/lh5_long.lzh"]
+    {
+        let mut expected = delharc::parse_file(name).unwrap();
+        let mut lha_reader = delharc::parse_file(name).unwrap();
+        loop {
+            if expected.is_decoder_supported() {
+                let content = expected.read_entry_to_vec().unwrap();
+                // file to file
+                let mut file = fs::File::create(&path).unwrap();
+                assert_eq!(lha_reader.copy_seekable_entry_to(&mut file).unwrap(), content.len() as u64, "{}", name);
+                drop(file);
+                assert_eq!(fs::read(&path).unwrap(), content, "{}", name);
+                assert!(lha_reader.is_empty());
+                assert_eq!(lha_reader.total_output(), expected.total_output());
+                assert_eq!(lha_reader.copy_seekable_entry_to(&mut io::sink()).unwrap(), 0);
+                assert_eq!(lha_reader.copy_entry_to(&mut io::sink()).unwrap(), 0);
+            }
+            let more = lha_reader.next_file().unwrap();
+            assert_eq!(expected.next_file().unwrap(), more);
+            if !more {
+                break
+            }
+        }
+    }
+    fs::remove_file(&path).unwrap();
+    // the checksum is verified
+    let data = fs::read("tests/lha213/lh0.lzh").unwrap();
+    let mut rd = io::Cursor::new(&data[..]);
+    let mut header = LhaHeader::read(&mut rd).unwrap().unwrap();
+    header.file_crc ^= 1;
+    let mut lha_reader = delharc::LhaDecodeReader::default();
+    let decoder = DecoderAny::new_from_header(&header, rd);
+    lha_reader.begin_with_header_and_decoder(header, decoder);
+    let mut out = Vec::new();
+    assert!(matches!(lha_reader.copy_seekable_entry_to(&mut out), Err(delharc::LhaError::Checksum(..))));
+    assert_eq!(out.len() as u64, lha_reader.header().original_size);
+    // the stored content is truncated
+    let mut lha_reader = delharc::LhaDecodeReader::new(io::Cursor::new(&data[..data.len() - 100])).unwrap();
+    assert!(matches!(lha_reader.copy_seekable_entry_to(&mut io::sink()), Err(delharc::LhaError::Io(..))));
+    let mut lha_reader = delharc::LhaDecodeReader::new(&data[..data.len() - 100]).unwrap();
+    assert!(matches!(lha_reader.copy_entry_to(&mut io::sink()), Err(delharc::LhaError::Io(..))));
+    // streams don't need to be seekable and the content already buffered is copied first
+    for name in ["tests/lha213/lh0.lzh", "tests/lha213/lh5_long.lzh"] {
+        use std::io::BufRead;
+        let data = fs::read(name).unwrap();
+        let expected = delharc::LhaDecodeReader::new(&data[..]).unwrap().read_entry_to_vec().unwrap();
+        let mut lha_reader = delharc::LhaDecodeReader::new(&data[..]).unwrap();
+        let len = lha_reader.fill_buf().unwrap().len().min(10);
+        lha_reader.consume(len);
+        let mut out = expected[..len].to_vec();
+        assert_eq!(lha_reader.copy_entry_to(&mut out).unwrap(), (expected.len() - len) as u64, "{}", name);
+        assert_eq!(out, expected, "{}", name);
+        let mut lha_reader = delharc::LhaDecodeReader::new(io::Cursor::new(&data[..])).unwrap();
+        lha_reader.fill_buf().unwrap();
+        lha_reader.consume(len);
+        let mut out = expected[..len].to_vec();
+        assert_eq!(lha_reader.copy_seekable_entry_to(&mut out).unwrap(), (expected.len() - len) as u64, "{}", name);
+        assert_eq!(out, expected, "{}", name);
+    }
+}
+
+#[test]
+fn test_decode_reader_buf_read() {
+    use std::io::BufRead;
+    for name in ["tests/lha_unix114i/h1_lh5.lzh", "tests/lha213/lh0.lzh", "tests/lha213/lh5_long.lzh",
+                 "tests/regression/multiple.lzh"]
+    {
+        let mut expected = delharc::parse_file(name).unwrap();
+        let mut lha_reader = delharc::parse_file(name).unwrap();
+        for index in 0.. {
+            let supported = expected.is_decoder_supported();
+            if supported && index % 2 == 0 {
+                let content = expected.read_entry_to_vec().unwrap();
+                let mut lines = 0;
+                let mut output = Vec::new();
+                while lha_reader.read_until(b'\n', &mut output).unwrap() != 0 {
+                    lines += 1;
+                }
+                assert_eq!(output, content, "{}", name);
+                assert_eq!(lines, content.split_inclusive(|&b| b == b'\n').count(), "{}", name);
+                lha_reader.crc_check().unwrap();
+            }
+            else if supported {
+                // the buffered data is discarded when skipping to the next file
+                lha_reader.fill_buf().unwrap();
+                lha_reader.consume(1);
+            }
+            let more = lha_reader.next_file().unwrap();
+            assert_eq!(expected.next_file().unwrap(), more);
+            if !more {
+                break
+            }
+            assert_eq!(lha_reader.len(), lha_reader.header().original_size);
+        }
+    }
+}
+
+#[test]
+fn test_strict_crc() {
+    use std::io::BufRead;
+    for name in ["tests/lha213/lh0.lzh", "tests/lha213/lh5_long.lzh", "tests/lha_unix114i/h2_subdir.lzh",
+                 "tests/regression/multiple.lzh"]
+    {
+        let mut lha_reader = delharc::parse_file(name).unwrap();
+        assert!(!lha_reader.is_strict());
+        lha_reader.set_strict(true);
+        loop {
+            if lha_reader.is_decoder_supported() {
+                io::copy(&mut lha_reader, &mut io::sink()).unwrap();
+                assert_eq!(lha_reader.read(&mut [0u8; 1]).unwrap(), 0);
+                assert!(lha_reader.fill_buf().unwrap().is_empty());
+            }
+            if !lha_reader.next_file().unwrap() {
+                break
+            }
+            assert!(lha_reader.is_strict());
+        }
+    }
+    let data = fs::read("tests/lha213/lh5_long.lzh").unwrap();
+    let corrupted = |strict| {
+        let mut rd = &data[..];
+        let mut header = LhaHeader::read(&mut rd).unwrap().unwrap();
+        header.file_crc ^= 1;
+        let mut lha_reader = delharc::LhaDecodeReader::<&[u8]>::default();
+        lha_reader.set_strict(strict);
+        let decoder = DecoderAny::new_from_header(&header, rd);
+        lha_reader.begin_with_header_and_decoder(header, decoder);
+        lha_reader
+    };
+    // without the strict mode the corruption goes unnoticed
+    let mut lha_reader = corrupted(false);
+    io::copy(&mut lha_reader, &mut io::sink()).unwrap();
+    assert!(!lha_reader.crc_is_ok());
+    // the read reaching the end of the file fails
+    let mut lha_reader = corrupted(true);
+    let err = io::copy(&mut lha_reader, &mut io::sink()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(lha_reader.is_empty());
+    assert_eq!(lha_reader.read(&mut [0u8; 1]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    let mut buf = vec![0u8; lha_reader.header().original_size as usize];
+    let mut lha_reader = corrupted(true);
+    assert_eq!(lha_reader.read_exact(&mut buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    // the same with BufRead
+    let mut lha_reader = corrupted(true);
+    loop {
+        match lha_reader.fill_buf() {
+            Ok(buf) => {
+                assert!(!buf.is_empty());
+                let len = buf.len();
+                lha_reader.consume(len);
+            }
+            Err(err) => {
+                assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+                break
+            }
+        }
+    }
+    // the compressed data ends early
+    let mut lha_reader = delharc::LhaDecodeReader::new(&data[..data.len() - 10]).unwrap();
+    lha_reader.set_strict(true);
+    let err = io::copy(&mut lha_reader, &mut io::sink()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(!lha_reader.is_empty());
+    assert_eq!(lha_reader.read(&mut [0u8; 1]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[derive(Debug)]
+struct ReverseDecoder<R> {
+    inner: R,
+    buf: Vec<u8>
+}
+
+impl<R: delharc::stub_io::Read<Error=io::Error>> Decoder<R> for ReverseDecoder<R> {
+    type Error = io::Error;
+
+    fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> Result<(), delharc::LhaError<Self::Error>> {
+        if self.buf.is_empty() {
+            let mut chunk = [0u8; 1024];
+            loop {
+                match self.inner.read_all(&mut chunk).map_err(delharc::LhaError::Io)? {
+                    0 => break,
+                    n => self.buf.extend_from_slice(&chunk[..n])
+                }
+            }
+        }
+        let start = self.buf.len().checked_sub(buf.len()).ok_or(delharc::LhaError::Decompress("too much"))?;
+        buf.copy_from_slice(&self.buf[start..]);
+        buf.reverse();
+        self.buf.truncate(start);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_decoder_registry() {
+    use delharc::stub_io::Take;
+    fn reverse_decoder(_: &LhaHeader, rd: Take<fs::File>) -> BoxedDecoder<Take<fs::File>> {
+        Box::new(ReverseDecoder { inner: rd, buf: Vec::new() })
+    }
+    let expected = fs::read("tests/lha213/lh0.lzh").map(|data| {
+        let mut lha_reader = delharc::LhaDecodeReader::new(&data[..]).unwrap();
+        lha_reader.read_entry_to_vec().unwrap()
+    }).unwrap();
+    // the registered decoder replaces the built-in one
+    let mut lha_reader = delharc::LhaDecodeReader::default();
+    assert!(!lha_reader.register_decoder(*b"-lh0-", reverse_decoder));
+    assert!(lha_reader.register_decoder(*b"-lh0-", reverse_decoder));
+    assert_eq!(format!("{:?}", lha_reader.decoder_registry()), "[-lh0-]");
+    assert!(lha_reader.begin_new(fs::File::open("tests/lha213/lh0.lzh").unwrap()).unwrap());
+    assert!(lha_reader.is_decoder_supported());
+    let mut content = Vec::new();
+    lha_reader.read_to_end(&mut content).unwrap();
+    content.reverse();
+    assert_eq!(content, expected);
+    assert!(!lha_reader.crc_is_ok());
+    // the inner reader is recovered from the boxed decoder
+    assert!(!lha_reader.next_file().unwrap());
+    // the registry given to the constructor applies to the first file
+    let file = fs::File::open("tests/lha213/lh0.lzh").unwrap();
+    // factories are not required to be Send or Sync
+    let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+    let mut registry = DecoderRegistry::new();
+    let counter = calls.clone();
+    assert!(!registry.register(*b"-lh0-", move |header: &LhaHeader, rd| {
+        counter.set(counter.get() + 1);
+        reverse_decoder(header, rd)
+    }));
+    let mut lha_reader = delharc::LhaDecodeReader::with_limits_and_registry(file, Default::default(), registry).unwrap();
+    let mut content = Vec::new();
+    lha_reader.read_to_end(&mut content).unwrap();
+    content.reverse();
+    assert_eq!(content, expected);
+    assert_eq!(calls.get(), 1);
+    // DecoderAny
+    let mut rd = fs::File::open("tests/lha213/lh0.lzh").unwrap();
+    let header = LhaHeader::read(&mut rd).unwrap().unwrap();
+    let mut decoder = DecoderAny::new_from_header_with_registry(&header, rd, lha_reader.decoder_registry());
+    assert!(matches!(decoder, DecoderAny::BoxedDecoder(..)));
+    assert!(decoder.is_supported());
+    let mut content = vec![0u8; expected.len()];
+    decoder.fill_buffer(&mut content).unwrap();
+    content.reverse();
+    assert_eq!(content, expected);
+    assert_eq!(decoder.into_inner().limit(), 0);
+}
+
 fn test_decoder<R: io::Read, D: Decoder<R>>(
     decoder: &mut D,
     mut target_size: usize,
@@ -60,3 +530,55 @@ fn test_decoder<R: io::Read, D: Decoder<R>>(
     }
     assert_eq!(sink.crc32.get_crc(), crc_validate);
 }
+
+struct CountingReader<R> {
+    inner: R,
+    reads: usize
+}
+
+impl<R: io::Read> io::Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reads += 1;
+        self.inner.read(buf)
+    }
+}
+
+#[test]
+fn test_read_ahead() {
+    // headers are read in a few chunks and never past their end
+    for name in ["tests/lha_unix114i/h0_lh7.lzh", "tests/lha_unix114i/h1_lh6.lzh",
+                 "tests/lha_unix114i/h2_subdir.lzh", "tests/lha213/subdir.lzh",
+                 "tests/larc333/lz5.lzs"]
+    {
+        let data = fs::read(name).unwrap();
+        let mut rest = &data[..];
+        loop {
+            let mut rd = CountingReader { inner: rest, reads: 0 };
+            let header = LhaHeader::read(&mut rd).unwrap();
+            let expected = LhaHeaderRef::read(&mut rest).unwrap();
+            // level 1 extended headers are being read one by one
+            let extra = expected.map_or(0, |h| if h.level == 1 { h.iter_extra().count() } else { 0 });
+            assert!(rd.reads <= 3 + extra, "{}: {} reads", name, rd.reads);
+            assert_eq!(rd.inner.len(), rest.len(), "{}", name);
+            let header = match header {
+                Some(header) => header,
+                None => {
+                    assert!(expected.is_none());
+                    break
+                }
+            };
+            assert_eq!(expected.unwrap().to_header().as_header_ref(), header.as_header_ref());
+            // the compressed data is read in chunks and never past its end
+            let next = &rest[header.compressed_size as usize..];
+            let mut decoder = DecoderAny::new_from_header(&header, CountingReader { inner: rest, reads: 0 });
+            if decoder.is_supported() {
+                let mut buf = vec![0u8; header.original_size as usize];
+                decoder.fill_buffer(&mut buf).unwrap();
+                let rd = decoder.into_inner().into_inner();
+                assert!(rd.inner.len() >= next.len(), "{}", name);
+                assert!(rd.reads <= 2 + header.compressed_size as usize / 128, "{}: {} reads", name, rd.reads);
+            }
+            rest = next;
+        }
+    }
+}
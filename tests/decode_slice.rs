@@ -27,6 +27,7 @@ fn test_decode_slice() {
     let mut decoder = PassthroughDecoder::new(&file[..]);
     test_decoder(&mut decoder, size, CRC32, usize::max_value());
 
+    #[cfg(feature = "alloc")]
     for (name, file, compression, offset) in TESTS_CASES {
         println!("-------------\n{:?}", name);
         let compressed_size = file.len() as u64;
@@ -43,6 +44,133 @@ fn test_decode_slice() {
     }
 }
 
+#[test]
+fn test_decode_slice_with_workspace() {
+    const CRC32: u32 = 0x4e46f4a1;
+    let size = include_bytes!("decode/lh0.bin").len();
+    for (name, file, compression, offset) in TESTS_CASES {
+        println!("-------------\n{:?}", name);
+        let rd = (&file[..]).take(file.len() as u64 - offset);
+        match compression {
+            #[cfg(feature = "lz")]
+            CompressionMethod::Lzs => test_decoder(
+                &mut LzsDecoder::with_workspace(rd, &mut LzsWorkspace::new()), size, CRC32, 31),
+            #[cfg(feature = "lz")]
+            CompressionMethod::Lz5 => test_decoder(
+                &mut Lz5Decoder::with_workspace(rd, &mut Lz5Workspace::new()), size, CRC32, 31),
+            #[cfg(feature = "lh1")]
+            CompressionMethod::Lh1 => test_decoder(
+                &mut Lh1Decoder::with_workspace(rd, &mut Lh1Workspace::new()), size, CRC32, 31),
+            CompressionMethod::Lh5 => test_decoder(
+                &mut Lh5Decoder::with_workspace(rd, &mut Lh5Workspace::new()), size, CRC32, 31),
+            _ => test_decoder(
+                &mut Lh7Decoder::with_workspace(rd, &mut Lh7Workspace::new()), size, CRC32, 31)
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_decode_reader_buf_read() {
+    use stub_io::BufRead;
+    let data = include_bytes!("lha_unix114i/h1_lh5.lzh");
+    let mut expected = delharc::LhaDecodeReader::new(&data[..]).unwrap();
+    let size = expected.len() as usize;
+    let mut content = vec![0u8; size];
+    expected.read_exact(&mut content).unwrap();
+    // chunks consumed partially, mixed with reads
+    let mut lha_reader = delharc::LhaDecodeReader::new(&data[..]).unwrap();
+    let mut output = Vec::new();
+    while !lha_reader.is_empty() {
+        let chunk = lha_reader.fill_buf().unwrap();
+        assert!(!chunk.is_empty());
+        let amt = chunk.len().min(1000);
+        output.extend_from_slice(&chunk[..amt]);
+        lha_reader.consume(amt);
+        assert_eq!(lha_reader.len() as usize, size - output.len());
+        let mut buf = [0u8; 333];
+        let len = buf.len().min(lha_reader.len() as usize);
+        lha_reader.read_exact(&mut buf[..len]).unwrap();
+        output.extend_from_slice(&buf[..len]);
+    }
+    assert_eq!(output, content);
+    assert!(lha_reader.fill_buf().unwrap().is_empty());
+    assert!(lha_reader.crc_is_ok());
+    // the checksum includes only the consumed data
+    let mut lha_reader = delharc::LhaDecodeReader::new(&data[..]).unwrap();
+    let len = lha_reader.fill_buf().unwrap().len();
+    assert!(len > 0 && len < size);
+    lha_reader.consume(len - 1);
+    assert_eq!(lha_reader.len() as usize, size - len + 1);
+    let mut rest = vec![0u8; size - len];
+    lha_reader.read_exact(&mut rest).unwrap();
+    assert!(!lha_reader.crc_is_ok());
+    let mut last = [0u8];
+    lha_reader.read_exact(&mut last).unwrap();
+    assert!(lha_reader.crc_is_ok());
+    assert!(!lha_reader.next_file().unwrap());
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+struct XorDecoder<R> {
+    inner: R
+}
+
+#[cfg(feature = "alloc")]
+impl<R: stub_io::Read> Decoder<R> for XorDecoder<R> where R::Error: core::fmt::Debug {
+    type Error = R::Error;
+
+    fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> Result<(), delharc::LhaError<Self::Error>> {
+        self.inner.read_exact(buf).map_err(delharc::LhaError::Io)?;
+        buf.iter_mut().for_each(|b| *b ^= 0x55);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_decoder_registry() {
+    let content = include_bytes!("decode/lh0.bin");
+    let mut crc = delharc::crc::Crc16::default();
+    crc.digest(content);
+    let size = content.len() as u64;
+    let header = LhaHeaderBuilder::new()
+        .raw_compression(*b"-xor-")
+        .path("xored")
+        .sizes(size, size)
+        .file_crc(crc.sum16())
+        .build().unwrap();
+    let mut data = header.to_bytes().unwrap();
+    data.extend(content.iter().map(|b| b ^ 0x55));
+    data.push(0);
+    let data: &'static [u8] = data.leak();
+    // unknown methods are not supported by default
+    let lha_reader = delharc::LhaDecodeReader::new(data).unwrap();
+    assert!(!lha_reader.is_decoder_supported());
+    assert!(lha_reader.decoder_registry().is_empty());
+    let mut lha_reader = delharc::LhaDecodeReader::default();
+    assert!(!lha_reader.register_decoder(*b"-xor-", |_: &LhaHeader, rd| -> BoxedDecoder<_> {
+        Box::new(XorDecoder { inner: rd })
+    }));
+    assert!(lha_reader.decoder_registry().contains(b"-xor-"));
+    assert!(lha_reader.begin_new(data).unwrap());
+    assert!(lha_reader.is_decoder_supported());
+    let mut output = vec![0u8; content.len()];
+    lha_reader.read_exact(&mut output).unwrap();
+    assert_eq!(output, content);
+    assert_eq!(lha_reader.crc_check().unwrap(), crc.sum16());
+    assert!(!lha_reader.next_file().unwrap());
+    // unregistered decoders are no longer used
+    assert!(lha_reader.decoder_registry_mut().unregister(b"-xor-"));
+    assert!(lha_reader.begin_new(data).unwrap());
+    assert!(!lha_reader.is_decoder_supported());
+}
+
 fn test_decoder<R: stub_io::Read, D: Decoder<R>>(
     decoder: &mut D,
     mut target_size: usize,
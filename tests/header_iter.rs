@@ -0,0 +1,75 @@
+use std::{fs, io::{self, Cursor}};
+use delharc::{HeaderIter, LhaDecodeReader, LhaError, LhaHeader, Limits};
+
+fn decoder_headers(data: &[u8]) -> io::Result<Vec<LhaHeader>> {
+    let mut lha_reader = LhaDecodeReader::new(data)?;
+    let mut headers = Vec::new();
+    loop {
+        headers.push(lha_reader.header().clone());
+        if !lha_reader.next_file()? {
+            break
+        }
+    }
+    Ok(headers)
+}
+
+fn assert_same_headers(left: &[LhaHeader], right: &[LhaHeader]) {
+    assert_eq!(left.len(), right.len());
+    for (left, right) in left.iter().zip(right.iter()) {
+        assert_eq!(left.as_header_ref(), right.as_header_ref());
+    }
+}
+
+#[test]
+fn test_header_iter() -> io::Result<()> {
+    for name in ["tests/lha_unix114i/h2_subdir.lzh",
+                 "tests/lha_unix114i/h1_symlink.lzh",
+                 "tests/lha_amiga_212/lh1.lzh",
+                 "tests/lha213/subdir.lzh",
+                 "tests/larc333/subdir.lzs",
+                 "tests/regression/multiple.lzh"]
+    {
+        let data = fs::read(name)?;
+        let expected = decoder_headers(&data)?;
+        let headers = HeaderIter::new(&data[..]).collect::<Result<Vec<_>, _>>()?;
+        assert_same_headers(&headers, &expected);
+        let headers = HeaderIter::new_seekable(Cursor::new(&data)).collect::<Result<Vec<_>, _>>()?;
+        assert_same_headers(&headers, &expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_header_iter_position() -> io::Result<()> {
+    let data = fs::read("tests/lha_unix114i/h2_subdir.lzh")?;
+    let mut headers = HeaderIter::new_seekable(Cursor::new(&data));
+    let mut position = 0;
+    while let Some(header) = headers.next_header()? {
+        let header_len = headers.get_ref().position() - position;
+        assert!(header_len > 0);
+        position += header_len + header.compressed_size;
+    }
+    assert_eq!(headers.get_ref().position(), data.len() as u64);
+    assert!(headers.next().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_header_iter_errors() -> io::Result<()> {
+    let data = fs::read("tests/lha_unix114i/h2_subdir.lzh")?;
+    let limits = Limits { max_entries: 2, ..Limits::default() };
+    let mut headers = HeaderIter::with_limits(&data[..], limits);
+    assert!(headers.next().unwrap().is_ok());
+    assert!(headers.next().unwrap().is_ok());
+    assert!(matches!(headers.next(), Some(Err(LhaError::Limit("number of entries")))));
+    assert!(headers.next().is_none());
+    // the second header is truncated
+    let mut headers = HeaderIter::new_seekable(Cursor::new(&data));
+    let header = headers.next_header()?.unwrap();
+    let truncated_len = headers.get_ref().position() + header.compressed_size + 10;
+    let mut headers = HeaderIter::new(&data[..truncated_len as usize]);
+    assert!(headers.next().unwrap().is_ok());
+    assert!(matches!(headers.next(), Some(Err(LhaError::Io(..)))));
+    assert!(headers.next().is_none());
+    Ok(())
+}
@@ -39,9 +39,15 @@ fn test_lha_amiga_212() -> io::Result<()> {
             assert_eq!(header.file_crc, *crc16);
             if header.level == 0 {
                 assert_eq!(header.parse_os_type()?, OsType::Generic);
+                assert_eq!(header.parse_amiga_protection(), None);
             }
             else {
                 assert_eq!(header.parse_os_type()?, OsType::Amiga);
+                // ----rw-d
+                let protection = header.parse_amiga_protection().unwrap();
+                assert_eq!(protection, AmigaProtection::EXECUTE);
+                assert!(!protection.is_executable());
+                assert_eq!(protection.to_unix_permissions(), 0o644);
             }
             if *compr == CompressionMethod::Lhd {
                 assert!(io::copy(&mut lha_reader, &mut sink).is_err());
@@ -115,3 +115,43 @@ fn test_lha_os2_208() -> io::Result<()> {
     }
     Ok(())
 }
+
+const EXT_ATTRS_CASES: &[(&str, &[(u16, &str, &str)])] = &[
+    ("h3_lh0.lzh", &[(0x20, "2011-12-03 21:35:22 UTC", "2011-12-03 23:01:22 UTC")]),
+    ("h3_lh5.lzh", &[(0x20, "2011-12-03 21:29:04 UTC", "2011-12-03 23:01:50 UTC")]),
+    ("h3_lfn.lzh", &[(0x20, "2011-12-03 21:38:50 UTC", "2011-12-03 23:03:54 UTC")]),
+    ("h3_subdir.lzh", &[(0x00, "", ""),
+                        (0x00, "", ""),
+                        (0x20, "2011-12-03 21:38:50 UTC", "2011-12-03 23:01:32 UTC")]),
+];
+
+#[test]
+fn test_lha_os2_208_ext_attrs() -> io::Result<()> {
+    for name in ["lh0.lzh", "lh5.lzh", "subdir.lzh"] {
+        let lha_reader = delharc::parse_file(format!("tests/lha_os2_208/{}", name))?;
+        assert_eq!(lha_reader.header().parse_ext_attrs(), None);
+        assert!(lha_reader.header().parse_os2_eas().is_empty());
+    }
+    for (name, cases) in EXT_ATTRS_CASES {
+        println!("-------------\n{:?}", name);
+        let mut lha_reader = delharc::parse_file(format!("tests/lha_os2_208/{}", name))?;
+        for filen in 0.. {
+            assert!(filen < cases.len());
+            let (attrs, created, accessed) = cases[filen];
+            let header = lha_reader.header();
+            let ext_attrs = header.parse_ext_attrs().unwrap();
+            assert_eq!(ext_attrs.attrs, MsDosAttrs::from_bits_retain(attrs));
+            assert_eq!(ext_attrs.attrs, header.msdos_attrs);
+            assert_eq!(ext_attrs.mode, 0o660);
+            assert_eq!(ext_attrs.group, 0);
+            assert_eq!(ext_attrs.owner, 0);
+            assert_eq!(ext_attrs.parse_created().map(|t| t.to_string()).unwrap_or_default(), created);
+            assert_eq!(ext_attrs.parse_accessed().map(|t| t.to_string()).unwrap_or_default(), accessed);
+            assert!(header.parse_os2_eas().is_empty());
+            if !lha_reader.next_file().unwrap() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
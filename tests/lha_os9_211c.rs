@@ -61,3 +61,37 @@ fn test_lha_os9_211c() -> io::Result<()> {
     }
     Ok(())
 }
+
+const METADATA_CASES: &[(&str, &[(u16, u16)])] = &[
+    ("h0_lh0.lzh",    &[(0x13, 0x16)]),
+    #[cfg(feature = "lh1")]
+    ("h0_lh1.lzh",    &[(0x2B, 0x1C)]),
+    ("h0_subdir.lzh", &[(0x0B, 0x16)]),
+    ("h1_lh0.lzh",    &[(0x13, 0x16)]),
+    ("h1_subdir.lzh", &[(0x0B, 0x16)]),
+    ("h2_lh0.lzh",    &[(0x13, 0x16)]),
+    ("h2_subdir.lzh", &[(0x0B, 0x16)]),
+];
+
+#[test]
+fn test_lha_os9_211c_metadata() -> io::Result<()> {
+    for (name, entries) in METADATA_CASES {
+        println!("-------------\n{:?}", name);
+        let mut lha_reader = delharc::parse_file(format!("tests/lha_os9_211c/{}", name))?;
+        for (attrs, fd_type) in entries.iter().copied() {
+            let meta = lha_reader.header().parse_os9_metadata().unwrap();
+            assert_eq!(meta.attrs, Os9Attrs::from_bits_retain(attrs));
+            assert_eq!(meta.fd_type, Some(fd_type));
+            assert_eq!(meta.owner, None);
+            assert_eq!(meta.group, None);
+            assert!(!meta.is_directory());
+            lha_reader.next_file()?;
+        }
+    }
+    let lha_reader = delharc::parse_file("tests/lha_os9_211c/h2_lh0.lzh")?;
+    let meta = lha_reader.header().parse_os9_metadata().unwrap();
+    // ---w--wr
+    assert_eq!(meta.attrs.to_unix_permissions(), 0o622);
+    assert_eq!(meta.to_unix_mode(), 0o100622);
+    Ok(())
+}
@@ -120,3 +120,29 @@ fn test_lha_osk_201() -> io::Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_lha_osk_201_metadata() -> io::Result<()> {
+    for level in 0..=2 {
+        let name = format!("tests/lha_osk_201/h{}_subdir.lzh", level);
+        println!("-------------\n{:?}", name);
+        let mut lha_reader = delharc::parse_file(name)?;
+        for attrs in [0xffbf, 0xffbf, 0x0027] {
+            let meta = lha_reader.header().parse_os9_metadata().unwrap();
+            assert_eq!(meta.attrs, Os9Attrs::from_bits_retain(attrs));
+            assert_eq!(meta.is_directory(), attrs == 0xffbf);
+            assert_eq!(meta.owner, Some(0));
+            assert_eq!(meta.group, Some(0));
+            assert_eq!(meta.fd_type, None);
+            lha_reader.next_file()?;
+        }
+    }
+    let lha_reader = delharc::parse_file("tests/lha_osk_201/h0_subdir.lzh")?;
+    let meta = lha_reader.header().parse_os9_metadata().unwrap();
+    assert_eq!(meta.to_unix_mode(), 0o040777);
+    let lha_reader = delharc::parse_file("tests/lha_osk_201/h1_lh5.lzh")?;
+    let meta = lha_reader.header().parse_os9_metadata().unwrap();
+    // --e--ewr
+    assert_eq!(meta.to_unix_mode(), 0o100711);
+    Ok(())
+}
@@ -78,6 +78,11 @@ fn test_lha_unix114i() -> io::Result<()> {
             assert_eq!(&header.parse_pathname().to_str().unwrap(), &path1);
             let path1 = path.replace("*", "/");
             assert_eq!(&header.parse_pathname_to_str(), &path1);
+            let (link, target) = header.parse_link();
+            match path1.split_once('|') {
+                Some((path1, target1)) => assert_eq!((link.as_str(), target.as_deref()), (path1, Some(target1))),
+                None => assert_eq!((link, target), (path1, None))
+            }
             let last_modified = format!("{}", header.parse_last_modified());
             assert_eq!(&last_modified, modif);
             assert_eq!(header.file_crc, *crc16);
@@ -0,0 +1,90 @@
+use std::{fs, io::{self, Read}};
+use delharc::{LhaDecodeReader, LhaError, LhaHeader, Limits};
+
+fn limit_error(err: &io::Error) -> Option<&'static str> {
+    match err.get_ref()?.downcast_ref::<LhaError<io::Error>>()? {
+        LhaError::Limit(e) => Some(e),
+        _ => None
+    }
+}
+
+fn read_archive(data: &[u8], limits: Limits) -> io::Result<usize> {
+    let mut lha_reader = LhaDecodeReader::with_limits(data, limits)?;
+    let mut entries = 0;
+    loop {
+        entries += 1;
+        if lha_reader.is_decoder_supported() {
+            io::copy(&mut lha_reader, &mut io::sink())?;
+            lha_reader.crc_check()?;
+        }
+        if !lha_reader.next_file()? {
+            break
+        }
+    }
+    Ok(entries)
+}
+
+#[test]
+fn test_limits_header() -> io::Result<()> {
+    let data = fs::read("tests/lha_unix114i/h2_subdir.lzh")?;
+    let header = LhaHeader::read_with_limits(&mut &data[..], &Limits::default())?.unwrap();
+    assert_eq!(header.parse_pathname_to_str(), "subdir");
+    assert_eq!(read_archive(&data, Limits::default())?, 3);
+    let limits = Limits { max_header_size: 50, ..Limits::default() };
+    assert!(matches!(LhaHeader::read_with_limits(&mut &data[..], &limits).unwrap_err(),
+                     LhaError::Limit("header size")));
+    let limits = Limits { max_extra_headers: 4, ..Limits::default() };
+    assert!(matches!(LhaHeader::read_with_limits(&mut &data[..], &limits).unwrap_err(),
+                     LhaError::Limit("number of extra headers")));
+    let limits = Limits { max_extra_headers: 5, ..Limits::default() };
+    assert_eq!(read_archive(&data, limits)?, 3);
+    let limits = Limits { max_original_size: 11, ..Limits::default() };
+    let err = read_archive(&data, limits).unwrap_err();
+    assert_eq!(limit_error(&err), Some("entry original size"));
+    assert_eq!(err.to_string(), "limit exceeded: entry original size");
+    let limits = Limits { max_original_size: 12, ..Limits::default() };
+    assert_eq!(read_archive(&data, limits)?, 3);
+    Ok(())
+}
+
+#[test]
+fn test_limits_ratio() -> io::Result<()> {
+    let data = fs::read("tests/lha213/lh5_long.lzh")?;
+    let header = LhaHeader::read(&mut &data[..])?.unwrap();
+    let ratio = header.original_size / header.compressed_size;
+    assert!(ratio > 1);
+    let limits = Limits { max_ratio: ratio, ..Limits::default() };
+    let err = read_archive(&data, limits).unwrap_err();
+    assert_eq!(limit_error(&err), Some("entry compression ratio"));
+    let limits = Limits { max_ratio: ratio + 1, ..Limits::default() };
+    assert_eq!(read_archive(&data, limits)?, 1);
+    Ok(())
+}
+
+#[test]
+fn test_limits_reader() -> io::Result<()> {
+    let data = fs::read("tests/lha_unix114i/h2_subdir.lzh")?;
+    let limits = Limits { max_entries: 2, ..Limits::default() };
+    let err = read_archive(&data, limits).unwrap_err();
+    assert_eq!(limit_error(&err), Some("number of entries"));
+    let limits = Limits { max_entries: 0, ..Limits::default() };
+    let err = read_archive(&data, limits).unwrap_err();
+    assert_eq!(limit_error(&err), Some("number of entries"));
+    let limits = Limits { max_entries: 3, ..Limits::default() };
+    assert_eq!(read_archive(&data, limits)?, 3);
+
+    let data = fs::read("tests/lha213/lh5_long.lzh")?;
+    let limits = Limits { max_total_output: 1000, ..Limits::default() };
+    let mut lha_reader = LhaDecodeReader::with_limits(&data[..], limits)?;
+    let mut buf = [0u8; 600];
+    lha_reader.read_exact(&mut buf)?;
+    assert_eq!(lha_reader.total_output(), 600);
+    let err = lha_reader.read_exact(&mut buf).unwrap_err();
+    assert_eq!(limit_error(&err), Some("total output size"));
+    lha_reader.set_limits(Limits::default());
+    assert_eq!(lha_reader.limits(), &Limits::UNLIMITED);
+    io::copy(&mut lha_reader, &mut io::sink())?;
+    lha_reader.crc_check()?;
+    assert_eq!(lha_reader.total_output(), lha_reader.header().original_size);
+    Ok(())
+}
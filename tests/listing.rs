@@ -0,0 +1,84 @@
+use std::io;
+use delharc::{Listing, ListingFormat};
+
+fn list_archive(archive: &str) -> io::Result<Listing> {
+    let mut listing = Listing::new();
+    listing.set_now(None);
+    let mut lha_reader = delharc::parse_file(archive)?;
+    loop {
+        listing.push(lha_reader.header());
+        if !lha_reader.next_file()? {
+            break
+        }
+    }
+    Ok(listing)
+}
+
+fn render(listing: &Listing, format: ListingFormat) -> String {
+    let mut output = String::new();
+    listing.write(&mut output, format).unwrap();
+    output
+}
+
+#[test]
+fn test_listing() -> io::Result<()> {
+    let listing = list_archive("tests/lha_unix114i/h2_subdir.lzh")?;
+    assert_eq!(listing.entries().len(), 3);
+    let entry = &listing.entries()[2];
+    assert_eq!(entry.path, "subdir/subdir2/hello.txt");
+    assert_eq!(entry.method(), "-lh0-");
+    assert_eq!(entry.ratio_permille(), Some(1000));
+    assert_eq!(entry.os_name(), "Unix");
+    assert_eq!(entry.unix_mode, Some(0o100644));
+    assert_eq!(entry.unix_uid_gid, Some((1000, 1000)));
+    assert_eq!(listing.entries()[0].ratio_permille(), None);
+    let totals = listing.totals();
+    assert_eq!(totals.files, 3);
+    assert_eq!(totals.original_size, 12);
+    assert_eq!(totals.ratio_permille(), Some(1000));
+    assert_eq!(render(&listing, ListingFormat::Lha), "\
+PERMISSION  UID  GID      SIZE  RATIO     STAMP           NAME
+---------- ----------- ------- ------ ------------ --------------------
+drwx------  1000/1000        0 ****** Apr 24  2012 subdir
+dr-xr-xr-x  1000/1000        0 ****** Apr 24  2012 subdir/subdir2
+-rw-r--r--  1000/1000       12 100.0% Jan  1  2010 subdir/subdir2/hello.txt
+---------- ----------- ------- ------ ------------ --------------------
+ Total         3 files      12 100.0% Apr 24  2012
+");
+    Ok(())
+}
+
+#[test]
+fn test_listing_verbose() -> io::Result<()> {
+    let listing = list_archive("tests/lha_unix114i/h2_symlink3.lzh")?;
+    let output = render(&listing, ListingFormat::LhaVerbose);
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines[0],
+        "PERMISSION  UID  GID    PACKED    SIZE  RATIO METHOD CRC     STAMP          NAME");
+    assert_eq!(lines[2],
+        "lrwxrwxrwx  1000/1000        0       0 ****** -lhd- 0000 Feb  3  2013 subdir/symlink -> /absolute/path");
+    let entry = &listing.entries()[0];
+    assert_eq!(entry.path, "subdir/symlink");
+    assert_eq!(entry.link_target.as_deref(), Some("/absolute/path"));
+
+    let listing = list_archive("tests/lha_os2_208/h3_subdir.lzh")?;
+    let output = render(&listing, ListingFormat::LhaVerbose);
+    assert!(output.lines().any(|line| line ==
+        "[OS/2]                      14      14 100.0% -lh0- 3197 Dec  3  2011 subdir/subdir2/HELLO.TXT"));
+    Ok(())
+}
+
+#[test]
+fn test_listing_separated() -> io::Result<()> {
+    let listing = list_archive("tests/lha_unix114i/h2_symlink3.lzh")?;
+    assert_eq!(render(&listing, ListingFormat::Csv), "\
+path,link_target,method,compressed_size,original_size,ratio,crc,level,os,attributes,mode,uid,gid,last_modified
+subdir/symlink,/absolute/path,-lhd-,0,0,,0000,2,Unix,-----A,120777,1000,1000,2013-02-03 22:12:20
+");
+    let listing = list_archive("tests/lha_os2_208/h3_subdir.lzh")?;
+    let output = render(&listing, ListingFormat::Tsv);
+    let row: Vec<_> = output.lines().last().unwrap().split('\t').collect();
+    assert_eq!(row, ["subdir/subdir2/HELLO.TXT", "", "-lh0-", "14", "14", "100.0", "3197", "3", "OS/2",
+                     "-----A", "", "", "", "2011-12-03 21:38:50"]);
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::io::{self, Read};
+use crc_any::CRCu16;
+use delharc::macbinary::*;
+
+fn macbinary_header(name: &[u8], data_len: u32, rsrc_len: u32, version: u8) -> [u8; 128] {
+    let mut block = [0u8; 128];
+    block[1] = name.len() as u8;
+    block[2..2 + name.len()].copy_from_slice(name);
+    block[65..69].copy_from_slice(b"TEXT");
+    block[69..73].copy_from_slice(b"ttxt");
+    block[73] = 0x01;
+    block[75..77].copy_from_slice(&10u16.to_be_bytes());
+    block[77..79].copy_from_slice(&20u16.to_be_bytes());
+    block[83..87].copy_from_slice(&data_len.to_be_bytes());
+    block[87..91].copy_from_slice(&rsrc_len.to_be_bytes());
+    block[91..95].copy_from_slice(&0xB492_0000u32.to_be_bytes());
+    block[95..99].copy_from_slice(&0xB492_1000u32.to_be_bytes());
+    if version >= 2 {
+        block[101] = 0x20;
+        block[122] = 128 + version - 1;
+        block[123] = 129;
+        if version == 3 {
+            block[102..106].copy_from_slice(b"mBIN");
+        }
+        let mut crc = CRCu16::crc16xmodem();
+        crc.digest(&block[..124]);
+        block[124..126].copy_from_slice(&crc.get_crc().to_be_bytes());
+    }
+    block
+}
+
+fn macbinary_file(version: u8, data: &[u8], rsrc: &[u8]) -> Vec<u8> {
+    let mut file = macbinary_header(b"Read Me", data.len() as u32, rsrc.len() as u32, version).to_vec();
+    for fork in [data, rsrc] {
+        file.extend_from_slice(fork);
+        file.resize((file.len() + 127) / 128 * 128, 0);
+    }
+    file
+}
+
+#[test]
+fn test_macbinary() -> io::Result<()> {
+    let data = b"Hello, Macintosh!\r".repeat(10);
+    let rsrc = b"resource fork".repeat(3);
+    for (version, expected) in [(1, MacBinaryVersion::MacBinary1),
+                                (2, MacBinaryVersion::MacBinary2),
+                                (3, MacBinaryVersion::MacBinary3)]
+    {
+        let file = macbinary_file(version, &data, &rsrc);
+        let mut rd = MacBinaryReader::new(&file[..])?;
+        assert!(rd.is_macbinary());
+        let header = rd.header().unwrap();
+        assert_eq!(header.version, expected);
+        assert_eq!(header.parse_filename(), "Read Me");
+        assert_eq!(&header.finder_info.file_type, b"TEXT");
+        assert_eq!(&header.finder_info.creator, b"ttxt");
+        assert_eq!(header.finder_info.flags, if version == 1 { 0x0100 } else { 0x0120 });
+        assert_eq!(header.finder_info.location, (10, 20));
+        assert_eq!(header.parse_created().unwrap().to_string(), "1999-12-31 06:38:56");
+        assert_eq!(header.parse_modified().unwrap().to_string(), "1999-12-31 07:47:12");
+        assert_eq!(header.data_fork_offset(), 128);
+        assert_eq!(header.resource_fork_offset(), 384);
+        assert_eq!(rd.fork(), Fork::Data);
+        let mut fork = Vec::new();
+        rd.read_to_end(&mut fork)?;
+        assert_eq!(fork, data);
+        assert!(rd.next_fork()?);
+        assert_eq!(rd.fork(), Fork::Resource);
+        fork.clear();
+        rd.read_to_end(&mut fork)?;
+        assert_eq!(fork, rsrc);
+        assert!(!rd.next_fork()?);
+        assert_eq!(rd.fork(), Fork::End);
+        assert_eq!(rd.read(&mut [0u8; 16])?, 0);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_macbinary_skip_data_fork() -> io::Result<()> {
+    let file = macbinary_file(2, b"data", b"rsrc");
+    let mut rd = MacBinaryReader::new(&file[..])?;
+    assert!(rd.next_fork()?);
+    let mut fork = Vec::new();
+    rd.read_to_end(&mut fork)?;
+    assert_eq!(fork, b"rsrc");
+    // truncated stream
+    let mut rd = MacBinaryReader::new(&file[..200])?;
+    assert_eq!(rd.next_fork().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    Ok(())
+}
+
+#[test]
+fn test_macbinary_passthrough() -> io::Result<()> {
+    for len in [0, 10, 128, 1000] {
+        let content: Vec<u8> = (0..len).map(|i| (i % 251) as u8 + 1).collect();
+        let mut rd = MacBinaryReader::new(&content[..])?;
+        assert!(!rd.is_macbinary());
+        let mut data = Vec::new();
+        rd.read_to_end(&mut data)?;
+        assert_eq!(data, content);
+        assert!(!rd.next_fork()?);
+    }
+    // a corrupted MacBinary II header
+    let mut file = macbinary_file(2, b"data", b"rsrc");
+    file[125] ^= 1;
+    let rd = MacBinaryReader::new(&file[..])?;
+    assert!(!rd.is_macbinary());
+    Ok(())
+}
+
+#[test]
+fn test_macbinary_apple_double() -> io::Result<()> {
+    assert_eq!(MacBinaryHeader::apple_double_name("Read Me"), "._Read Me");
+    let file = macbinary_file(3, b"data", b"rsrc");
+    let mut rd = MacBinaryReader::new(&file[..])?;
+    let mut sidecar = Vec::new();
+    assert_eq!(rd.write_apple_double(&mut sidecar)?, 86);
+    assert_eq!(sidecar.len(), 86);
+    assert_eq!(&sidecar[..8], &[0, 5, 0x16, 7, 0, 2, 0, 0]);
+    assert_eq!(&sidecar[24..26], &[0, 2]);
+    assert_eq!(&sidecar[26..38], &[0, 0, 0, 9, 0, 0, 0, 50, 0, 0, 0, 32]);
+    assert_eq!(&sidecar[38..50], &[0, 0, 0, 2, 0, 0, 0, 82, 0, 0, 0, 4]);
+    assert_eq!(&sidecar[50..58], b"TEXTttxt");
+    assert_eq!(&sidecar[82..], b"rsrc");
+    assert!(rd.write_apple_double(&mut sidecar).is_err());
+    Ok(())
+}
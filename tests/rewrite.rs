@@ -0,0 +1,215 @@
+use std::{io::{self, Read}, fs};
+use delharc::header::*;
+use delharc::{LhaDecodeReader, LhaRewriter, LhaWriter};
+use delharc::write::convert_level;
+
+mod sink;
+use sink::SinkSum;
+
+fn list(data: &[u8]) -> io::Result<Vec<(String, u64, u32)>> {
+    let mut entries = Vec::new();
+    let mut lha_reader = LhaDecodeReader::new(data)?;
+    loop {
+        let mut sink = SinkSum::new();
+        let path = lha_reader.header().parse_pathname_to_str();
+        if lha_reader.is_decoder_supported() {
+            io::copy(&mut lha_reader, &mut sink)?;
+            lha_reader.crc_check()?;
+        }
+        entries.push((path, sink.length, sink.crc32.get_crc()));
+        if !lha_reader.next_file()? {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+#[test]
+fn test_rewrite_keep() -> io::Result<()> {
+    for name in ["tests/lha_unix114i/h0_subdir.lzh", "tests/lha_unix114i/h1_subdir.lzh",
+                 "tests/lha_unix114i/h2_subdir.lzh", "tests/lha_osk_201/h2_subdir.lzh",
+                 "tests/lhmelt_16536/h1_lh1.lzh", "tests/lha213/lh5_long.lzh"]
+    {
+        println!("-------------\n{:?}", name);
+        let data = fs::read(name)?;
+        let (rd, output) = LhaRewriter::new(&data[..], Vec::new())?.finish()?;
+        assert_eq!(&output[..], &data[..output.len()]);
+        assert_eq!(rd.len(), data.len() - output.len());
+        assert_eq!(list(&output)?, list(&data)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_rewrite_edit() -> io::Result<()> {
+    const CONTENT: &[u8] = b"Hello, world!\n";
+    let mut sink = SinkSum::new();
+    io::copy(&mut &CONTENT[..], &mut sink)?;
+    let crc = sink.crc16.get_crc();
+    for level in 1..=2 {
+        let name = format!("tests/lha_unix114i/h{}_subdir.lzh", level);
+        println!("-------------\n{:?}", name);
+        let data = fs::read(name)?;
+        let mut rewriter = LhaRewriter::new(&data[..], Vec::new())?;
+        let new_header = LhaHeaderBuilder::new()
+            .level(level)
+            .path("new.txt")
+            .sizes(CONTENT.len() as u64, CONTENT.len() as u64)
+            .file_crc(crc)
+            .os_type(OsType::Unix)
+            .unix_permissions(0o100644)
+            .build()?;
+        rewriter.append(&new_header, CONTENT)?;
+        while let Some(header) = rewriter.header() {
+            match header.parse_pathname_to_str().as_str() {
+                "subdir" => rewriter.keep()?,
+                "subdir/subdir2" => rewriter.delete()?,
+                "subdir/subdir2/hello.txt" => {
+                    let header = LhaHeaderBuilder::from_header(header)
+                        .path("a/much/longer/directory/path/hello_world.txt")
+                        .comment("greetings")
+                        .build()?;
+                    rewriter.keep_with_header(&header)?
+                }
+                path => panic!("unexpected entry: {}", path)
+            }
+        }
+        assert!(rewriter.keep().is_err());
+        let (rd, output) = rewriter.finish()?;
+        assert!(rd.len() <= 1);
+        assert_eq!(output.last(), Some(&0));
+        let original = list(&data)?;
+        assert_eq!(list(&output)?, [
+            ("new.txt".to_string(), CONTENT.len() as u64, sink.crc32.get_crc()),
+            original[0].clone(),
+            ("a/much/longer/directory/path/hello_world.txt".to_string(), original[2].1, original[2].2)
+        ]);
+        let mut lha_reader = LhaDecodeReader::new(&output[..])?;
+        let mut content = Vec::new();
+        lha_reader.read_to_end(&mut content)?;
+        assert_eq!(content, CONTENT);
+        assert!(lha_reader.next_file()?);
+        assert!(lha_reader.next_file()?);
+        assert_eq!(lha_reader.header().parse_comment().unwrap(), "greetings");
+        assert!(!lha_reader.next_file()?);
+    }
+    Ok(())
+}
+
+fn headers(mut data: &[u8]) -> io::Result<Vec<LhaHeader>> {
+    let mut headers = Vec::new();
+    while let Some(header) = LhaHeader::read(&mut data)? {
+        data = &data[header.compressed_size as usize..];
+        headers.push(header);
+    }
+    Ok(headers)
+}
+
+#[test]
+fn test_convert_level() -> io::Result<()> {
+    for name in ["tests/lha_unix114i/h0_subdir.lzh", "tests/lha_unix114i/h0_lh5.lzh",
+                 "tests/lha_unix114i/h1_subdir.lzh", "tests/lha_unix114i/h1_symlink.lzh",
+                 "tests/lhmelt_16536/h0_lh1.lzh", "tests/lhmelt_16536/h1_subdir.lzh",
+                 "tests/lha_amiga_122/level0.lzh", "tests/lha_amiga_122/level1.lzh",
+                 "tests/lha_os9_211c/h0_subdir.lzh"]
+    {
+        println!("-------------\n{:?}", name);
+        let data = fs::read(name)?;
+        let source = headers(&data)?;
+        for level in 2..=3 {
+            let (_, output) = convert_level(&data[..], Vec::new(), level)?;
+            assert_eq!(list(&output)?, list(&data)?);
+            let converted = headers(&output)?;
+            assert_eq!(converted.len(), source.len());
+            for (conv, orig) in converted.iter().zip(source.iter()) {
+                assert_eq!(conv.level, level);
+                assert_eq!(conv.parse_pathname(), orig.parse_pathname());
+                assert_eq!(conv.compression, orig.compression);
+                assert_eq!(conv.compressed_size, orig.compressed_size);
+                assert_eq!(conv.original_size, orig.original_size);
+                assert_eq!(conv.file_crc, orig.file_crc);
+                assert_eq!(conv.os_type, orig.os_type);
+                assert_eq!(conv.msdos_attrs, orig.msdos_attrs);
+                assert_eq!(conv.parse_comment(), orig.parse_comment());
+                assert_eq!(conv.parse_last_modified().to_naive_utc(), orig.parse_last_modified().to_naive_utc());
+                if orig.level == 0 && orig.os_type == b'U' && orig.extended_area.len() >= 11 {
+                    // Unix permissions, uid and gid from the level 0 extended area
+                    let area = &orig.extended_area;
+                    assert!(conv.iter_extra().any(|h| h[0] == 0x50 && h[1..] == area[5..7]));
+                    assert!(conv.iter_extra().any(|h| h[0] == 0x51 && h[1..] == [area[9], area[10], area[7], area[8]]));
+                }
+            }
+            // converting to the same level copies the archive verbatim
+            let (_, again) = convert_level(&output[..], Vec::new(), level)?;
+            assert_eq!(again, output);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_convert_level_os9() -> io::Result<()> {
+    for name in ["tests/lha_os9_211c/h0_lh0.lzh", "tests/lha_os9_211c/h0_subdir.lzh",
+                 "tests/lha_os9_211c/h1_lh1.lzh", "tests/lha_osk_201/h0_lh5.lzh",
+                 "tests/lha_osk_201/h0_subdir.lzh", "tests/lha_osk_201/h1_lh5.lzh"]
+    {
+        let data = fs::read(name)?;
+        let source = headers(&data)?;
+        for level in 2..=3 {
+            let (_, output) = convert_level(&data[..], Vec::new(), level)?;
+            // and back to the level 1
+            let (_, back) = convert_level(&output[..], Vec::new(), 1)?;
+            for archive in [&output, &back] {
+                assert_eq!(list(archive)?, list(&data)?);
+                let converted = headers(archive)?;
+                assert_eq!(converted.len(), source.len());
+                for (conv, orig) in converted.iter().zip(source.iter()) {
+                    assert!(orig.parse_os9_metadata().is_some());
+                    assert_eq!(conv.parse_os9_metadata(), orig.parse_os9_metadata(), "{}", name);
+                    assert_eq!(conv.parse_pathname(), orig.parse_pathname());
+                    assert_eq!(conv.parse_last_modified().to_naive_utc(), orig.parse_last_modified().to_naive_utc());
+                }
+            }
+        }
+    }
+    // the extended area which can't be converted
+    let content = b"content";
+    let header = LhaHeaderBuilder::new()
+        .level(0)
+        .path("file")
+        .sizes(content.len() as u64, content.len() as u64)
+        .raw_os_type(b'M')
+        .extended_area([1, 2, 3])
+        .build()?;
+    let mut writer = LhaWriter::new(Vec::new());
+    writer.write_entry(&header, &content[..])?;
+    let data = writer.finish()?;
+    assert_eq!(convert_level(&data[..], Vec::new(), 2).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    let (_, output) = convert_level(&data[..], Vec::new(), 1)?;
+    assert_eq!(&headers(&output)?[0].extended_area[..], &[1, 2, 3]);
+    Ok(())
+}
+
+#[test]
+fn test_rewrite_rename_and_errors() -> io::Result<()> {
+    let data = fs::read("tests/lha_unix114i/h1_lh5.lzh")?;
+    let mut rewriter = LhaRewriter::new(&data[..], Vec::new())?;
+    let mut header = rewriter.header().unwrap().clone();
+    header.compressed_size += 1;
+    assert_eq!(rewriter.keep_with_header(&header).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    rewriter.rename("LICENSE/gpl-2.0.txt")?;
+    assert!(rewriter.header().is_none());
+    let (_, output) = rewriter.finish()?;
+    let entries = list(&output)?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].0, "LICENSE/gpl-2.0.txt");
+    assert_eq!(&entries[0].1, &list(&data)?[0].1);
+    // the payload is too short
+    let header = LhaHeaderBuilder::new().sizes(10, 10).build()?;
+    let mut writer = LhaWriter::new(Vec::new());
+    assert_eq!(writer.write_entry(&header, &b"12345"[..]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    // truncated source
+    let mut rewriter = LhaRewriter::new(&data[..data.len() - 100], Vec::new())?;
+    assert_eq!(rewriter.keep().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    Ok(())
+}
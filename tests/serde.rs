@@ -0,0 +1,106 @@
+use std::io;
+use delharc::{CompressionMethod, HeaderManifest, LhaHeader, OsType, TimestampResult};
+
+fn headers(archive: &str) -> io::Result<Vec<LhaHeader>> {
+    let mut headers = Vec::new();
+    let mut lha_reader = delharc::parse_file(archive)?;
+    loop {
+        headers.push(lha_reader.header().clone());
+        if !lha_reader.next_file()? {
+            break
+        }
+    }
+    Ok(headers)
+}
+
+#[test]
+fn test_serde_header() -> io::Result<()> {
+    for archive in ["tests/lha_unix114i/h0_subdir.lzh", "tests/lha_unix114i/h2_symlink3.lzh",
+                    "tests/lha_os2_208/h3_subdir.lzh", "tests/lha_amiga_122/subdir.lzh"] {
+        for header in headers(archive)? {
+            let json = serde_json::to_string(&header)?;
+            let decoded: LhaHeader = serde_json::from_str(&json)?;
+            assert_eq!(decoded.level, header.level);
+            assert_eq!(decoded.compression, header.compression);
+            assert_eq!(decoded.compressed_size, header.compressed_size);
+            assert_eq!(decoded.original_size, header.original_size);
+            assert_eq!(decoded.filename, header.filename);
+            assert_eq!(decoded.msdos_attrs, header.msdos_attrs);
+            assert_eq!(decoded.last_modified, header.last_modified);
+            assert_eq!(decoded.os_type, header.os_type);
+            assert_eq!(decoded.file_crc, header.file_crc);
+            assert_eq!(decoded.extended_area, header.extended_area);
+            assert_eq!(decoded.first_header_len, header.first_header_len);
+            assert_eq!(decoded.extra_headers, header.extra_headers);
+            assert_eq!(serde_json::to_string(&decoded)?, json);
+            assert_eq!(decoded.manifest(), header.manifest());
+        }
+    }
+    let header = LhaHeader { filename: b"a\xff\0b"[..].into(), ..Default::default() };
+    let json = serde_json::to_value(&header)?;
+    assert_eq!(json["filename"], serde_json::json!([97, 255, 0, 98]));
+    assert_eq!(json["msdos_attrs"], "ARCHIVE");
+    Ok(())
+}
+
+#[test]
+fn test_serde_malformed_header() -> io::Result<()> {
+    let header = headers("tests/lha_unix114i/h2_symlink3.lzh")?.remove(0);
+    let count = header.iter_extra().count();
+    assert!(count > 2);
+    let json = serde_json::to_value(&header)?;
+    let len = header.extra_headers.len();
+    let first = header.first_header_len as usize;
+    let malformed = [
+        ("first_header_len", serde_json::json!(len + 1)),
+        ("first_header_len", serde_json::json!(1)),
+        ("first_header_len", serde_json::json!(u32::MAX)),
+        ("extra_headers", serde_json::json!(&header.extra_headers[..first - 1])),
+        ("extra_headers", serde_json::json!(&header.extra_headers[..len - 1])),
+        ("level", serde_json::json!(3)),
+    ];
+    for (field, value) in malformed {
+        let mut json = json.clone();
+        json[field] = value;
+        let decoded: LhaHeader = serde_json::from_value(json)?;
+        // the inconsistent extra headers are not iterated past the available data
+        assert!(decoded.iter_extra().count() <= count);
+        let _ = decoded.manifest();
+        let _ = decoded.to_bytes();
+        let _ = decoded.parse_pathname();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_serde_types() -> io::Result<()> {
+    assert_eq!(serde_json::to_string(&CompressionMethod::Lh5)?, r#""Lh5""#);
+    assert_eq!(serde_json::to_string(&OsType::Amiga)?, r#""Amiga""#);
+    let timestamp = TimestampResult::Utc(chrono::DateTime::from_timestamp(1359929540, 0).unwrap());
+    let json = serde_json::to_string(&timestamp)?;
+    assert_eq!(json, r#"{"Utc":"2013-02-03T22:12:20Z"}"#);
+    assert_eq!(serde_json::from_str::<TimestampResult>(&json)?, timestamp);
+    assert_eq!(serde_json::from_str::<TimestampResult>(r#""None""#)?, TimestampResult::None);
+    Ok(())
+}
+
+#[test]
+fn test_serde_manifest() -> io::Result<()> {
+    let headers = headers("tests/lha_unix114i/h2_symlink3.lzh")?;
+    let manifest = headers[0].manifest();
+    assert_eq!(manifest.path, "subdir/symlink");
+    assert_eq!(manifest.link_target.as_deref(), Some("/absolute/path"));
+    assert_eq!(manifest.compression, Some(CompressionMethod::Lhd));
+    assert_eq!(manifest.os_type, Some(OsType::Unix));
+    assert_eq!(manifest.unix_mode, Some(0o120777));
+    assert!(manifest.is_directory);
+    let json = serde_json::to_value(&manifest)?;
+    assert_eq!(json["path"], "subdir/symlink");
+    assert_eq!(json["method"], "-lhd-");
+    assert_eq!(json["os_type"], "Unix");
+    assert_eq!(json["last_modified"]["Utc"], "2013-02-03T22:12:20Z");
+    assert_eq!(json["unix_uid_gid"], serde_json::json!([1000, 1000]));
+    let decoded: HeaderManifest = serde_json::from_value(json)?;
+    assert_eq!(decoded, manifest);
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use std::{fs, io, path::Path};
+use delharc::decode::{BoxedDecoder, Decoder, DecoderAny, DecoderRegistry, WorkspacePool};
+use delharc::crc::Crc16;
+use delharc::{LhaError, LhaHeader, LhaHeaderRef, LhaSliceArchive, LhaSliceEntry, Limits};
+
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = Crc16::default();
+    crc.digest(data);
+    crc.sum16()
+}
+
+fn archives() -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut archives = Vec::new();
+    for dir in fs::read_dir("tests")? {
+        let dir = dir?.path();
+        if !dir.is_dir() {
+            continue
+        }
+        for file in fs::read_dir(&dir)? {
+            let path = file?.path();
+            if let Some("lzh"|"lzs"|"lha") = path.extension().and_then(|ext| ext.to_str()) {
+                let data = fs::read(&path)?;
+                archives.push((path.display().to_string(), data));
+            }
+        }
+    }
+    archives.sort();
+    Ok(archives)
+}
+
+fn read_headers(mut data: &[u8]) -> (Vec<LhaHeader>, bool) {
+    let mut headers = Vec::new();
+    loop {
+        match LhaHeader::read(&mut data) {
+            Ok(Some(header)) => {
+                data = match data.get(header.compressed_size as usize..) {
+                    Some(rest) => rest,
+                    None => return (headers, false)
+                };
+                headers.push(header);
+            }
+            Ok(None) => return (headers, true),
+            Err(_) => return (headers, false)
+        }
+    }
+}
+
+#[test]
+fn test_slice_headers() -> io::Result<()> {
+    let archives = archives()?;
+    assert!(archives.len() > 50);
+    for (name, data) in archives.iter() {
+        let (headers, ok) = read_headers(data);
+        let archive = LhaSliceArchive::new(data);
+        let entries: Vec<_> = archive.entries().collect();
+        assert_eq!(entries.len(), headers.len() + usize::from(!ok), "{}", name);
+        assert_eq!(entries.iter().all(Result::is_ok), ok, "{}", name);
+        for (header, entry) in headers.iter().zip(entries.iter()) {
+            let entry = entry.as_ref().unwrap();
+            assert_eq!(entry.header.to_header().as_header_ref(), header.as_header_ref(), "{}", name);
+            assert_eq!(entry.header.parse_pathname(), header.parse_pathname());
+            assert_eq!(entry.header.parse_last_modified(), header.parse_last_modified());
+            assert_eq!(entry.data.len() as u64, header.compressed_size);
+            assert_eq!(&data[entry.data_offset..entry.next_offset()], entry.data);
+            assert_eq!(archive.entry_at(entry.offset).unwrap(), Some(*entry));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_slice_decode() -> io::Result<()> {
+    let data = fs::read("tests/lha_unix114i/h2_subdir.lzh")?;
+    let archive = LhaSliceArchive::new(&data);
+    let mut count = 0;
+    for entry in archive.entries() {
+        let entry = entry.unwrap();
+        assert!(!entry.header.extra_headers.is_empty());
+        if entry.header.is_directory() {
+            continue
+        }
+        let mut decoder = entry.decoder();
+        assert!(decoder.is_supported());
+        let mut buf = vec![0u8; entry.header.original_size as usize];
+        decoder.fill_buffer(&mut buf).unwrap();
+        assert_eq!(crc16(&buf), entry.header.file_crc);
+        count += 1;
+    }
+    assert_eq!(count, 1);
+
+    let mut pool = WorkspacePool::new();
+    let names = ["tests/lha213/lh5_long.lzh", "tests/lha_unix114i/h0_lh7.lzh", "tests/lengths/lz5-64k.lzs"];
+    for name in names.into_iter().filter(|name| cfg!(feature = "lz") || !name.ends_with(".lzs")) {
+        let data = fs::read(name)?;
+        let entry = LhaSliceArchive::new(&data).entry_at(0).unwrap().unwrap();
+        let mut buf = vec![0u8; entry.header.original_size as usize];
+        entry.decoder().fill_buffer(&mut buf).unwrap();
+        assert_eq!(crc16(&buf), entry.header.file_crc, "{}", name);
+        let mut out = vec![0u8; buf.len()];
+        entry.decode_to_slice(&mut out).unwrap();
+        assert_eq!(out, buf, "{}", name);
+        out.fill(0);
+        entry.decode_to_slice_in(&mut out, &mut pool, &DecoderRegistry::new()).unwrap();
+        assert_eq!(out, buf, "{}", name);
+        assert!(matches!(entry.decode_to_slice(&mut out[1..]), Err(LhaError::Decompress(..))));
+        let header = LhaHeaderRef { file_crc: !entry.header.file_crc, ..entry.header };
+        let corrupted = LhaSliceEntry { header, ..entry };
+        assert!(matches!(corrupted.decode_to_slice(&mut out), Err(LhaError::Checksum(..))));
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct InvertDecoder<'a> {
+    inner: &'a [u8]
+}
+
+impl<'a> Decoder<&'a [u8]> for InvertDecoder<'a> {
+    type Error = io::Error;
+
+    fn into_inner(self) -> &'a [u8] {
+        self.inner
+    }
+
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> Result<(), LhaError<Self::Error>> {
+        io::Read::read_exact(&mut self.inner, buf).map_err(LhaError::Io)?;
+        buf.iter_mut().for_each(|b| *b = !*b);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_slice_decoder_registry() -> io::Result<()> {
+    let data: &'static [u8] = fs::read("tests/lha213/lh0.lzh")?.leak();
+    let entry = LhaSliceArchive::new(data).entry_at(0).unwrap().unwrap();
+    let mut expected = vec![0u8; entry.header.original_size as usize];
+    entry.decode_to_slice(&mut expected).unwrap();
+    let mut registry = DecoderRegistry::new();
+    assert!(!registry.register(*b"-lh0-", |_: &LhaHeader, rd| -> BoxedDecoder<_> {
+        Box::new(InvertDecoder { inner: rd })
+    }));
+    let mut pool = WorkspacePool::new();
+    let mut decoder = entry.decoder_in(&mut pool, &registry);
+    assert!(matches!(decoder, DecoderAny::BoxedDecoder(..)));
+    let mut out = vec![0u8; expected.len()];
+    decoder.fill_buffer(&mut out).unwrap();
+    assert!(out.iter().zip(&expected).all(|(a, b)| *a == !*b));
+    assert!(decoder.into_inner_in(&mut pool).is_empty());
+    // the registered decoder is used to decode to slice as well
+    assert!(matches!(entry.decode_to_slice_in(&mut out, &mut pool, &registry), Err(LhaError::Checksum(..))));
+    entry.decode_to_slice_in(&mut out, &mut pool, &DecoderRegistry::new()).unwrap();
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_slice_read_header() -> io::Result<()> {
+    let data = fs::read(Path::new("tests/lha_unix114i/h1_lh5.lzh"))?;
+    let mut rest = &data[..];
+    let header = LhaHeaderRef::read(&mut rest).unwrap().unwrap();
+    assert_eq!(header.level, 1);
+    assert_eq!(header.parse_pathname_to_str(), "gpl-2");
+    assert_eq!(data.len() - rest.len() + header.compressed_size as usize + 1, data.len());
+    let mut end = &rest[header.compressed_size as usize..];
+    assert_eq!(LhaHeaderRef::read(&mut end).unwrap(), None);
+    assert!(end.is_empty());
+    assert_eq!(LhaHeaderRef::read(&mut end).unwrap(), None);
+    // truncated header is an error and leaves the input unchanged
+    for len in 1..data.len() - rest.len() {
+        let mut truncated = &data[..len];
+        assert!(matches!(LhaHeaderRef::read(&mut truncated).unwrap_err(), LhaError::Io(..)));
+        assert_eq!(truncated.len(), len);
+    }
+    // truncated data
+    let archive = LhaSliceArchive::new(&data[..data.len() - 2]);
+    let mut entries = archive.entries();
+    assert!(matches!(entries.next(), Some(Err(LhaError::Io(..)))));
+    assert!(entries.next().is_none());
+    assert!(matches!(LhaSliceArchive::new(&data).entry_at(data.len() + 1), Err(LhaError::Io(..))));
+    // limits
+    let limits = Limits { max_header_size: 20, ..Limits::default() };
+    assert!(matches!(LhaSliceArchive::with_limits(&data, limits).entry_at(0),
+                     Err(LhaError::Limit("header size"))));
+    Ok(())
+}
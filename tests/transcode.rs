@@ -0,0 +1,146 @@
+use std::io::{self, Read, Write};
+#[cfg(feature = "lh1")]
+use std::fs;
+use delharc::header::*;
+use delharc::decode::{Decoder, DecoderAny};
+use delharc::encode::EncoderAny;
+#[cfg(all(feature = "lh1", feature = "lz"))]
+use delharc::write::transcode_legacy;
+use delharc::{LhaDecodeReader, LhaRewriter};
+
+mod sink;
+use sink::SinkSum;
+
+type Entry = (String, [u8;5], u64, u32);
+
+fn list(data: &[u8]) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut lha_reader = LhaDecodeReader::new(data)?;
+    loop {
+        let mut sink = SinkSum::new();
+        let header = lha_reader.header();
+        let (path, compression) = (header.parse_pathname_to_str(), header.compression);
+        if lha_reader.is_decoder_supported() {
+            io::copy(&mut lha_reader, &mut sink)?;
+            lha_reader.crc_check()?;
+        }
+        entries.push((path, compression, sink.length, sink.crc32.get_crc()));
+        if !lha_reader.next_file()? {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(all(feature = "lh1", feature = "lz"))]
+#[test]
+fn test_transcode_legacy() -> io::Result<()> {
+    for name in ["tests/lharc113/lh1.lzh", "tests/lharc113/long.lzh", "tests/lhmelt_16536/h0_lh1.lzh",
+                 "tests/lha_amiga_122/lh1.lzh", "tests/lhmelt_16536/h2_lh1.lzh", "tests/larc333/lz5.lzs",
+                 "tests/larc333/long.lzs", "tests/lzs/lzs.lzs", "tests/lzs/long.lzs",
+                 "tests/lharc_atari_313a/h1_lz5.lzh", "tests/lharc_atari_313a/h2_subdir.lzh"]
+    {
+        println!("-------------\n{:?}", name);
+        let data = fs::read(name)?;
+        let original = list(&data)?;
+        for method in [CompressionMethod::Lh5, CompressionMethod::Lh7] {
+            let (_, output) = transcode_legacy(&data[..], Vec::new(), method)?;
+            let transcoded = list(&output)?;
+            assert_eq!(transcoded.len(), original.len());
+            for (conv, orig) in transcoded.iter().zip(original.iter()) {
+                assert_eq!((&conv.0, conv.2, conv.3), (&orig.0, orig.2, orig.3));
+                match &orig.1 {
+                    b"-lh1-"|b"-lzs-"|b"-lz5-" => assert_eq!(&conv.1, method.as_identifier()),
+                    compression => assert_eq!(&conv.1, compression)
+                }
+            }
+            let mut rd = &output[..];
+            let mut src = &data[..];
+            while let Some(header) = LhaHeader::read(&mut rd)? {
+                let orig = LhaHeader::read(&mut src)?.unwrap();
+                assert_eq!(header.level, orig.level);
+                // the header is rebuilt, so only the order of the extra headers may change
+                let extra = |header: &LhaHeader| {
+                    let mut extra: Vec<Vec<u8>> = header.iter_extra().filter(|h| h[0] != 0x00)
+                                                        .map(Into::into).collect();
+                    extra.sort();
+                    extra
+                };
+                assert_eq!(extra(&header), extra(&orig));
+                assert_eq!(header.parse_last_modified(), orig.parse_last_modified());
+                rd = &rd[header.compressed_size as usize..];
+                src = &src[orig.compressed_size as usize..];
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "lh1")]
+#[test]
+fn test_transcode_errors() -> io::Result<()> {
+    let data = fs::read("tests/lharc113/lh1.lzh")?;
+    let mut rewriter = LhaRewriter::new(&data[..], Vec::new())?;
+    assert_eq!(rewriter.transcode(CompressionMethod::Lh1).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    // corrupted file checksum
+    let mut header = rewriter.header().unwrap().clone();
+    header.file_crc ^= 1;
+    rewriter.keep_with_header(&header)?;
+    let (_, bad) = rewriter.finish()?;
+    let mut rewriter = LhaRewriter::new(&bad[..], Vec::new())?;
+    assert_eq!(rewriter.transcode(CompressionMethod::Lh5).unwrap_err().to_string(), "crc16 mismatch");
+    Ok(())
+}
+
+#[test]
+fn test_transcode_size_header() -> io::Result<()> {
+    let mut content = Vec::new();
+    delharc::parse_file("tests/lha213/lh5_long.lzh")?.read_to_end(&mut content)?;
+    let mut crc = delharc::crc::Crc16::default();
+    crc.digest(&content);
+    let size = content.len() as u64;
+    let mut sizes = size.to_le_bytes().to_vec();
+    sizes.extend_from_slice(&size.to_le_bytes());
+    let header = LhaHeaderBuilder::new()
+        .path("long.txt")
+        .sizes(size, size)
+        .file_crc(crc.sum16())
+        .extra_header(0x42, &sizes)
+        .build().unwrap();
+    assert!(header.iter_extra().any(|h| h[0] == 0x42));
+    let mut writer = delharc::LhaWriter::new(Vec::new());
+    writer.write_entry(&header, &content[..])?;
+    let data = writer.finish()?;
+    let mut rewriter = LhaRewriter::new(&data[..], Vec::new())?;
+    rewriter.transcode(CompressionMethod::Lh5)?;
+    let (_, output) = rewriter.finish()?;
+    let mut rd = &output[..];
+    let header = LhaHeader::read(&mut rd)?.unwrap();
+    assert_eq!(header.compression_method(), Ok(CompressionMethod::Lh5));
+    assert!(header.compressed_size < size);
+    assert_eq!(header.original_size, size);
+    assert_eq!(rd.len() as u64, header.compressed_size + 1);
+    assert_eq!(list(&output)?, [("long.txt".into(), *b"-lh5-", size, list(&data)?[0].3)]);
+    Ok(())
+}
+
+#[test]
+fn test_encoder_any() -> io::Result<()> {
+    let mut content = Vec::new();
+    delharc::parse_file("tests/lha213/lh5_long.lzh")?.read_to_end(&mut content)?;
+    for method in [CompressionMethod::Lh0, CompressionMethod::Lh5, CompressionMethod::Lh6, CompressionMethod::Lh7] {
+        assert!(EncoderAny::<Vec<u8>>::is_supported(method));
+        let mut encoder = EncoderAny::new_from_compression(method, Vec::new()).unwrap();
+        encoder.write_all(&content)?;
+        let encoded = encoder.finish()?;
+        println!("{:?}: {} -> {}", method, content.len(), encoded.len());
+        if method != CompressionMethod::Lh0 {
+            assert!(encoded.len() < content.len());
+        }
+        let mut decoded = vec![0u8; content.len()];
+        DecoderAny::new_from_compression(method, &encoded[..]).fill_buffer(&mut decoded)?;
+        assert!(decoded == content);
+    }
+    assert!(EncoderAny::new_from_compression(CompressionMethod::Lh1, Vec::new()).is_none());
+    Ok(())
+}